@@ -1,5 +1,39 @@
+//SETTINGS
+/// Nastavení závislé na hardwarové sestavě (databáze, mount root, LED,
+/// websocket, šachty), viz `settings`.
+pub const SETTINGS_PATH: &str = "/etc/cratec/cratec.toml";
+
 //DATABASE
-pub const DATABASE_PATH: &str = "/var/lib/cratec/database.db";
+pub fn database_path() -> &'static str {
+    &crate::settings::settings().database.path
+}
+
+/// Spool výstupu běžících procesů pro dávkový zápis do `process_log_lines`.
+pub const PROCESS_LOG_SPOOL_DIR: &str = "/var/lib/cratec/spool";
 
 //MOUNT
-pub const MOUNT_ROOT: &str = "/mnt/cratec";
\ No newline at end of file
+pub fn mount_root() -> &'static str {
+    &crate::settings::settings().storage.mount_root
+}
+
+//REPORT
+/// Offline bundle tectonic (fonty, balíčky LaTeXu) instalovaný s aplikací.
+/// V terénu se nic nestahuje; chybějící bundle se hlásí při startu.
+pub const TECTONIC_BUNDLE: &str = "/usr/share/cratec/tectonic/bundle.zip";
+
+/// Cache zkompilovaného formátu LaTeXu (vytvoří se při prvním překladu).
+pub const TECTONIC_FORMAT_CACHE_DIR: &str = "/var/lib/cratec/tectonic/formats";
+
+/// Složka s operátorskými šablonami reportu (`en_ewf.tex`, `en_dd.tex`, loga),
+/// nastavuje se v `[report]`.
+pub fn report_template_dir() -> &'static str {
+    &crate::settings::settings().report.template_dir
+}
+
+/// Maximální velikost chybového logu vloženého do přílohy reportu.
+pub const REPORT_ERROR_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+//ADMIN
+/// SHA-256 (hex) servisního kódu pro administrátorské akce. Bez souboru jsou
+/// administrátorské akce zakázané.
+pub const ADMIN_CODE_HASH_PATH: &str = "/etc/cratec/admin_code.sha256";
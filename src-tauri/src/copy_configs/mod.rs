@@ -1,8 +1,15 @@
 use rusqlite::{Connection, Result, ToSql};
-use crate::config::DATABASE_PATH;
+use crate::config::database_path;
+use policy::FieldPolicies;
+use validation::{validate_dd_config, validate_ewf_config, ConfigError};
+
+pub mod policy;
+pub mod presets;
+pub mod validation;
+pub mod versions;
 
 /// **Struktura pro vkládání nového záznamu (bez ID) pro EWF konfiguraci**
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct NewConfig {
     pub confname: String,
     pub codepage: String,
@@ -22,6 +29,9 @@ pub struct NewConfig {
     pub segment_size: String,
     pub zero_on_read_error: bool,
     pub use_chunk_data: bool,
+    /// Pevná / zadávaná pole, viz `policy`
+    #[serde(default)]
+    pub field_policy: FieldPolicies,
 }
 
 /// **Struktura pro načítání záznamu z databáze (s ID) pro EWF konfiguraci**
@@ -47,6 +57,8 @@ pub struct StoredEWFConfig {
     pub zero_on_read_error: bool,
     pub use_chunk_data: bool,
     pub created: String,
+    pub version: i64,
+    pub field_policy: FieldPolicies,
 }
 
 /// **Uložení nové EWF konfigurace do databáze**
@@ -70,6 +82,7 @@ pub fn save_ewf_config(conn: &Connection, config: NewConfig) -> Result<()> {
         &config.segment_size,
         &config.zero_on_read_error,
         &config.use_chunk_data,
+        &config.field_policy,
     ];
     conn.execute(
         r#"INSERT INTO ewf_config (
@@ -90,8 +103,9 @@ pub fn save_ewf_config(conn: &Connection, config: NewConfig) -> Result<()> {
             swap_byte_pairs,
             segment_size,
             zero_on_read_error,
-            use_chunk_data
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)"#,
+            use_chunk_data,
+            field_policy
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)"#,
         params.as_slice(),
     )?;
     Ok(())
@@ -117,7 +131,8 @@ pub async fn save_new_ewf_config(
     segment_size: String,
     zero_on_read_error: bool,
     use_chunk_data: bool,
-) -> Result<(), String> {
+    field_policy: Option<FieldPolicies>,
+) -> Result<(), ConfigError> {
     let config = NewConfig {
         confname,
         codepage,
@@ -137,7 +152,9 @@ pub async fn save_new_ewf_config(
         segment_size,
         zero_on_read_error,
         use_chunk_data,
+        field_policy: field_policy.unwrap_or_default(),
     };
+    validate_ewf_config(&config)?;
 
     let db_conn = crate::db::DB_CONN.clone();
 
@@ -152,7 +169,7 @@ pub async fn save_new_ewf_config(
 }
 
 /// **Upravená struktura pro vkládání nové DCFLDD konfigurace**
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct NewDDConfig {
     pub confname: String,
     pub format: String,
@@ -164,6 +181,9 @@ pub struct NewDDConfig {
     pub vf: i32,
     pub diffwr: i32,
     pub notes: String,
+    /// Pevná / zadávaná pole, viz `policy`
+    #[serde(default)]
+    pub field_policy: FieldPolicies,
 }
 
 /// **Synchronní funkce pro uložení DCFLDD konfigurace do databáze**
@@ -179,6 +199,7 @@ pub fn save_dd_config(conn: &Connection, config: NewDDConfig) -> Result<()> {
         &config.vf,
         &config.diffwr,
         &config.notes,
+        &config.field_policy,
     ];
     conn.execute(
         r#"INSERT INTO dd_config (
@@ -191,8 +212,9 @@ pub fn save_dd_config(conn: &Connection, config: NewDDConfig) -> Result<()> {
             split,
             vf,
             diffwr,
-            notes
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"#,
+            notes,
+            field_policy
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"#,
         params.as_slice(),
     )?;
     Ok(())
@@ -213,7 +235,8 @@ pub async fn save_new_dd_config(
     vf: String,
     diffwr: String,
     notes: String,
-) -> Result<(), String> {
+    field_policy: Option<FieldPolicies>,
+) -> Result<(), ConfigError> {
     let vf_parsed = if vf == "on" { 1 } else { 0 };
     let diffwr_parsed = if diffwr == "on" { 1 } else { 0 };
 
@@ -240,7 +263,9 @@ pub async fn save_new_dd_config(
         vf: vf_parsed,
         diffwr: diffwr_parsed,
         notes,
+        field_policy: field_policy.unwrap_or_default(),
     };
+    validate_dd_config(&config)?;
 
     let db_conn = crate::db::DB_CONN.clone();
     let conn = db_conn.lock().await;
@@ -265,6 +290,8 @@ pub struct StoredDDConfig {
     pub vf: bool,
     pub diffwr: bool,
     pub notes: String,
+    pub version: i64,
+    pub field_policy: FieldPolicies,
 }
 
 /// **Struktura pro vrácení kombinovaných konfigurací**
@@ -297,7 +324,9 @@ pub fn get_all_configs(conn: &Connection) -> Result<CombinedConfigs> {
             segment_size,
             zero_on_read_error,
             use_chunk_data,
-            created
+            created,
+            version,
+            field_policy
          FROM ewf_config
          WHERE active = true"#,
     )?;
@@ -323,6 +352,8 @@ pub fn get_all_configs(conn: &Connection) -> Result<CombinedConfigs> {
             zero_on_read_error: row.get(17)?,
             use_chunk_data: row.get(18)?,
             created: row.get(19)?,
+            version: row.get(20)?,
+            field_policy: row.get(21)?,
         })
     })?;
     let mut ewf_configs = Vec::new();
@@ -344,7 +375,9 @@ pub fn get_all_configs(conn: &Connection) -> Result<CombinedConfigs> {
             split,
             vf,
             diffwr,
-            notes
+            notes,
+            version,
+            field_policy
          FROM dd_config
          WHERE active = true"#, // odstraněno seek, skip
     )?;
@@ -363,6 +396,8 @@ pub fn get_all_configs(conn: &Connection) -> Result<CombinedConfigs> {
             vf: row.get(10)?,
             diffwr: row.get(11)?,
             notes: row.get(12)?,
+            version: row.get(13)?,
+            field_policy: row.get(14)?,
         })
     })?;
     let mut dd_configs = Vec::new();
@@ -379,7 +414,7 @@ pub fn get_all_configs(conn: &Connection) -> Result<CombinedConfigs> {
 /// **Asynchronní příkaz pro získání všech aktivních konfigurací**
 #[tauri::command(rename_all = "snake_case")]
 pub async fn get_all_active_configs() -> Result<CombinedConfigs, String> {
-    let conn = Connection::open(DATABASE_PATH)
+    let conn = Connection::open(database_path())
         .map_err(|e| format!("Error opening DB: {}", e))?;
     let configs = tauri::async_runtime::spawn_blocking(move || get_all_configs(&conn))
         .await
@@ -402,7 +437,14 @@ pub fn delete_or_deactivate(conn: &Connection, config_id: i32, config_type: &str
     let mut stmt = conn.prepare(&query)?;
     let count: i32 = stmt.query_row([&config_id], |row| row.get(0))?;
 
-    if count > 0 {
+    // Verze, na kterou navazuje novější verze, zůstává kvůli historii
+    let successors: i32 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM {} WHERE previous_version_id = ?1", config_table),
+        [&config_id],
+        |row| row.get(0),
+    )?;
+
+    if count > 0 || successors > 0 {
         // Pokud existují odkazy, deaktivujeme konfiguraci
         let update_query = format!("UPDATE {} SET active = false WHERE id = ?1", config_table);
         conn.execute(&update_query, [&config_id])?;
@@ -423,7 +465,7 @@ pub async fn delete_or_deactivate_config(
     config_id: i32,
     config_type: String,
 ) -> Result<(), String> {
-    let conn = Connection::open(DATABASE_PATH)
+    let conn = Connection::open(database_path())
         .map_err(|e| format!("Chyba při otevírání DB: {}", e))?;
     tauri::async_runtime::spawn_blocking(move || {
         delete_or_deactivate(&conn, config_id, &config_type)
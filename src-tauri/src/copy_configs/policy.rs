@@ -0,0 +1,329 @@
+//! Politika polí konfigurace: které hodnoty jsou pevně dané supervizorem
+//! a které smí operátor zadat při spuštění akvizice.
+//!
+//! Uloženo v `field_policy` (JSON) u `ewf_config` / `dd_config`:
+//!
+//! ```json
+//! { "compression_level": { "mode": "ask", "default": "fast" },
+//!   "segment_size": { "mode": "ask" },
+//!   "codepage": { "mode": "hidden" } }
+//! ```
+//!
+//! Pole bez záznamu je `fixed`. `hidden` se při spuštění chová jako `fixed`,
+//! jen se operátorovi nezobrazuje. Starší hodnoty `ask` (`offset`,
+//! `bytes_to_read`, `notes`, `limit_mode`) dál zadávají vlastní parametry
+//! `EwfParams` / `DdParams`.
+use super::validation::FieldError;
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyMode {
+    Fixed,
+    Ask,
+    Hidden,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldPolicy {
+    pub mode: PolicyMode,
+    /// Hodnota pro `ask`, pokud ji operátor nezadá; bez ní je zadání povinné
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// Politiky všech polí jedné konfigurace (sloupec `field_policy`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FieldPolicies(pub BTreeMap<String, FieldPolicy>);
+
+impl FieldPolicies {
+    pub fn mode(&self, field: &str) -> PolicyMode {
+        self.0.get(field).map_or(PolicyMode::Fixed, |p| p.mode)
+    }
+}
+
+impl ToSql for FieldPolicies {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        serde_json::to_string(self)
+            .map(ToSqlOutput::from)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+    }
+}
+
+impl FromSql for FieldPolicies {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Null => Ok(Self::default()),
+            other => {
+                serde_json::from_str(other.as_str()?).map_err(|e| FromSqlError::Other(Box::new(e)))
+            }
+        }
+    }
+}
+
+/// Pole, na která se politika nevztahuje.
+const NOT_CONFIGURABLE: &[&str] = &["confname", "field_policy"];
+
+/// Převede zadaný text na typ, který pole v konfiguraci má.
+fn typed_value(current: &Value, raw: &str) -> Result<Value, String> {
+    let flag = match raw.trim().to_lowercase().as_str() {
+        "true" | "on" | "1" => Some(true),
+        "false" | "off" | "0" => Some(false),
+        _ => None,
+    };
+    match current {
+        Value::Bool(_) => flag
+            .map(Value::Bool)
+            .ok_or_else(|| format!("'{}' není ano/ne", raw)),
+        // vf / diffwr u dd jsou uložené jako 0/1
+        Value::Number(_) => flag
+            .map(|f| Value::from(f as i32))
+            .ok_or_else(|| format!("'{}' není 0 ani 1", raw)),
+        _ => Ok(Value::String(raw.to_string())),
+    }
+}
+
+fn field_error(field: &str, message: String) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message,
+    }
+}
+
+/// Chyby samotné politiky (neznámé pole, pole bez politiky).
+pub fn check_policies<T: Serialize>(config: &T, policies: &FieldPolicies) -> Vec<FieldError> {
+    let fields = match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    policies
+        .0
+        .keys()
+        .filter(|f| NOT_CONFIGURABLE.contains(&f.as_str()) || !fields.contains_key(*f))
+        .map(|f| field_error(f, "Pro toto pole nelze nastavit politiku".into()))
+        .collect()
+}
+
+/// Vrátí konfiguraci s hodnotami, se kterými se akvizice opravdu spustí:
+/// zadání operátora (`overrides`) pro pole `ask`, jinak jejich výchozí hodnoty.
+/// Zadání pole, které není `ask`, je chyba. S `require_ask` musí mít každé
+/// pole `ask` bez výchozí hodnoty zadanou hodnotu (spuštění); bez něj
+/// (ukládání) se ponechá uložená hodnota.
+pub fn apply_policy<T: Serialize + DeserializeOwned>(
+    config: &T,
+    policies: &FieldPolicies,
+    overrides: &HashMap<String, String>,
+    require_ask: bool,
+) -> Result<T, Vec<FieldError>> {
+    let Ok(Value::Object(mut fields)) = serde_json::to_value(config) else {
+        return Err(vec![field_error("", "Konfiguraci nelze zpracovat".into())]);
+    };
+    let mut errors = check_policies(config, policies);
+
+    for field in overrides.keys() {
+        if policies.mode(field) != PolicyMode::Ask {
+            errors.push(field_error(
+                field,
+                "Hodnota je pevně daná konfigurací, nelze ji změnit".into(),
+            ));
+        }
+    }
+
+    for (field, policy) in &policies.0 {
+        if policy.mode != PolicyMode::Ask {
+            continue;
+        }
+        let Some(current) = fields.get(field) else {
+            continue;
+        };
+        let raw = match (overrides.get(field), &policy.default) {
+            (Some(value), _) | (None, Some(value)) => value,
+            (None, None) if require_ask => {
+                errors.push(field_error(field, "Hodnotu je nutné zadat".into()));
+                continue;
+            }
+            (None, None) => continue,
+        };
+        match typed_value(current, raw) {
+            Ok(value) => {
+                fields.insert(field.clone(), value);
+            }
+            Err(e) => errors.push(field_error(field, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    serde_json::from_value(Value::Object(fields))
+        .map_err(|e| vec![field_error("", format!("Konfiguraci nelze zpracovat: {e}"))])
+}
+
+/// JSON skutečně použitých hodnot pro `copy_log_*.effective_config`.
+/// `runtime` přepisuje pole, která se zadávají parametry spuštění (`ask`).
+pub fn effective_json<T: Serialize>(
+    config: &T,
+    overrides: &HashMap<String, String>,
+    runtime: &[(&str, Value)],
+) -> String {
+    let mut values = match serde_json::to_value(config) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+    values.remove("field_policy");
+    for (field, value) in runtime {
+        values.insert(field.to_string(), value.clone());
+    }
+    let mut asked: Vec<&String> = overrides.keys().collect();
+    asked.sort();
+    serde_json::json!({ "values": values, "asked": asked }).to_string()
+}
+
+/// Přepíše hodnoty presetu `config` (řádek `ewf_config` / `dd_config`)
+/// skutečně použitými hodnotami z `effective_config`. Záznamy z doby před
+/// `effective_config` (`NULL`) nechá beze změny. Čísla se u textových
+/// sloupců převedou na text, aby je report četl stejně jako preset.
+pub fn apply_effective_config(config: &mut Map<String, Value>, effective_config: Option<&str>) {
+    let Some(values) = effective_config
+        .and_then(|json| serde_json::from_str::<Value>(json).ok())
+        .and_then(|mut v| match v["values"].take() {
+            Value::Object(map) => Some(map),
+            _ => None,
+        })
+    else {
+        return;
+    };
+    for (field, value) in values {
+        let value = match (config.get(&field), value) {
+            (_, Value::Null) => continue,
+            (Some(Value::String(_)), Value::Number(n)) => Value::String(n.to_string()),
+            (Some(Value::String(_)), Value::Bool(b)) => Value::String(b.to_string()),
+            (_, value) => value,
+        };
+        config.insert(field, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy_configs::NewDDConfig;
+
+    fn dd(policy: &[(&str, PolicyMode, Option<&str>)]) -> NewDDConfig {
+        NewDDConfig {
+            confname: "DD".into(),
+            format: "auto".into(),
+            limit_mode: "whole".into(),
+            offset: "0".into(),
+            hash_types: "md5".into(),
+            hashwindow: "whole".into(),
+            split: "whole".into(),
+            vf: 0,
+            diffwr: 0,
+            notes: "none".into(),
+            field_policy: FieldPolicies(
+                policy
+                    .iter()
+                    .map(|(field, mode, default)| {
+                        (
+                            field.to_string(),
+                            FieldPolicy {
+                                mode: *mode,
+                                default: default.map(str::to_string),
+                            },
+                        )
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn overrides(values: &[(&str, &str)]) -> HashMap<String, String> {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn ask_takes_override_then_default() {
+        let config = dd(&[
+            ("split", PolicyMode::Ask, Some("2G")),
+            ("hashwindow", PolicyMode::Ask, Some("1M")),
+        ]);
+        let applied = apply_policy(
+            &config,
+            &config.field_policy,
+            &overrides(&[("split", "4G")]),
+            true,
+        )
+        .unwrap();
+        assert_eq!(applied.split, "4G");
+        assert_eq!(applied.hashwindow, "1M");
+        assert_eq!(applied.format, "auto");
+    }
+
+    #[test]
+    fn ask_without_default_is_required_only_at_start() {
+        let config = dd(&[("split", PolicyMode::Ask, None)]);
+        let err = apply_policy(&config, &config.field_policy, &HashMap::new(), true).unwrap_err();
+        assert_eq!(fields(&err), ["split"]);
+        let saved = apply_policy(&config, &config.field_policy, &HashMap::new(), false).unwrap();
+        assert_eq!(saved.split, "whole");
+    }
+
+    #[test]
+    fn override_of_fixed_or_hidden_field_is_rejected() {
+        let config = dd(&[("notes", PolicyMode::Hidden, None)]);
+        let err = apply_policy(
+            &config,
+            &config.field_policy,
+            &overrides(&[("notes", "ask"), ("format", "4096")]),
+            true,
+        )
+        .unwrap_err();
+        let mut rejected = fields(&err);
+        rejected.sort();
+        assert_eq!(rejected, ["format", "notes"]);
+    }
+
+    #[test]
+    fn numeric_flags_are_typed() {
+        let config = dd(&[("vf", PolicyMode::Ask, None)]);
+        let applied = apply_policy(
+            &config,
+            &config.field_policy,
+            &overrides(&[("vf", "on")]),
+            true,
+        )
+        .unwrap();
+        assert_eq!(applied.vf, 1);
+        let err = apply_policy(
+            &config,
+            &config.field_policy,
+            &overrides(&[("vf", "maybe")]),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(fields(&err), ["vf"]);
+    }
+
+    #[test]
+    fn policy_for_unknown_or_reserved_field_is_rejected() {
+        let config = dd(&[
+            ("confname", PolicyMode::Ask, None),
+            ("bogus", PolicyMode::Fixed, None),
+        ]);
+        let err = apply_policy(&config, &config.field_policy, &HashMap::new(), false).unwrap_err();
+        assert_eq!(fields(&err), ["bogus", "confname"]);
+    }
+}
@@ -0,0 +1,385 @@
+//! Import a export presetů (EWF a dd konfigurací) pro přenos mezi jednotkami.
+//!
+//! Formát souboru (verze 1):
+//!
+//! ```json
+//! {
+//!   "format": "cratec-presets",
+//!   "version": 1,
+//!   "exported_at": "2025-01-01 12:00:00",
+//!   "software_version": "0.1.0",
+//!   "ewf": [ { "confname": "...", "codepage": "ascii", ... } ],
+//!   "dd":  [ { "confname": "...", "format": "512", ... } ]
+//! }
+//! ```
+//!
+//! Položky `ewf`/`dd` mají stejná pole jako `NewConfig`/`NewDDConfig`.
+use super::validation::{validate_dd_config, validate_ewf_config, FieldError};
+use super::{save_dd_config, save_ewf_config, NewConfig, NewDDConfig};
+use crate::db::DB_POOL;
+use crate::disk_utils::writers::write_guard;
+use crate::logger::write_audit;
+use crate::report::{sha256_hex, template::mounted_file, validate_output_mount};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::process::Command;
+
+pub const PRESET_FORMAT: &str = "cratec-presets";
+pub const PRESET_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresetFile {
+    pub format: String,
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: String,
+    #[serde(default)]
+    pub software_version: String,
+    #[serde(default)]
+    pub ewf: Vec<NewConfig>,
+    #[serde(default)]
+    pub dd: Vec<NewDDConfig>,
+}
+
+/// Co dělat, když už aktivní preset se stejným názvem existuje.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Preset se neimportuje
+    Skip,
+    /// Importuje se pod názvem `název (2)`, `název (3)`, …
+    Rename,
+    /// Stávající preset se deaktivuje (nebo smaže, pokud není použitý)
+    Replace,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedPreset {
+    pub config_type: String,
+    pub confname: String,
+    /// imported | renamed | replaced | skipped
+    pub action: String,
+}
+
+const EWF_COLUMNS: &str = "confname, codepage, sectors_per_read, bytes_to_read, compression_method,
+    compression_level, hash_types, ewf_format, granularity_sectors, notes, offset,
+    process_buffer_size, bytes_per_sector, read_retry_count, swap_byte_pairs, segment_size,
+    zero_on_read_error, use_chunk_data, field_policy";
+const DD_COLUMNS: &str = "confname, format, limit_mode, offset, hash_types, hashwindow, split,
+    vf, diffwr, notes, field_policy";
+
+fn ewf_from_row(row: &rusqlite::Row) -> rusqlite::Result<NewConfig> {
+    Ok(NewConfig {
+        confname: row.get(0)?,
+        codepage: row.get(1)?,
+        sectors_per_read: row.get(2)?,
+        bytes_to_read: row.get(3)?,
+        compression_method: row.get(4)?,
+        compression_level: row.get(5)?,
+        hash_types: row.get(6)?,
+        ewf_format: row.get(7)?,
+        granularity_sectors: row.get(8)?,
+        notes: row.get(9)?,
+        offset: row.get(10)?,
+        process_buffer_size: row.get(11)?,
+        bytes_per_sector: row.get(12)?,
+        read_retry_count: row.get(13)?,
+        swap_byte_pairs: row.get(14)?,
+        segment_size: row.get(15)?,
+        zero_on_read_error: row.get(16)?,
+        use_chunk_data: row.get(17)?,
+        field_policy: row.get(18)?,
+    })
+}
+
+fn dd_from_row(row: &rusqlite::Row) -> rusqlite::Result<NewDDConfig> {
+    Ok(NewDDConfig {
+        confname: row.get(0)?,
+        format: row.get(1)?,
+        limit_mode: row.get(2)?,
+        offset: row.get(3)?,
+        hash_types: row.get(4)?,
+        hashwindow: row.get(5)?,
+        split: row.get(6)?,
+        vf: row.get(7)?,
+        diffwr: row.get(8)?,
+        notes: row.get(9)?,
+        field_policy: row.get(10)?,
+    })
+}
+
+/// Presety k exportu: jeden podle typu a ID, jinak všechny aktivní.
+fn load_presets(
+    conn: &Connection,
+    config_type: Option<&str>,
+    config_id: Option<i64>,
+) -> Result<PresetFile, String> {
+    let mut file = PresetFile {
+        format: PRESET_FORMAT.into(),
+        version: PRESET_VERSION,
+        exported_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        software_version: env!("CARGO_PKG_VERSION").into(),
+        ewf: Vec::new(),
+        dd: Vec::new(),
+    };
+    let db = |e: rusqlite::Error| e.to_string();
+
+    if let Some(id) = config_id {
+        match config_type {
+            Some("ewf") => file.ewf.extend(
+                conn.query_row(
+                    &format!("SELECT {EWF_COLUMNS} FROM ewf_config WHERE id = ?1"),
+                    params![id],
+                    ewf_from_row,
+                )
+                .optional()
+                .map_err(db)?,
+            ),
+            Some("dd") => file.dd.extend(
+                conn.query_row(
+                    &format!("SELECT {DD_COLUMNS} FROM dd_config WHERE id = ?1"),
+                    params![id],
+                    dd_from_row,
+                )
+                .optional()
+                .map_err(db)?,
+            ),
+            other => return Err(format!("Unknown config_type: {:?}", other)),
+        }
+        if file.ewf.is_empty() && file.dd.is_empty() {
+            return Err(format!("Konfigurace {id} neexistuje"));
+        }
+        return Ok(file);
+    }
+
+    if config_type.is_none_or(|t| t == "ewf") {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {EWF_COLUMNS} FROM ewf_config WHERE active = true ORDER BY id"
+            ))
+            .map_err(db)?;
+        file.ewf = stmt
+            .query_map([], ewf_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(db)?;
+    }
+    if config_type.is_none_or(|t| t == "dd") {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {DD_COLUMNS} FROM dd_config WHERE active = true ORDER BY id"
+            ))
+            .map_err(db)?;
+        file.dd = stmt
+            .query_map([], dd_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(db)?;
+    }
+    Ok(file)
+}
+
+fn field_errors(prefix: &str, errors: Vec<FieldError>) -> Vec<String> {
+    errors
+        .into_iter()
+        .map(|e| format!("{prefix}.{}: {}", e.field, e.message))
+        .collect()
+}
+
+/// Ověří hlavičku a všechna pole všech presetů; vrací všechny chyby najednou.
+fn validate_file(file: &PresetFile) -> Result<(), String> {
+    if file.format != PRESET_FORMAT {
+        return Err(format!("Soubor není ve formátu {PRESET_FORMAT}"));
+    }
+    if file.version == 0 || file.version > PRESET_VERSION {
+        return Err(format!(
+            "Nepodporovaná verze presetů {} (podporovaná {PRESET_VERSION})",
+            file.version
+        ));
+    }
+    let mut errors = Vec::new();
+    for (i, config) in file.ewf.iter().enumerate() {
+        if let Err(e) = validate_ewf_config(config) {
+            errors.extend(field_errors(&format!("ewf[{i}] '{}'", config.confname), e));
+        }
+    }
+    for (i, config) in file.dd.iter().enumerate() {
+        if let Err(e) = validate_dd_config(config) {
+            errors.extend(field_errors(&format!("dd[{i}] '{}'", config.confname), e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn active_ids(conn: &Connection, table: &str, confname: &str) -> rusqlite::Result<Vec<i64>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id FROM {table} WHERE active = true AND confname = ?1"
+    ))?;
+    let ids = stmt.query_map(params![confname], |r| r.get(0))?;
+    ids.collect()
+}
+
+/// Vyřeší konflikt názvu. `None` = preset přeskočit.
+fn resolve_conflict(
+    conn: &Connection,
+    config_type: &str,
+    confname: &str,
+    policy: ConflictPolicy,
+) -> rusqlite::Result<Option<(String, &'static str)>> {
+    let table = if config_type == "ewf" {
+        "ewf_config"
+    } else {
+        "dd_config"
+    };
+    let existing = active_ids(conn, table, confname)?;
+    if existing.is_empty() {
+        return Ok(Some((confname.to_string(), "imported")));
+    }
+    match policy {
+        ConflictPolicy::Skip => Ok(None),
+        ConflictPolicy::Replace => {
+            for id in existing {
+                super::delete_or_deactivate(conn, id as i32, config_type)?;
+            }
+            Ok(Some((confname.to_string(), "replaced")))
+        }
+        ConflictPolicy::Rename => {
+            let mut n = 2;
+            loop {
+                let candidate = format!("{confname} ({n})");
+                if active_ids(conn, table, &candidate)?.is_empty() {
+                    return Ok(Some((candidate, "renamed")));
+                }
+                n += 1;
+            }
+        }
+    }
+}
+
+fn import_file(
+    conn: &mut Connection,
+    file: PresetFile,
+    policy: ConflictPolicy,
+    source: &str,
+    source_sha256: &str,
+) -> rusqlite::Result<Vec<ImportedPreset>> {
+    let tx = conn.transaction()?;
+    let mut result = Vec::new();
+    for mut config in file.ewf {
+        let original = config.confname.clone();
+        let action = match resolve_conflict(&tx, "ewf", &original, policy)? {
+            Some((name, action)) => {
+                config.confname = name;
+                save_ewf_config(&tx, config)?;
+                action
+            }
+            None => "skipped",
+        };
+        result.push(ImportedPreset {
+            config_type: "ewf".into(),
+            confname: original,
+            action: action.into(),
+        });
+    }
+    for mut config in file.dd {
+        let original = config.confname.clone();
+        let action = match resolve_conflict(&tx, "dd", &original, policy)? {
+            Some((name, action)) => {
+                config.confname = name;
+                save_dd_config(&tx, config)?;
+                action
+            }
+            None => "skipped",
+        };
+        result.push(ImportedPreset {
+            config_type: "dd".into(),
+            confname: original,
+            action: action.into(),
+        });
+    }
+
+    let summary = result
+        .iter()
+        .map(|p| format!("{} '{}' ({})", p.config_type, p.confname, p.action))
+        .collect::<Vec<_>>()
+        .join(", ");
+    write_audit(
+        &tx,
+        &format!("Import presetů z {source} (sha256 {source_sha256}): {summary}"),
+    )?;
+    tx.commit()?;
+    Ok(result)
+}
+
+/// Exportuje presety do `cratec-presets-<YYYYmmdd-HHMMSS>.json` na připojený výstup.
+/// Bez `config_id` se exportují všechny aktivní presety (volitelně jen jednoho typu).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_presets(
+    target: String,
+    config_type: Option<String>,
+    config_id: Option<i64>,
+) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+        let mount = validate_output_mount(&target)?;
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let file = load_presets(pooled.connection(), config_type.as_deref(), config_id)?;
+        drop(pooled);
+        if file.ewf.is_empty() && file.dd.is_empty() {
+            return Err("Žádné presety k exportu".into());
+        }
+
+        let json = serde_json::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        let file_name = format!(
+            "cratec-presets-{}.json",
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        );
+        let tmp_path = format!("/tmp/{file_name}");
+        fs::write(&tmp_path, json).map_err(|e| e.to_string())?;
+        let dest = format!("{mount}/{file_name}");
+        let _writing = write_guard(&mount)?;
+        let status = Command::new("sudo")
+            .arg("cp")
+            .arg(&tmp_path)
+            .arg(&dest)
+            .status();
+        let _ = fs::remove_file(&tmp_path);
+        match status {
+            Ok(s) if s.success() => Ok(dest),
+            Ok(s) => Err(format!("sudo cp failed with status {:?}", s.code())),
+            Err(e) => Err(e.to_string()),
+        }
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Importuje presety ze souboru na připojeném výstupu. Soubor se nejdřív celý
+/// ověří; při jakékoli chybě pole se neimportuje nic.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_presets(
+    path: String,
+    on_conflict: ConflictPolicy,
+) -> Result<Vec<ImportedPreset>, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<ImportedPreset>, String> {
+        let src = mounted_file(&path)?;
+        let data = fs::read(&src).map_err(|e| e.to_string())?;
+        let file: PresetFile =
+            serde_json::from_slice(&data).map_err(|e| format!("Neplatný soubor presetů: {e}"))?;
+        validate_file(&file)?;
+
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        import_file(
+            pooled.connection(),
+            file,
+            on_conflict,
+            &src.to_string_lossy(),
+            &sha256_hex(&data),
+        )
+        .map_err(|e| format!("Import presetů selhal: {e}"))
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
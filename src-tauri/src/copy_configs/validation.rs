@@ -0,0 +1,654 @@
+//! Kontrola hodnot konfigurací proti omezením schématu (`CHECK` v
+//! `ewf_config_scheme.rs` a `dd_config_scheme.rs`).
+use super::policy::apply_policy;
+use super::{NewConfig, NewDDConfig};
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const EWF_CODEPAGES: &[&str] = &[
+    "ascii",
+    "windows-874",
+    "windows-932",
+    "windows-936",
+    "windows-949",
+    "windows-950",
+    "windows-1250",
+    "windows-1251",
+    "windows-1252",
+    "windows-1253",
+    "windows-1254",
+    "windows-1255",
+    "windows-1256",
+    "windows-1257",
+    "windows-1258",
+];
+pub const EWF_SECTORS_PER_READ: &[&str] = &[
+    "16", "32", "64", "128", "256", "512", "1024", "2048", "4096", "8192", "16384", "32768",
+];
+pub const EWF_BYTES_TO_READ: &[&str] = &["whole", "ask"];
+pub const EWF_COMPRESSION_METHODS: &[&str] = &["deflate"];
+pub const EWF_COMPRESSION_LEVELS: &[&str] = &["none", "empty-block", "fast", "best"];
+pub const EWF_FORMATS: &[&str] = &[
+    "ewf", "smart", "ftk", "encase1", "encase2", "encase3", "encase4", "encase5", "encase6",
+    "linen5", "linen6", "ewfx",
+];
+pub const EWF_OFFSETS: &[&str] = &["0", "ask"];
+/// `ewfacquire -d` (MD5 se počítá vždy)
+pub const EWF_HASH_TYPES: &[&str] = &["md5", "sha1", "sha256"];
+
+pub const DD_LIMIT_MODES: &[&str] = &["whole", "ask"];
+pub const DD_NOTES: &[&str] = &["ask", "none"];
+pub const DD_HASH_TYPES: &[&str] = &["md5", "sha1", "sha256", "sha384", "sha512"];
+
+/// Chyba jednoho pole konfigurace, pro zobrazení u pole v UI.
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Default)]
+struct Errors(Vec<FieldError>);
+
+impl Errors {
+    fn push(&mut self, field: &str, message: String) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message,
+        });
+    }
+
+    fn one_of(&mut self, field: &str, value: &str, allowed: &[&str]) {
+        if !allowed.contains(&value) {
+            self.push(
+                field,
+                format!("'{}' není povolená hodnota ({})", value, allowed.join(", ")),
+            );
+        }
+    }
+
+    fn not_empty(&mut self, field: &str, value: &str) {
+        if value.trim().is_empty() {
+            self.push(field, "Hodnota nesmí být prázdná".into());
+        }
+    }
+
+    fn hash_types(&mut self, field: &str, value: &str, allowed: &[&str]) {
+        for hash in hash_list(value) {
+            if !allowed.contains(&hash.as_str()) {
+                self.push(field, format!("Nepodporovaný hash '{}'", hash));
+            }
+        }
+    }
+
+    fn into_result(self) -> Result<(), Vec<FieldError>> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(self.0)
+        }
+    }
+}
+
+/// Chyba ukládání konfigurace: celkový popis a chyby jednotlivých polí
+/// (prázdné, pokud selhalo něco jiného než kontrola hodnot).
+#[derive(Debug, Serialize)]
+pub struct ConfigError {
+    pub message: String,
+    pub fields: Vec<FieldError>,
+}
+
+impl From<Vec<FieldError>> for ConfigError {
+    fn from(fields: Vec<FieldError>) -> Self {
+        Self {
+            message: describe(&fields),
+            fields,
+        }
+    }
+}
+
+impl From<String> for ConfigError {
+    fn from(message: String) -> Self {
+        Self {
+            message,
+            fields: Vec::new(),
+        }
+    }
+}
+
+/// Chyby polí jako jeden text (`pole: zpráva; …`) pro příkazy vracející `String`.
+pub fn describe(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("{}: {}", e.field, e.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Seznam hashů z uloženého tvaru (`md5,sha1`, případně `["md5"]`).
+pub fn hash_list(value: &str) -> Vec<String> {
+    value
+        .replace(['[', ']', '"', '\''], "")
+        .split(',')
+        .map(|h| h.trim().to_lowercase())
+        .filter(|h| !h.is_empty())
+        .collect()
+}
+
+/// Velikost v bajtech z textu (`1.4 GiB`, `650MiB`, `5G`, `1MB`, `4096`).
+/// Jednotky s `i` a jednopísmenné (`k`, `M`, `G`, `T` jako u dd) jsou
+/// násobky 1024, dvoupísmenné bez `i` (`kB`, `MB`, `GB`, `TB`) násobky 1000.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "k" | "K" | "KiB" | "kiB" => 1 << 10,
+        "kB" | "KB" => 1_000,
+        "M" | "MiB" => 1 << 20,
+        "MB" => 1_000_000,
+        "G" | "GiB" => 1 << 30,
+        "GB" => 1_000_000_000,
+        "T" | "TiB" => 1 << 40,
+        "TB" => 1_000_000_000_000,
+        other => return Err(format!("Neznámá jednotka '{}'", other)),
+    };
+    if number.is_empty() {
+        return Err(format!("'{}' není velikost", value));
+    }
+    if let Ok(n) = number.parse::<u64>() {
+        return n
+            .checked_mul(multiplier)
+            .ok_or_else(|| format!("'{}' je příliš velké", value));
+    }
+    match number.parse::<f64>() {
+        Ok(n) if n.is_finite() && n * (multiplier as f64) < u64::MAX as f64 => {
+            Ok((n * multiplier as f64).floor() as u64)
+        }
+        _ => Err(format!("'{}' není velikost", value)),
+    }
+}
+
+/// Ověřené číselné hodnoty EWF konfigurace, ze kterých se skládá příkaz `ewfacquire`.
+#[derive(Debug, Clone)]
+pub struct EwfSettings {
+    pub sectors_per_read: u32,
+    pub granularity_sectors: u32,
+    /// `None` = `auto` (výchozí velikost podle ewfacquire, `-p` se nepředává)
+    pub process_buffer_size: Option<u64>,
+    /// `None` = `auto` (zjistí ewfacquire)
+    pub bytes_per_sector: Option<u32>,
+    pub read_retry_count: u8,
+    pub segment_size: u64,
+}
+
+/// Ověřené číselné hodnoty dd konfigurace pro příkaz `dcfldd`.
+#[derive(Debug, Clone)]
+pub struct DdSettings {
+    /// `None` = `auto` (logická velikost sektoru zdroje)
+    pub block_size: Option<u64>,
+    /// `None` = `ask` (zadá se při spuštění)
+    pub offset: Option<u64>,
+    /// `None` = `whole`
+    pub hashwindow: Option<u64>,
+    /// `None` = `whole`
+    pub split: Option<u64>,
+}
+
+/// Maximální segment pro formáty bez 64bitových offsetů (ewfacquire: 1.9 GiB).
+const EWF_SEGMENT_MAX_32: u64 = i32::MAX as u64;
+const EWF_SEGMENT_MIN: u64 = 1 << 20;
+const EWF_BYTES_PER_SECTOR: &[u32] = &[128, 256, 512, 1024, 2048, 4096, 8192, 16384];
+const DD_BLOCK_SIZE_MAX: u64 = 64 << 20;
+const DD_SPLIT_MIN: u64 = 1 << 20;
+
+impl Errors {
+    fn parsed<T>(&mut self, field: &str, result: Result<T, String>) -> Option<T> {
+        result.map_err(|e| self.push(field, e)).ok()
+    }
+
+    fn in_range<T: PartialOrd + std::fmt::Display>(
+        &mut self,
+        field: &str,
+        value: Option<T>,
+        min: T,
+        max: T,
+    ) -> Option<T> {
+        let value = value?;
+        if value < min || value > max {
+            self.push(field, format!("{} není v rozsahu {}–{}", value, min, max));
+            return None;
+        }
+        Some(value)
+    }
+}
+
+fn parse_int<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("'{}' není celé číslo v povoleném rozsahu", value))
+}
+
+/// Úplná kontrola EWF konfigurace: povolené hodnoty i typy a rozsahy číselných polí.
+pub fn parse_ewf_config(config: &NewConfig) -> Result<EwfSettings, Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.not_empty("confname", &config.confname);
+    errors.one_of("codepage", &config.codepage, EWF_CODEPAGES);
+    errors.one_of(
+        "sectors_per_read",
+        &config.sectors_per_read,
+        EWF_SECTORS_PER_READ,
+    );
+    errors.one_of("bytes_to_read", &config.bytes_to_read, EWF_BYTES_TO_READ);
+    errors.one_of(
+        "compression_method",
+        &config.compression_method,
+        EWF_COMPRESSION_METHODS,
+    );
+    errors.one_of(
+        "compression_level",
+        &config.compression_level,
+        EWF_COMPRESSION_LEVELS,
+    );
+    errors.one_of("ewf_format", &config.ewf_format, EWF_FORMATS);
+    errors.one_of("offset", &config.offset, EWF_OFFSETS);
+    errors.hash_types("hash_types", &config.hash_types, EWF_HASH_TYPES);
+
+    let sectors_per_read = config.sectors_per_read.parse::<u32>().ok();
+
+    let granularity = errors.parsed(
+        "granularity_sectors",
+        parse_int::<u32>(&config.granularity_sectors),
+    );
+    let granularity = errors.in_range(
+        "granularity_sectors",
+        granularity,
+        1,
+        sectors_per_read.unwrap_or(u32::MAX),
+    );
+    if let (Some(g), Some(s)) = (granularity, sectors_per_read) {
+        if s % g != 0 {
+            errors.push(
+                "granularity_sectors",
+                format!("{} nedělí počet sektorů na čtení ({})", g, s),
+            );
+        }
+    }
+
+    let process_buffer_size = match config.process_buffer_size.trim() {
+        "" | "auto" => None,
+        value => {
+            let size = errors.parsed("process_buffer_size", parse_size(value));
+            errors.in_range("process_buffer_size", size, 1, u32::MAX as u64)
+        }
+    };
+
+    let bytes_per_sector = match config.bytes_per_sector.trim() {
+        "auto" => None,
+        value => {
+            let size = errors.parsed("bytes_per_sector", parse_int::<u32>(value));
+            if size.is_some_and(|s| !EWF_BYTES_PER_SECTOR.contains(&s)) {
+                errors.push(
+                    "bytes_per_sector",
+                    format!("'{}' není povolená velikost sektoru", value),
+                );
+            }
+            size
+        }
+    };
+
+    let read_retry_count = errors.parsed(
+        "read_retry_count",
+        parse_int::<u8>(&config.read_retry_count),
+    );
+
+    // encase6/ewfx mají 64bitové offsety, ostatní formáty max. 1.9 GiB
+    let segment_max = match config.ewf_format.as_str() {
+        "encase6" | "ewfx" => i64::MAX as u64,
+        _ => EWF_SEGMENT_MAX_32,
+    };
+    let segment_size = errors.parsed("segment_size", parse_size(&config.segment_size));
+    let segment_size = errors.in_range("segment_size", segment_size, EWF_SEGMENT_MIN, segment_max);
+
+    match (
+        errors.0.is_empty(),
+        sectors_per_read,
+        granularity,
+        read_retry_count,
+        segment_size,
+    ) {
+        (
+            true,
+            Some(sectors_per_read),
+            Some(granularity_sectors),
+            Some(read_retry_count),
+            Some(segment_size),
+        ) => Ok(EwfSettings {
+            sectors_per_read,
+            granularity_sectors,
+            process_buffer_size,
+            bytes_per_sector,
+            read_retry_count,
+            segment_size,
+        }),
+        _ => Err(errors.0),
+    }
+}
+
+/// Úplná kontrola dd konfigurace: povolené hodnoty i typy a rozsahy číselných polí.
+pub fn parse_dd_config(config: &NewDDConfig) -> Result<DdSettings, Vec<FieldError>> {
+    let mut errors = Errors::default();
+    errors.not_empty("confname", &config.confname);
+    errors.one_of("limit_mode", &config.limit_mode, DD_LIMIT_MODES);
+    errors.one_of("notes", &config.notes, DD_NOTES);
+    errors.hash_types("hash_types", &config.hash_types, DD_HASH_TYPES);
+    for (field, value) in [("vf", config.vf), ("diffwr", config.diffwr)] {
+        if !(0..=1).contains(&value) {
+            errors.push(field, format!("'{}' není 0 ani 1", value));
+        }
+    }
+
+    let block_size = match config.format.trim() {
+        "auto" => None,
+        value => {
+            let size = errors.parsed("format", parse_size(value));
+            let size = errors.in_range("format", size, 512, DD_BLOCK_SIZE_MAX);
+            if size.is_some_and(|s| s % 512 != 0) {
+                errors.push("format", "Velikost bloku musí být násobek 512 B".into());
+            }
+            size
+        }
+    };
+
+    let offset = match config.offset.trim() {
+        "ask" => None,
+        value => errors.parsed("offset", parse_int::<u64>(value)),
+    };
+
+    let hashwindow = match config.hashwindow.trim() {
+        "whole" => None,
+        value => {
+            let size = errors.parsed("hashwindow", parse_size(value));
+            // dcfldd hashuje okna po bajtech nezávisle na bs, stačí kladná velikost
+            errors.in_range("hashwindow", size, 1, u64::MAX)
+        }
+    };
+
+    let split = match config.split.trim() {
+        "whole" => None,
+        value => {
+            let size = errors.parsed("split", parse_size(value));
+            errors.in_range("split", size, DD_SPLIT_MIN, u64::MAX)
+        }
+    };
+
+    errors.into_result()?;
+    Ok(DdSettings {
+        block_size,
+        offset,
+        hashwindow,
+        split,
+    })
+}
+
+/// Kontrola při ukládání: uložené hodnoty, politika polí a výchozí hodnoty pro `ask`.
+pub fn validate_ewf_config(config: &NewConfig) -> Result<(), Vec<FieldError>> {
+    parse_ewf_config(config)?;
+    let with_defaults = apply_policy(config, &config.field_policy, &HashMap::new(), false)?;
+    parse_ewf_config(&with_defaults).map(|_| ())
+}
+
+/// Kontrola při ukládání: uložené hodnoty, politika polí a výchozí hodnoty pro `ask`.
+pub fn validate_dd_config(config: &NewDDConfig) -> Result<(), Vec<FieldError>> {
+    parse_dd_config(config)?;
+    let with_defaults = apply_policy(config, &config.field_policy, &HashMap::new(), false)?;
+    parse_dd_config(&with_defaults).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::copy_configs::policy::{FieldPolicies, FieldPolicy, PolicyMode};
+
+    fn ewf() -> NewConfig {
+        NewConfig {
+            confname: "EWF".into(),
+            codepage: "ascii".into(),
+            sectors_per_read: "64".into(),
+            bytes_to_read: "whole".into(),
+            compression_method: "deflate".into(),
+            compression_level: "fast".into(),
+            hash_types: "md5,sha1".into(),
+            ewf_format: "encase6".into(),
+            granularity_sectors: "64".into(),
+            notes: String::new(),
+            offset: "0".into(),
+            process_buffer_size: "auto".into(),
+            bytes_per_sector: "auto".into(),
+            read_retry_count: "2".into(),
+            swap_byte_pairs: false,
+            segment_size: "1.4 GiB".into(),
+            zero_on_read_error: false,
+            use_chunk_data: false,
+            field_policy: FieldPolicies::default(),
+        }
+    }
+
+    fn dd() -> NewDDConfig {
+        NewDDConfig {
+            confname: "DD".into(),
+            format: "auto".into(),
+            limit_mode: "whole".into(),
+            offset: "0".into(),
+            hash_types: "md5".into(),
+            hashwindow: "whole".into(),
+            split: "whole".into(),
+            vf: 0,
+            diffwr: 0,
+            notes: "none".into(),
+            field_policy: FieldPolicies::default(),
+        }
+    }
+
+    fn fields(errors: &[FieldError]) -> Vec<&str> {
+        errors.iter().map(|e| e.field.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_size_units() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("512B"), Ok(512));
+        assert_eq!(parse_size("1k"), Ok(1024));
+        assert_eq!(parse_size("1KiB"), Ok(1024));
+        assert_eq!(parse_size("1kB"), Ok(1_000));
+        assert_eq!(parse_size("1M"), Ok(1 << 20));
+        assert_eq!(parse_size("1MiB"), Ok(1 << 20));
+        assert_eq!(parse_size("1MB"), Ok(1_000_000));
+        assert_eq!(parse_size(" 8 G "), Ok(8 << 30));
+        assert_eq!(parse_size("2GB"), Ok(2_000_000_000));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        assert_eq!(parse_size("1TB"), Ok(1_000_000_000_000));
+    }
+
+    #[test]
+    fn parse_size_fractions() {
+        assert_eq!(parse_size("1.4 GiB"), Ok(1_503_238_553));
+        assert_eq!(parse_size("0.5k"), Ok(512));
+        assert_eq!(parse_size("1.5MB"), Ok(1_500_000));
+        assert!(parse_size("1.2.3M").is_err());
+    }
+
+    #[test]
+    fn parse_size_rejects_invalid_and_overflow() {
+        assert!(parse_size("").is_err());
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("1e5").is_err());
+        assert!(parse_size("10 PB").is_err());
+        assert!(parse_size("-1").is_err());
+        assert_eq!(parse_size("18446744073709551615"), Ok(u64::MAX));
+        assert!(parse_size("18446744073709551616").is_err());
+        assert!(parse_size("16777216T").is_err());
+        assert!(parse_size("16777215.9T").is_ok());
+        assert!(parse_size("20000000 TB").is_err());
+    }
+
+    #[test]
+    fn ewf_config_valid() {
+        let settings = parse_ewf_config(&ewf()).unwrap();
+        assert_eq!(settings.sectors_per_read, 64);
+        assert_eq!(settings.granularity_sectors, 64);
+        assert_eq!(settings.process_buffer_size, None);
+        assert_eq!(settings.bytes_per_sector, None);
+        assert_eq!(settings.read_retry_count, 2);
+        assert_eq!(settings.segment_size, 1_503_238_553);
+    }
+
+    #[test]
+    fn ewf_config_granularity() {
+        let mut config = ewf();
+        config.granularity_sectors = "3".into();
+        assert_eq!(
+            fields(&parse_ewf_config(&config).unwrap_err()),
+            ["granularity_sectors"]
+        );
+        config.granularity_sectors = "128".into();
+        assert_eq!(
+            fields(&parse_ewf_config(&config).unwrap_err()),
+            ["granularity_sectors"]
+        );
+        config.granularity_sectors = "0".into();
+        assert!(parse_ewf_config(&config).is_err());
+        config.granularity_sectors = "16".into();
+        assert_eq!(parse_ewf_config(&config).unwrap().granularity_sectors, 16);
+    }
+
+    #[test]
+    fn ewf_config_segment_size_depends_on_format() {
+        let mut config = ewf();
+        config.segment_size = "3 GiB".into();
+        assert!(parse_ewf_config(&config).is_ok());
+        config.ewf_format = "encase5".into();
+        assert_eq!(
+            fields(&parse_ewf_config(&config).unwrap_err()),
+            ["segment_size"]
+        );
+        config.segment_size = "1.9 GiB".into();
+        assert!(parse_ewf_config(&config).is_ok());
+        config.segment_size = "512k".into();
+        assert_eq!(
+            fields(&parse_ewf_config(&config).unwrap_err()),
+            ["segment_size"]
+        );
+    }
+
+    #[test]
+    fn ewf_config_numeric_fields() {
+        let mut config = ewf();
+        config.bytes_per_sector = "4096".into();
+        config.process_buffer_size = "64 MiB".into();
+        let settings = parse_ewf_config(&config).unwrap();
+        assert_eq!(settings.bytes_per_sector, Some(4096));
+        assert_eq!(settings.process_buffer_size, Some(64 << 20));
+
+        config.bytes_per_sector = "300".into();
+        config.read_retry_count = "300".into();
+        config.process_buffer_size = "8G".into();
+        assert_eq!(
+            fields(&parse_ewf_config(&config).unwrap_err()),
+            [
+                "process_buffer_size",
+                "bytes_per_sector",
+                "read_retry_count"
+            ]
+        );
+    }
+
+    #[test]
+    fn ewf_config_allowed_values() {
+        let mut config = ewf();
+        config.confname = " ".into();
+        config.codepage = "utf-8".into();
+        config.hash_types = "md5,sha512".into();
+        config.compression_level = "max".into();
+        let errors = parse_ewf_config(&config).unwrap_err();
+        assert_eq!(
+            fields(&errors),
+            ["confname", "codepage", "compression_level", "hash_types"]
+        );
+        assert!(errors[3].message.contains("sha512"));
+    }
+
+    #[test]
+    fn dd_config_valid() {
+        let settings = parse_dd_config(&dd()).unwrap();
+        assert_eq!(settings.block_size, None);
+        assert_eq!(settings.offset, Some(0));
+        assert_eq!(settings.hashwindow, None);
+        assert_eq!(settings.split, None);
+
+        let mut config = dd();
+        config.format = "4k".into();
+        config.offset = "ask".into();
+        config.hashwindow = "1000".into();
+        config.split = "1G".into();
+        let settings = parse_dd_config(&config).unwrap();
+        assert_eq!(settings.block_size, Some(4096));
+        assert_eq!(settings.offset, None);
+        assert_eq!(settings.hashwindow, Some(1000));
+        assert_eq!(settings.split, Some(1 << 30));
+    }
+
+    #[test]
+    fn dd_config_block_size() {
+        let mut config = dd();
+        config.format = "1000".into();
+        assert_eq!(fields(&parse_dd_config(&config).unwrap_err()), ["format"]);
+        config.format = "256".into();
+        assert_eq!(fields(&parse_dd_config(&config).unwrap_err()), ["format"]);
+        config.format = "128M".into();
+        assert_eq!(fields(&parse_dd_config(&config).unwrap_err()), ["format"]);
+        config.format = "64M".into();
+        assert_eq!(parse_dd_config(&config).unwrap().block_size, Some(64 << 20));
+    }
+
+    #[test]
+    fn dd_config_field_errors() {
+        let mut config = dd();
+        config.limit_mode = "partial".into();
+        config.hash_types = "crc32".into();
+        config.vf = 2;
+        config.offset = "abc".into();
+        config.hashwindow = "0".into();
+        config.split = "512k".into();
+        assert_eq!(
+            fields(&parse_dd_config(&config).unwrap_err()),
+            [
+                "limit_mode",
+                "hash_types",
+                "vf",
+                "offset",
+                "hashwindow",
+                "split"
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_checks_ask_defaults() {
+        let mut config = dd();
+        config.field_policy.0.insert(
+            "split".into(),
+            FieldPolicy {
+                mode: PolicyMode::Ask,
+                default: Some("2G".into()),
+            },
+        );
+        assert!(validate_dd_config(&config).is_ok());
+        config.field_policy.0.get_mut("split").unwrap().default = Some("1k".into());
+        assert_eq!(fields(&validate_dd_config(&config).unwrap_err()), ["split"]);
+    }
+}
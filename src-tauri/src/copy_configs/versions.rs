@@ -0,0 +1,240 @@
+//! Úpravy konfigurací s neměnnou historií verzí.
+//!
+//! Uložená konfigurace se nikdy nepřepisuje: úprava vloží nový řádek
+//! s `version + 1` a `previous_version_id` na předchozí verzi, která se
+//! deaktivuje. Staré verze tak zůstávají odkazované z `copy_log_*.config_id`
+//! a report vždy cituje přesně to nastavení, se kterým akvizice běžela.
+use super::validation::{validate_dd_config, validate_ewf_config, ConfigError};
+use super::{save_dd_config, save_ewf_config, NewConfig, NewDDConfig};
+use crate::db::DB_POOL;
+use crate::logger::write_audit;
+use rusqlite::types::Value as SqlValue;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Sloupce, které popisují verzi, ne nastavení – v diffu se neporovnávají.
+const META_COLUMNS: &[&str] = &["id", "created", "active", "version", "previous_version_id"];
+
+#[derive(Debug, Serialize)]
+pub struct ConfigVersion {
+    pub id: i64,
+    pub version: i64,
+    pub previous_version_id: Option<i64>,
+    pub created: String,
+    pub active: bool,
+    pub confname: String,
+    /// Počet akvizic, které s touto verzí proběhly
+    pub used_by: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConfigFieldChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+fn tables(config_type: &str) -> Result<(&'static str, &'static str), String> {
+    match config_type {
+        "ewf" => Ok(("ewf_config", "copy_log_ewf")),
+        "dd" => Ok(("dd_config", "copy_log_dd")),
+        other => Err(format!("Unknown config_type: {}", other)),
+    }
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+/// Nastavení jedné verze jako dvojice (sloupec, hodnota), bez metadat verze.
+fn settings(conn: &Connection, table: &str, id: i64) -> Result<Vec<(String, Value)>, String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {table} WHERE id = ?1"))
+        .map_err(db_err)?;
+    let columns: Vec<String> = stmt.column_names().iter().map(|c| c.to_string()).collect();
+    stmt.query_row(params![id], |row| {
+        let mut values = Vec::new();
+        for (i, name) in columns.iter().enumerate() {
+            if META_COLUMNS.contains(&name.as_str()) {
+                continue;
+            }
+            let value = match row.get::<_, SqlValue>(i)? {
+                SqlValue::Null => Value::Null,
+                SqlValue::Integer(n) => Value::from(n),
+                SqlValue::Real(f) => Value::from(f),
+                SqlValue::Text(s) => Value::String(s),
+                SqlValue::Blob(b) => Value::String(String::from_utf8_lossy(&b).to_string()),
+            };
+            values.push((name.clone(), value));
+        }
+        Ok(values)
+    })
+    .optional()
+    .map_err(db_err)?
+    .ok_or_else(|| format!("Konfigurace {id} neexistuje"))
+}
+
+fn diff(
+    conn: &Connection,
+    table: &str,
+    from_id: i64,
+    to_id: i64,
+) -> Result<Vec<ConfigFieldChange>, String> {
+    let from = settings(conn, table, from_id)?;
+    let to = settings(conn, table, to_id)?;
+    Ok(from
+        .into_iter()
+        .zip(to)
+        .filter(|((_, a), (_, b))| a != b)
+        .map(|((field, from), (_, to))| ConfigFieldChange { field, from, to })
+        .collect())
+}
+
+/// Uloží `insert` jako novou verzi konfigurace `config_id` a vrátí ID nové verze.
+fn save_version(
+    conn: &mut Connection,
+    config_type: &str,
+    config_id: i64,
+    insert: impl FnOnce(&Connection) -> rusqlite::Result<()>,
+) -> Result<i64, String> {
+    let (table, _) = tables(config_type)?;
+    let tx = conn.transaction().map_err(db_err)?;
+    let (active, version, old_name): (bool, i64, String) = tx
+        .query_row(
+            &format!("SELECT active, version, confname FROM {table} WHERE id = ?1"),
+            params![config_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .optional()
+        .map_err(db_err)?
+        .ok_or_else(|| format!("Konfigurace {config_id} neexistuje"))?;
+    if !active {
+        return Err("Upravit lze jen aktuální verzi konfigurace".into());
+    }
+
+    insert(&tx).map_err(|e| format!("Error saving config: {e}"))?;
+    let new_id = tx.last_insert_rowid();
+    tx.execute(
+        &format!("UPDATE {table} SET version = ?1, previous_version_id = ?2 WHERE id = ?3"),
+        params![version + 1, config_id, new_id],
+    )
+    .map_err(db_err)?;
+    if diff(&tx, table, config_id, new_id)?.is_empty() {
+        return Err("Konfigurace se nezměnila".into());
+    }
+    tx.execute(
+        &format!("UPDATE {table} SET active = false WHERE id = ?1"),
+        params![config_id],
+    )
+    .map_err(db_err)?;
+
+    write_audit(
+        &tx,
+        &format!(
+            "Konfigurace {config_type} '{old_name}' upravena: v{version} (id {config_id}) -> v{} (id {new_id})",
+            version + 1
+        ),
+    )
+    .map_err(db_err)?;
+    tx.commit().map_err(db_err)?;
+    Ok(new_id)
+}
+
+/// Úprava EWF konfigurace – uloží novou verzi a vrátí její ID.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn edit_ewf_config(config_id: i64, config: NewConfig) -> Result<i64, ConfigError> {
+    validate_ewf_config(&config)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        save_version(pooled.connection(), "ewf", config_id, |conn| {
+            save_ewf_config(conn, config)
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+    .map_err(ConfigError::from)
+}
+
+/// Úprava dd konfigurace – uloží novou verzi a vrátí její ID.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn edit_dd_config(config_id: i64, config: NewDDConfig) -> Result<i64, ConfigError> {
+    validate_dd_config(&config)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        save_version(pooled.connection(), "dd", config_id, |conn| {
+            save_dd_config(conn, config)
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+    .map_err(ConfigError::from)
+}
+
+/// Všechny verze presetu, do kterého patří `config_id`, od první po nejnovější.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_config_history(
+    config_type: String,
+    config_id: i64,
+) -> Result<Vec<ConfigVersion>, String> {
+    let (table, log_table) = tables(&config_type)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let conn = pooled.connection();
+        let mut stmt = conn
+            .prepare(&format!(
+                "WITH RECURSIVE
+                    back(id, prev) AS (
+                        SELECT id, previous_version_id FROM {table} WHERE id = ?1
+                        UNION ALL
+                        SELECT c.id, c.previous_version_id FROM {table} c JOIN back ON c.id = back.prev
+                    ),
+                    chain(id) AS (
+                        SELECT id FROM back WHERE prev IS NULL
+                        UNION ALL
+                        SELECT c.id FROM {table} c JOIN chain ON c.previous_version_id = chain.id
+                    )
+                 SELECT c.id, c.version, c.previous_version_id, c.created, c.active, c.confname,
+                        (SELECT COUNT(*) FROM {log_table} l WHERE l.config_id = c.id)
+                 FROM {table} c JOIN chain USING (id)
+                 ORDER BY c.version"
+            ))
+            .map_err(db_err)?;
+        let versions = stmt
+            .query_map(params![config_id], |r| {
+                Ok(ConfigVersion {
+                    id: r.get(0)?,
+                    version: r.get(1)?,
+                    previous_version_id: r.get(2)?,
+                    created: r.get(3)?,
+                    active: r.get(4)?,
+                    confname: r.get(5)?,
+                    used_by: r.get(6)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(db_err)?;
+        if versions.is_empty() {
+            return Err(format!("Konfigurace {config_id} neexistuje"));
+        }
+        Ok(versions)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Rozdíl nastavení mezi dvěma verzemi (jen změněná pole).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn diff_config_versions(
+    config_type: String,
+    from_id: i64,
+    to_id: i64,
+) -> Result<Vec<ConfigFieldChange>, String> {
+    let (table, _) = tables(&config_type)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        diff(pooled.connection(), table, from_id, to_id)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -1,7 +1,6 @@
-use crate::logger::{log_debug, log_error, log_warn};
+use crate::logger::{log_debug, log_error};
 use once_cell::sync::Lazy;
 use serde::Serialize;
-use std::collections::HashMap;
 use std::process::Command;
 use std::str;
 use std::sync::{Arc, Mutex as StdMutex};
@@ -10,7 +9,9 @@ use std::time::Duration;
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
 use tauri::command;
 use udev::Device;
-use crate::config::MOUNT_ROOT;
+use crate::config::mount_root;
+
+pub mod monitor;
 // Konstantní cesta pro mount root – tento adresář vytvořte jednou jako root a změňte vlastníka (např. na "master")
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -22,6 +23,8 @@ pub struct SataDevice {
     pub sector_size: Option<u64>,
     pub side: Option<String>,
     pub mountpoint: Option<String>, // nový údaj pro mountpoint
+    /// Disk byl bezpečně odebrán (`eject_device`) a lze jej vytáhnout
+    pub ejected: bool,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -33,6 +36,8 @@ pub struct UsbDevice {
     pub sector_size: Option<u64>,
     pub side: Option<String>,
     pub mountpoint: Option<String>, // přidaný mountpoint pro USB zařízení
+    /// Disk byl bezpečně odebrán (`eject_device`) a lze jej vytáhnout
+    pub ejected: bool,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -43,10 +48,27 @@ pub struct DeviceStatus {
     pub ram_usage: f32,
 }
 
+/// Disk v šachtě; v JSON `{"bus": "usb" | "sata", "device": {...}}`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "bus", content = "device", rename_all = "lowercase")]
+pub enum BlockDevice {
+    Usb(UsbDevice),
+    Sata(SataDevice),
+}
+
+/// Zprávy websocketu o zařízeních. Úplný stav (`Status`) dostane jen nově
+/// připojený klient, dál chodí jen změny.
 #[derive(Serialize)]
 #[serde(tag = "type", content = "data")]
 pub enum DeviceUpdate {
+    #[serde(rename = "Status")]
     Full(DeviceStatus),
+    DeviceAttached(BlockDevice),
+    DeviceDetached(BlockDevice),
+    DeviceChanged(BlockDevice),
+    /// Disk je odpojený a uspaný / vypnutý, lze jej vytáhnout
+    DeviceEjected(BlockDevice),
+    SystemUsage { cpu_usage: f32, ram_usage: f32 },
 }
 
 // Funkce pro kontrolu, zda je zařízení USB (fallback)
@@ -103,49 +125,6 @@ fn get_disk_sector_info(devnode: &str) -> Option<(u64, u64)> {
     Some((sector_count, sector_size))
 }
 
-// Funkce pro získání seznamu disků pomocí smartctl
-fn get_smartctl_disks() -> Result<Vec<String>, String> {
-    let output = Command::new("smartctl")
-        .arg("--scan")
-        .output()
-        .map_err(|e| {
-            log_error(&format!("Failed to execute smartctl: {}", e));
-            format!("Failed to execute smartctl: {}", e)
-        })?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log_error(&format!(
-            "smartctl exited with status {}: {}",
-            output.status.code().unwrap_or(-1),
-            stderr
-        ));
-        return Err(format!(
-            "smartctl exited with status {}: {}",
-            output.status.code().unwrap_or(-1),
-            stderr
-        ));
-    }
-
-    let stdout = str::from_utf8(&output.stdout).map_err(|e| {
-        log_error(&format!("Invalid UTF-8 output from smartctl: {}", e));
-        format!("Invalid UTF-8 output from smartctl: {}", e)
-    })?;
-
-    let mut disks = Vec::new();
-    for line in stdout.lines() {
-        if let Some(device_path) = line.split_whitespace().next() {
-            disks.push(device_path.to_string());
-        } else {
-            log_warn(&format!(
-                "Unexpected line format in smartctl output: {}",
-                line
-            ));
-        }
-    }
-    Ok(disks)
-}
-
 /// Vrací filesystem type oddílu pomocí příkazu blkid.
 fn get_fs_type(device: &str) -> Option<String> {
     let output = Command::new("sudo")
@@ -216,7 +195,7 @@ fn auto_mount(device: &str) -> Option<String> {
     // Vygenerujeme mountpoint a případně se pokusíme zařízení připojit.
     let mountpoint = format!(
         "{}/{}",
-        MOUNT_ROOT,
+        mount_root(),
         device_to_mount.trim_start_matches("/dev/")
     );
 
@@ -279,248 +258,36 @@ fn auto_mount(device: &str) -> Option<String> {
     }
 }
 
-#[command]
-pub fn get_device_status() -> Result<DeviceStatus, String> {
-    // Získání DB připojení pomocí async mutexu
-    let conn_guard = match crate::db::DB_CONN.try_lock() {
-        Ok(guard) => guard,
-        Err(e) => {
-            log_error(&format!("Failed to acquire DB lock: {}", e));
-            return Err(format!("Failed to acquire DB lock: {}", e));
-        }
-    };
-
-    fn normalize_interface_path(path: &str) -> String {
-        let path = path.replace("usbv3", "usb");
-        if path.ends_with(".0") {
-            path.trim_end_matches(".0").to_string()
-        } else {
-            path
-        }
+/// Sjednotí zápis `ID_PATH` s cestami v tabulce `interface`.
+pub(crate) fn normalize_interface_path(path: &str) -> String {
+    let path = path.replace("usbv3", "usb");
+    if path.ends_with(".0") {
+        path.trim_end_matches(".0").to_string()
+    } else {
+        path
     }
-    
-    let interface_map: HashMap<String, (String, String)> = {
-        let mut stmt = conn_guard
-            .prepare("SELECT interface_path, name, side FROM interface")
-            .map_err(|e| {
-                log_error(&format!("Failed to prepare statement: {}", e));
-                format!("Failed to prepare statement: {}", e)
-            })?;
-
-        let rows = stmt
-            .query_map([], |row| {
-                let path: String = row.get(0)?;
-                let name: String = row.get(1)?;
-                let side: String = row.get(2)?;
-                Ok((path, name, side))
-            })
-            .map_err(|e| {
-                log_error(&format!("Failed to query allowed interfaces: {}", e));
-                format!("Failed to query allowed interfaces: {}", e)
-            })?;
+}
 
-        let mut map = HashMap::new();
-        for row in rows {
-            if let Ok((path, name, side)) = row {
-                map.insert(normalize_interface_path(&path), (name, side));
-            }
+/// Aktuální vytížení CPU a RAM v procentech.
+pub fn system_usage() -> (f32, f32) {
+    let system = match SYSTEM_INFO.lock() {
+        Ok(system) => system,
+        Err(e) => {
+            log_error(&format!("Failed to acquire system lock: {}", e));
+            return (0.0, 0.0);
         }
-        map
     };
-
-    // Systémové informace – CPU a RAM.
-    let system = SYSTEM_INFO.lock().map_err(|e| {
-        log_error(&format!("Failed to acquire system lock: {}", e));
-        format!("Failed to acquire system lock: {}", e)
-    })?;
-    let cpu_usage = system.global_cpu_usage();
-    let used_memory = system.used_memory();
     let total_memory = system.total_memory();
     let ram_usage = if total_memory > 0 {
-        (used_memory as f32 / total_memory as f32) * 100.0
+        (system.used_memory() as f32 / total_memory as f32) * 100.0
     } else {
         0.0
     };
+    (system.global_cpu_usage(), ram_usage)
+}
 
-    // Připrava enumerátoru pro bloková zařízení.
-    let mut enumerator = udev::Enumerator::new().map_err(|e| {
-        log_error(&format!("Failed to create udev enumerator: {}", e));
-        format!("Failed to create udev enumerator: {}", e)
-    })?;
-    enumerator.match_subsystem("block").map_err(|e| {
-        log_error(&format!("Failed to match block subsystem: {}", e));
-        format!("Failed to match block subsystem: {}", e)
-    })?;
-
-    let mut usb_devices = Vec::new();
-    // Použijeme HashSet pro sledování serialů, abychom deduplikovali zařízení.
-    let mut seen_serials: std::collections::HashSet<String> = std::collections::HashSet::new();
-
-    // Projdeme všechna bloková zařízení a detekujeme USB zařízení.
-    for device in enumerator.scan_devices().map_err(|e| {
-        log_error(&format!("Failed to scan block devices: {}", e));
-        format!("Failed to scan block devices: {}", e)
-    })? {
-        let devtype = device
-            .property_value("DEVTYPE")
-            .and_then(|v| v.to_str())
-            .unwrap_or("");
-        if devtype != "disk" {
-            continue;
-        }
-        // Získáme fyzický interface pomocí vlastnosti "ID_PATH".
-        let interface_id =
-            if let Some(id) = device.property_value("ID_PATH").and_then(|v| v.to_str()) {
-                id.to_string()
-            } else {
-                continue;
-            };
-
-        // Zařízení zahrneme pouze, pokud je jeho fyzický interface v interface_map.
-        if !interface_map.contains_key(&interface_id) {
-            continue;
-        }
-
-        // Získáme unikátní identifikátor (serial) – preferujeme "ID_SERIAL_SHORT", případně "ID_SERIAL".
-        let serial = device
-            .property_value("ID_SERIAL_SHORT")
-            .or_else(|| device.property_value("ID_SERIAL"))
-            .and_then(|v| v.to_str())
-            .map(|s| s.to_string());
-
-        // Pokud je serial známý a již jsme ho viděli, přeskočíme zařízení.
-        if let Some(ref s) = serial {
-            if seen_serials.contains(s) {
-                continue;
-            } else {
-                seen_serials.insert(s.clone());
-            }
-        }
-
-        let id_bus = device
-            .property_value("ID_BUS")
-            .and_then(|v| v.to_str())
-            .unwrap_or("");
-        if id_bus == "usb" || is_usb_mass_storage(&device) {
-            if let Some(devnode_str) = device.devnode().map(|d| d.to_string_lossy().to_string()) {
-                let (sector_count, sector_size) =
-                    get_disk_sector_info(&devnode_str).unwrap_or((0, 512));
-                // Získáme hodnotu "side" z databáze.
-                let side_val = interface_map
-                    .get(&interface_id)
-                    .map(|(_, side)| side.clone());
-                // Pokud je "side" rovno "output", pokusíme se zařízení automaticky připojit.
-                let mountpoint = if let Some(ref side) = side_val {
-                    if side == "output" {
-                        auto_mount(&devnode_str)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
-
-                usb_devices.push(UsbDevice {
-                    interface: interface_id.clone(),
-                    serial: serial.clone(),
-                    name: interface_map
-                        .get(&interface_id)
-                        .map(|(name, _)| name.clone()),
-                    sector_count: Some(sector_count),
-                    sector_size: Some(sector_size),
-                    side: side_val,
-                    mountpoint, // připojení USB zařízení
-                });
-            } else {
-                log_warn(&format!(
-                    "USB zařízení s interface {} nemá dostupný devnode, přeskočeno.",
-                    interface_id
-                ));
-            }
-        }
-    }
-
-    // Pomocná funkce: získá ID_PATH a serial pro zařízení podle jeho devnode.
-    fn get_id_info_for_devnode(devnode: &str) -> Option<(String, Option<String>)> {
-        let mut enumerator = udev::Enumerator::new().ok()?;
-        enumerator.match_subsystem("block").ok()?;
-        for device in enumerator.scan_devices().ok()? {
-            if let Some(node) = device.devnode() {
-                if node.to_string_lossy() == devnode {
-                    let id_path = device
-                        .property_value("ID_PATH")
-                        .and_then(|v| v.to_str())
-                        .map(|s| s.to_string())?;
-                    let serial = device
-                        .property_value("ID_SERIAL_SHORT")
-                        .or_else(|| device.property_value("ID_SERIAL"))
-                        .and_then(|v| v.to_str())
-                        .map(|s| s.to_string());
-                    return Some((id_path, serial));
-                }
-            }
-        }
-        None
-    }
-
-    // Pro SATA zařízení využijeme smartctl, které vrací devnode (např. "/dev/sda").
-    let smartctl_disks = get_smartctl_disks()?;
-    let sata_devices: Vec<SataDevice> = smartctl_disks
-        .into_iter()
-        .filter(|disk| disk.starts_with("/dev/sd"))
-        .filter_map(|disk| {
-            if let Some((id_path, serial)) = get_id_info_for_devnode(&disk) {
-                // Normalizace ID_PATH pro konzistenci.
-                let normalized_id_path = normalize_interface_path(&id_path);
-                if interface_map.contains_key(&normalized_id_path) {
-                    // Pokud je serial znám a už jsme jej viděli jako USB, přeskočíme.
-                    if let Some(ref s) = serial {
-                        if seen_serials.contains(s) {
-                            return None;
-                        }
-                    }
-                    // Zvolíme nejvhodnější oddíl – pokud existuje, jinak celý disk.
-                    let disk_to_mount = choose_partition(&disk).unwrap_or_else(|| disk.clone());
-                    // Získání sektorových informací z vybraného oddílu nebo disku.
-                    let (sector_count, sector_size) =
-                        get_disk_sector_info(&disk_to_mount).unwrap_or((0, 512));
-                    // Použijeme normalizovanou hodnotu při získávání "side" z databáze.
-                    let side_val = interface_map
-                        .get(&normalized_id_path)
-                        .map(|(_name, side)| side.clone());
-                    // Pokud je "side" rovno "output", pokusíme se disk automaticky připojit.
-                    let mountpoint = if let Some(ref side) = side_val {
-                        if side == "output" {
-                            auto_mount(&disk_to_mount)
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    };
-
-                    Some(SataDevice {
-                        interface: normalized_id_path.clone(),
-                        serial,
-                        name: interface_map.get(&normalized_id_path).map(|(name, _)| name.clone()),
-                        sector_count: Some(sector_count),
-                        sector_size: Some(sector_size),
-                        side: side_val,
-                        mountpoint,
-                    })                    
-                } else {
-                    None
-                }
-            } else {
-                log_debug(&format!("ID_PATH pro disk {} nebyl nalezen.", disk));
-                None
-            }
-        })
-        .collect();
-    Ok(DeviceStatus {
-        usb_devices,
-        sata_devices,
-        cpu_usage,
-        ram_usage,
-    })
+/// Stav zařízení z registru, který udržuje `monitor` podle událostí udev.
+#[command]
+pub fn get_device_status() -> Result<DeviceStatus, String> {
+    Ok(monitor::snapshot())
 }
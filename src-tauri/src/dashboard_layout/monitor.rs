@@ -0,0 +1,422 @@
+//! Registr disků v šachtách udržovaný podle událostí udev.
+//!
+//! Při startu se disky jednou projdou enumerátorem, dál se registr mění jen
+//! na událost `add` / `change` / `remove` z podsystému `block`. Každá změna
+//! se rozešle klientům websocketu jako `DeviceAttached` / `DeviceDetached` /
+//! `DeviceChanged`; úplný stav dostane jen nově připojený klient.
+//!
+//! Disk viditelný přes více cest (multipath) se hlásí jednou: další cesty se
+//! stejnou identitou (`identity`) čekají v `standby` a po odebrání aktivní
+//! cesty ji nahradí.
+use super::{
+    auto_mount, get_disk_sector_info, is_usb_mass_storage, normalize_interface_path, system_usage,
+    BlockDevice, DeviceStatus, DeviceUpdate, SataDevice, UsbDevice,
+};
+use crate::db::DB_POOL;
+use crate::logger::{log_debug, log_error};
+use crate::websocket::broadcast_message;
+use once_cell::sync::Lazy;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use udev::{Device, EventType};
+
+#[derive(Default)]
+struct Registry {
+    /// Normalizovaná `interface_path` -> (název, strana)
+    interfaces: HashMap<String, (String, String)>,
+    /// syspath disku -> zařízení
+    devices: BTreeMap<String, BlockDevice>,
+    /// Další cesty k disku, který už je v `devices` (syspath -> zařízení)
+    standby: BTreeMap<String, BlockDevice>,
+    /// syspath -> identita fyzického disku (jen disky, které ji mají)
+    identities: HashMap<String, String>,
+}
+
+impl Registry {
+    /// Jiná aktivní cesta ke stejnému fyzickému disku.
+    fn active_path_of(&self, identity: &str, except: &str) -> Option<&String> {
+        self.devices.keys().find(|path| {
+            *path != except && self.identities.get(*path).map(String::as_str) == Some(identity)
+        })
+    }
+}
+
+/// Disk v šachtě a identita fyzického disku.
+struct Seen {
+    device: BlockDevice,
+    identity: Option<String>,
+}
+
+static REGISTRY: Lazy<Mutex<Registry>> = Lazy::new(|| Mutex::new(Registry::default()));
+static STARTED: AtomicBool = AtomicBool::new(false);
+/// Disky (devnode), které se právě nesmí automaticky připojovat
+static HELD: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+/// Bezpečně odebrané disky (syspath); nepřipojují se, dokud je někdo nevytáhne
+static EJECTED: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Po dobu existence brání automatickému připojení disku (např. při
+/// formátování, kdy udev hlásí nové oddíly ještě bez filesystému).
+pub struct MountHold(String);
+
+impl Drop for MountHold {
+    fn drop(&mut self) {
+        HELD.lock().unwrap().remove(&self.0);
+    }
+}
+
+pub fn hold_auto_mount(devnode: &str) -> MountHold {
+    HELD.lock().unwrap().insert(devnode.to_string());
+    MountHold(devnode.to_string())
+}
+
+fn load_interfaces() -> Result<HashMap<String, (String, String)>, String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let mut stmt = pooled
+        .connection()
+        .prepare("SELECT interface_path, name, side FROM interface")
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                normalize_interface_path(&row.get::<_, String>(0)?),
+                (row.get(1)?, row.get(2)?),
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<HashMap<_, _>>>())
+        .map_err(|e| format!("Failed to query allowed interfaces: {}", e))?;
+    Ok(rows)
+}
+
+fn property(dev: &Device, key: &str) -> Option<String> {
+    dev.property_value(key)
+        .and_then(|v| v.to_str())
+        .map(|s| s.to_string())
+}
+
+fn syspath(dev: &Device) -> String {
+    dev.syspath().to_string_lossy().to_string()
+}
+
+/// Disk, ke kterému zařízení patří (oddíl -> nadřazený disk).
+fn disk_of(dev: &Device) -> Option<Device> {
+    match property(dev, "DEVTYPE").as_deref() {
+        Some("disk") => Some(dev.clone()),
+        Some("partition") => dev.parent(),
+        _ => None,
+    }
+}
+
+/// Identita fyzického disku společná všem jeho cestám: WWN, u cest
+/// označených multipathd (`DM_MULTIPATH_DEVICE_PATH`) i jeho WWID
+/// (`ID_SERIAL`). Samotné sériové číslo nestačí – klony a levné USB můstky
+/// jej sdílejí i mezi různými disky.
+fn identity(disk: &Device) -> Option<String> {
+    property(disk, "ID_WWN_WITH_EXTENSION")
+        .or_else(|| property(disk, "ID_WWN"))
+        .or_else(|| {
+            (property(disk, "DM_MULTIPATH_DEVICE_PATH").as_deref() == Some("1"))
+                .then(|| property(disk, "ID_SERIAL"))
+                .flatten()
+        })
+}
+
+/// Popis disku, pokud je v některé ze šachet. Výstupní disk se rovnou
+/// připojí, pokud nejde o další cestu k už připojenému disku.
+fn describe(disk: &Device, interfaces: &HashMap<String, (String, String)>) -> Option<Seen> {
+    let interface = normalize_interface_path(&property(disk, "ID_PATH")?);
+    let (name, side) = interfaces.get(&interface)?.clone();
+    let devnode = disk.devnode()?.to_string_lossy().to_string();
+    let serial = property(disk, "ID_SERIAL_SHORT").or_else(|| property(disk, "ID_SERIAL"));
+    let identity = identity(disk);
+    let (sector_count, sector_size) = get_disk_sector_info(&devnode).unwrap_or((0, 512));
+    let held = HELD.lock().unwrap().contains(&devnode);
+    let path = syspath(disk);
+    let ejected = EJECTED.lock().unwrap().contains(&path);
+    let standby = identity
+        .as_deref()
+        .is_some_and(|id| REGISTRY.lock().unwrap().active_path_of(id, &path).is_some());
+    let mountpoint = if side == "output" && !held && !ejected && !standby {
+        auto_mount(&devnode)
+    } else {
+        None
+    };
+
+    let usb = property(disk, "ID_BUS").as_deref() == Some("usb") || is_usb_mass_storage(disk);
+    let device = if usb {
+        BlockDevice::Usb(UsbDevice {
+            interface,
+            serial,
+            name: Some(name),
+            sector_count: Some(sector_count),
+            sector_size: Some(sector_size),
+            side: Some(side),
+            mountpoint,
+            ejected,
+        })
+    } else {
+        BlockDevice::Sata(SataDevice {
+            interface,
+            serial,
+            name: Some(name),
+            sector_count: Some(sector_count),
+            sector_size: Some(sector_size),
+            side: Some(side),
+            mountpoint,
+            ejected,
+        })
+    };
+    Some(Seen { device, identity })
+}
+
+/// Odebere disk `syspath` z registru. Pokud šlo o aktivní cestu k disku
+/// s další cestou ve `standby`, ta jej nahradí (a případně se připojí).
+fn detach(syspath: String) -> Vec<DeviceUpdate> {
+    let (mut updates, next) = {
+        let mut registry = REGISTRY.lock().unwrap();
+        registry.standby.remove(&syspath);
+        let identity = registry.identities.remove(&syspath);
+        let Some(old) = registry.devices.remove(&syspath) else {
+            return Vec::new();
+        };
+        // vytažený disk se po dalším vložení zase připojuje
+        let ejected = EJECTED.lock().unwrap().remove(&syspath);
+        let next = identity.and_then(|id| {
+            registry
+                .standby
+                .keys()
+                .find(|path| registry.identities.get(*path) == Some(&id))
+                .cloned()
+        });
+        if let Some(path) = &next {
+            registry.standby.remove(path);
+            // odebraný disk zůstává odebraný i přes další cestu
+            if ejected {
+                EJECTED.lock().unwrap().insert(path.clone());
+            }
+        }
+        (vec![DeviceUpdate::DeviceDetached(old)], next)
+    };
+    if let Some(path) = next {
+        let interfaces = REGISTRY.lock().unwrap().interfaces.clone();
+        if let Ok(disk) = Device::from_syspath(Path::new(&path)) {
+            updates.extend(apply(path, describe(&disk, &interfaces)));
+        }
+    }
+    updates
+}
+
+/// Zapíše nový stav disku `syspath` do registru a vrátí odpovídající události.
+fn apply(syspath: String, seen: Option<Seen>) -> Vec<DeviceUpdate> {
+    let Some(Seen {
+        device: new,
+        identity,
+    }) = seen
+    else {
+        return detach(syspath);
+    };
+    let mut registry = REGISTRY.lock().unwrap();
+    match &identity {
+        Some(id) => registry.identities.insert(syspath.clone(), id.clone()),
+        None => registry.identities.remove(&syspath),
+    };
+    let standby = !registry.devices.contains_key(&syspath)
+        && identity
+            .as_deref()
+            .is_some_and(|id| registry.active_path_of(id, &syspath).is_some());
+    if standby {
+        // stejný disk viditelný přes více cest (multipath) se hlásí jednou
+        registry.standby.insert(syspath, new);
+        return Vec::new();
+    }
+    registry.standby.remove(&syspath);
+    match registry.devices.get(&syspath) {
+        Some(old) if *old == new => Vec::new(),
+        Some(_) => {
+            registry.devices.insert(syspath, new.clone());
+            vec![DeviceUpdate::DeviceChanged(new)]
+        }
+        None => {
+            registry.devices.insert(syspath, new.clone());
+            vec![DeviceUpdate::DeviceAttached(new)]
+        }
+    }
+}
+
+/// Znovu načte šachty a projde všechny disky; vrátí změny oproti registru.
+pub fn rescan() -> Result<Vec<DeviceUpdate>, String> {
+    let interfaces = load_interfaces()?;
+    REGISTRY.lock().unwrap().interfaces = interfaces.clone();
+
+    let mut enumerator =
+        udev::Enumerator::new().map_err(|e| format!("Failed to create udev enumerator: {}", e))?;
+    enumerator
+        .match_subsystem("block")
+        .map_err(|e| format!("Failed to match block subsystem: {}", e))?;
+    let disks: Vec<Device> = enumerator
+        .scan_devices()
+        .map_err(|e| format!("Failed to scan block devices: {}", e))?
+        .filter(|dev| property(dev, "DEVTYPE").as_deref() == Some("disk"))
+        .collect();
+
+    let mut updates = Vec::new();
+    let present: Vec<String> = disks.iter().map(syspath).collect();
+    let gone: Vec<String> = {
+        let registry = REGISTRY.lock().unwrap();
+        registry
+            .devices
+            .keys()
+            .chain(registry.standby.keys())
+            .filter(|path| !present.contains(path))
+            .cloned()
+            .collect()
+    };
+    for path in gone {
+        updates.extend(detach(path));
+    }
+    for disk in &disks {
+        updates.extend(apply(syspath(disk), describe(disk, &interfaces)));
+    }
+    Ok(updates)
+}
+
+fn handle_event(event: &udev::Event) -> Vec<DeviceUpdate> {
+    let event_type = event.event_type();
+    let is_disk = property(event, "DEVTYPE").as_deref() == Some("disk");
+    let Some(disk) = disk_of(event) else {
+        return Vec::new();
+    };
+    if is_disk && event_type == EventType::Remove {
+        return detach(syspath(&disk));
+    }
+    if !matches!(
+        event_type,
+        EventType::Add | EventType::Change | EventType::Remove
+    ) {
+        return Vec::new();
+    }
+    // nový / odebraný oddíl mění mountpoint nadřazeného disku
+    let interfaces = REGISTRY.lock().unwrap().interfaces.clone();
+    apply(syspath(&disk), describe(&disk, &interfaces))
+}
+
+fn publish_blocking(updates: Vec<DeviceUpdate>) {
+    for update in updates {
+        if let Ok(json) = serde_json::to_string(&update) {
+            tauri::async_runtime::block_on(broadcast_message(&json));
+        }
+    }
+}
+
+/// Rozešle změny registru klientům websocketu.
+pub async fn publish(updates: Vec<DeviceUpdate>) {
+    for update in updates {
+        if let Ok(json) = serde_json::to_string(&update) {
+            broadcast_message(&json).await;
+        }
+    }
+}
+
+/// Znovu projde disky po změně šachet a rozešle změny.
+pub async fn rescan_and_publish() {
+    match tauri::async_runtime::spawn_blocking(rescan).await {
+        Ok(Ok(updates)) => publish(updates).await,
+        Ok(Err(e)) => log_error(&format!("Device rescan failed: {}", e)),
+        Err(e) => log_error(&format!("Async error: {}", e)),
+    }
+}
+
+fn monitor_loop() -> Result<(), String> {
+    let socket = udev::MonitorBuilder::new()
+        .and_then(|b| b.match_subsystem("block"))
+        .and_then(|b| b.listen())
+        .map_err(|e| format!("Failed to start udev monitor: {}", e))?;
+    let mut fds = [libc::pollfd {
+        fd: socket.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    }];
+    loop {
+        // socket je neblokující – čekáme, až udev něco pošle
+        if unsafe { libc::poll(fds.as_mut_ptr(), 1, -1) } < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(format!("udev monitor poll failed: {}", err));
+        }
+        let updates: Vec<DeviceUpdate> = socket.iter().flat_map(|e| handle_event(&e)).collect();
+        if !updates.is_empty() {
+            log_debug(&format!("udev: {} změn zařízení", updates.len()));
+            publish_blocking(updates);
+        }
+    }
+}
+
+/// Naplní registr a spustí vlákno s posluchačem udev (jen jednou).
+pub async fn start() -> Result<(), String> {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    let scanned = tauri::async_runtime::spawn_blocking(rescan)
+        .await
+        .map_err(|e| format!("Async error: {}", e))
+        .and_then(|r| r);
+    if let Err(e) = scanned {
+        STARTED.store(false, Ordering::SeqCst);
+        return Err(e);
+    }
+    thread::spawn(|| {
+        if let Err(e) = monitor_loop() {
+            log_error(&e);
+        }
+    });
+    Ok(())
+}
+
+/// Označí disk `devnode` jako bezpečně odebraný: do vytažení se nepřipojí
+/// a klienti dostanou `DeviceEjected`. Volá se po odpojení a uspání disku.
+pub fn mark_ejected(devnode: &str) -> Option<DeviceUpdate> {
+    let name = devnode.trim_start_matches("/dev/");
+    let path = std::fs::canonicalize(format!("/sys/class/block/{}", name))
+        .ok()?
+        .to_string_lossy()
+        .to_string();
+    EJECTED.lock().unwrap().insert(path.clone());
+    let mut registry = REGISTRY.lock().unwrap();
+    let device = registry.devices.get_mut(&path)?;
+    match device {
+        BlockDevice::Usb(d) => {
+            d.mountpoint = None;
+            d.ejected = true;
+        }
+        BlockDevice::Sata(d) => {
+            d.mountpoint = None;
+            d.ejected = true;
+        }
+    }
+    Some(DeviceUpdate::DeviceEjected(device.clone()))
+}
+
+/// Úplný stav pro nově připojeného klienta nebo `get_device_status`.
+pub fn snapshot() -> DeviceStatus {
+    let registry = REGISTRY.lock().unwrap();
+    let mut usb_devices = Vec::new();
+    let mut sata_devices = Vec::new();
+    for device in registry.devices.values() {
+        match device {
+            BlockDevice::Usb(d) => usb_devices.push(d.clone()),
+            BlockDevice::Sata(d) => sata_devices.push(d.clone()),
+        }
+    }
+    let (cpu_usage, ram_usage) = system_usage();
+    DeviceStatus {
+        usb_devices,
+        sata_devices,
+        cpu_usage,
+        ram_usage,
+    }
+}
@@ -0,0 +1,292 @@
+//! Záloha, obnova a kontrola integrity databáze jednotky.
+//!
+//! Záloha se pořizuje online (SQLite backup API) do souboru
+//! `cratec-db-<YYYYmmdd-HHMMSS>.db` vedle kterého leží `<soubor>.sha256`
+//! ve formátu `sha256sum`. Obnova vyžaduje oba soubory.
+use super::migrations;
+use super::{create_new_connection, DB_POOL};
+use crate::config::database_path;
+use crate::disk_utils::writers::write_guard;
+use crate::lockscreen::require_admin;
+use crate::logger::{log_error, log_warn, write_audit};
+use crate::report::{sha256_hex, template::mounted_file, validate_output_mount};
+use once_cell::sync::Lazy;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use serde::Serialize;
+use std::fs;
+use std::process::Command;
+use std::sync::RwLock;
+
+/// Výsledek posledního `PRAGMA integrity_check` / `foreign_key_check`.
+static LAST_INTEGRITY: Lazy<RwLock<Option<IntegrityReport>>> = Lazy::new(|| RwLock::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub checked_at: String,
+    /// Výstup `integrity_check` (`["ok"]` pro neporušenou databázi)
+    pub integrity: Vec<String>,
+    pub foreign_key_violations: Vec<ForeignKeyViolation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatabaseBackup {
+    pub path: String,
+    pub sha256: String,
+    pub size: u64,
+    pub schema_version: i64,
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+fn integrity_report(conn: &Connection) -> rusqlite::Result<IntegrityReport> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let integrity = stmt
+        .query_map([], |r| r.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check")?;
+    let foreign_key_violations = stmt
+        .query_map([], |r| {
+            Ok(ForeignKeyViolation {
+                table: r.get(0)?,
+                rowid: r.get(1)?,
+                parent: r.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(IntegrityReport {
+        ok: integrity == ["ok"] && foreign_key_violations.is_empty(),
+        checked_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        integrity,
+        foreign_key_violations,
+    })
+}
+
+/// Zkontroluje databázi a výsledek si zapamatuje pro system info.
+/// Volá se při startu aplikace.
+pub fn run_integrity_check() -> Result<IntegrityReport, String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let report = integrity_report(pooled.connection()).map_err(db_err)?;
+    if !report.ok {
+        log_error(&format!(
+            "Kontrola integrity databáze selhala: {:?}, porušené cizí klíče: {}",
+            report.integrity,
+            report.foreign_key_violations.len()
+        ));
+    }
+    *LAST_INTEGRITY.write().unwrap() = Some(report.clone());
+    Ok(report)
+}
+
+/// Výsledek poslední kontroly integrity (`None`, pokud ještě neproběhla).
+pub fn last_integrity_report() -> Option<IntegrityReport> {
+    LAST_INTEGRITY.read().unwrap().clone()
+}
+
+fn sudo_cp(src: &str, dst: &str) -> Result<(), String> {
+    let status = Command::new("sudo")
+        .arg("cp")
+        .arg(src)
+        .arg(dst)
+        .status()
+        .map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("sudo cp failed with status {:?}", status.code()));
+    }
+    Ok(())
+}
+
+fn create_backup(mount: &str) -> Result<DatabaseBackup, String> {
+    let file_name = format!(
+        "cratec-db-{}.db",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let tmp_path = format!("/tmp/{file_name}");
+    let _ = fs::remove_file(&tmp_path);
+
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let schema_version = migrations::db_version(conn).map_err(db_err)?;
+    conn.backup(DatabaseName::Main, &tmp_path, None)
+        .map_err(|e| format!("Záloha databáze selhala: {e}"))?;
+
+    let result = (|| {
+        // záloha má být samostatný soubor bez -wal/-shm
+        Connection::open(&tmp_path)
+            .and_then(|c| c.pragma_update(None, "journal_mode", "DELETE"))
+            .map_err(db_err)?;
+        let data = fs::read(&tmp_path).map_err(|e| e.to_string())?;
+        let sha256 = sha256_hex(&data);
+        let sha_path = format!("{tmp_path}.sha256");
+        fs::write(&sha_path, format!("{sha256}  {file_name}\n")).map_err(|e| e.to_string())?;
+
+        let dest_dir = format!("{mount}/cratec-backup");
+        let status = Command::new("sudo")
+            .args(["mkdir", "-p", &dest_dir])
+            .status()
+            .map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("Nelze vytvořit {dest_dir}"));
+        }
+        let dest = format!("{dest_dir}/{file_name}");
+        sudo_cp(&tmp_path, &dest)?;
+        sudo_cp(&sha_path, &format!("{dest}.sha256"))?;
+        let _ = fs::remove_file(&sha_path);
+        let _ = Command::new("sync").status();
+
+        write_audit(
+            conn,
+            &format!(
+                "Záloha databáze (schéma v{schema_version}) uložena do {dest}, sha256 {sha256}"
+            ),
+        )
+        .map_err(db_err)?;
+        Ok(DatabaseBackup {
+            path: dest,
+            sha256,
+            size: data.len() as u64,
+            schema_version,
+        })
+    })();
+    let _ = fs::remove_file(&tmp_path);
+    result
+}
+
+/// Ověří zálohu před obnovou: hash ze souboru `.sha256`, integritu a verzi schématu.
+fn validate_backup(path: &str) -> Result<(String, i64), String> {
+    let data = fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let sha_file = fs::read_to_string(format!("{path}.sha256"))
+        .map_err(|_| format!("Chybí soubor {path}.sha256"))?;
+    let expected = sha_file
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let sha256 = sha256_hex(&data);
+    if sha256 != expected {
+        return Err("Záloha neodpovídá svému SHA-256".into());
+    }
+
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("Záloha nejde otevřít: {e}"))?;
+    if !migrations::has_app_tables(&conn).map_err(db_err)? {
+        return Err("Soubor není databáze CRATEC".into());
+    }
+    let version = migrations::db_version(&conn).map_err(db_err)?;
+    if version > migrations::current_version() {
+        return Err(format!(
+            "Záloha má schéma v{version}, tato verze aplikace podporuje nejvýše v{}",
+            migrations::current_version()
+        ));
+    }
+    let report = integrity_report(&conn).map_err(db_err)?;
+    if !report.ok {
+        return Err(format!("Záloha je poškozená: {:?}", report.integrity));
+    }
+    Ok((sha256, version))
+}
+
+/// Online záloha databáze do `cratec-backup/` na připojeném výstupu. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn backup_database(target: String, admin_code: String) -> Result<DatabaseBackup, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mount = validate_output_mount(&target)?;
+        let _writing = write_guard(&mount)?;
+        create_backup(&mount)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Obnoví databázi ze zálohy na připojeném výstupu. Současná databáze se předtím
+/// zkopíruje vedle sebe, starší schéma zálohy se hned zmigruje. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn restore_database(path: String, admin_code: String) -> Result<IntegrityReport, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let src = mounted_file(&path)?.to_string_lossy().to_string();
+        // ověřuje se a obnovuje z lokální kopie, aby šlo o tentýž obsah
+        let local = "/tmp/cratec_restore.db";
+        fs::copy(&src, local).map_err(|e| format!("{src}: {e}"))?;
+        fs::copy(format!("{src}.sha256"), format!("{local}.sha256"))
+            .map_err(|_| format!("Chybí soubor {src}.sha256"))?;
+        let restored = validate_backup(local)
+            .and_then(|(sha256, version)| restore_from(local, &src, &sha256, version));
+        let _ = fs::remove_file(local);
+        let _ = fs::remove_file(format!("{local}.sha256"));
+        restored
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Nahradí databázi ověřenou zálohou `local` (kopie `src`).
+fn restore_from(
+    local: &str,
+    src: &str,
+    sha256: &str,
+    version: i64,
+) -> Result<IntegrityReport, String> {
+    let mut conn = create_new_connection().map_err(|e| e.to_string())?;
+    let running: i64 = conn
+        .query_row(
+            "SELECT (SELECT COUNT(*) FROM copy_process WHERE status = 'running')
+                  + (SELECT COUNT(*) FROM wipe_job WHERE status = 'running')",
+            [],
+            |r| r.get(0),
+        )
+        .map_err(db_err)?;
+    if running > 0 {
+        return Err("Během akvizice nebo mazání disku nelze databázi obnovit".into());
+    }
+    let safety_copy = format!(
+        "{}.pre-restore-{}.bak",
+        database_path(),
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    migrations::backup_to(&conn, &safety_copy).map_err(db_err)?;
+    conn.restore(
+        DatabaseName::Main,
+        local,
+        None::<fn(rusqlite::backup::Progress)>,
+    )
+    .map_err(|e| format!("Obnova databáze selhala: {e}"))?;
+    drop(conn);
+
+    // záloha ze starší verze se dotáhne na aktuální schéma
+    super::initialize_db().map_err(|e| e.to_string())?;
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    write_audit(
+            pooled.connection(),
+            &format!(
+                "Databáze obnovena ze zálohy {src} (schéma v{version}, sha256 {sha256}), předchozí stav v {safety_copy}"
+            ),
+        )
+        .map_err(db_err)?;
+    drop(pooled);
+
+    let report = run_integrity_check()?;
+    if !report.ok {
+        log_warn("Obnovená databáze neprošla kontrolou integrity");
+    }
+    Ok(report)
+}
+
+/// Spustí `PRAGMA integrity_check` a `foreign_key_check`. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn check_database_integrity(admin_code: String) -> Result<IntegrityReport, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(run_integrity_check)
+        .await
+        .map_err(|e| format!("Async error: {}", e))?
+}
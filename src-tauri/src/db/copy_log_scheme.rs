@@ -27,6 +27,7 @@ pub fn initialize_copy_log_scheme(conn: &Connection) -> Result<()> {
             md5_hash TEXT DEFAULT NULL, 
             sha1_hash TEXT DEFAULT NULL,
             sha256_hash TEXT DEFAULT NULL,
+            effective_config TEXT DEFAULT NULL,
             FOREIGN KEY(config_id) REFERENCES ewf_config(id) ON DELETE CASCADE,
             FOREIGN KEY(source_disk_id) REFERENCES interface(id) ON DELETE CASCADE,
             FOREIGN KEY(dest_disk_id) REFERENCES interface(id) ON DELETE CASCADE,
@@ -65,6 +66,7 @@ pub fn initialize_copy_log_scheme(conn: &Connection) -> Result<()> {
             sha256_hash TEXT DEFAULT NULL,
             sha384_hash TEXT DEFAULT NULL,
             sha512_hash TEXT DEFAULT NULL,
+            effective_config TEXT DEFAULT NULL,
             FOREIGN KEY(config_id) REFERENCES dd_config(id) ON DELETE CASCADE,
             FOREIGN KEY(source_disk_id) REFERENCES interface(id) ON DELETE CASCADE,
             FOREIGN KEY(dest_disk_id) REFERENCES interface(id) ON DELETE CASCADE,
@@ -6,6 +6,8 @@ pub fn initialize_dd_config_scheme(conn: &Connection) -> Result<()> {
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
             active BOOLEAN NOT NULL DEFAULT true,
+            version INTEGER NOT NULL DEFAULT 1,
+            previous_version_id INTEGER REFERENCES dd_config(id),
             confname TEXT NOT NULL,
             format TEXT NOT NULL DEFAULT '512',
             limit_mode TEXT NOT NULL DEFAULT 'whole'
@@ -19,7 +21,8 @@ pub fn initialize_dd_config_scheme(conn: &Connection) -> Result<()> {
             diffwr BOOLEAN NOT NULL DEFAULT 0
                 CHECK(diffwr IN (0,1)),
             notes TEXT NOT NULL DEFAULT 'ask'
-                CHECK(notes IN ('ask','none'))
+                CHECK(notes IN ('ask','none')),
+            field_policy TEXT NOT NULL DEFAULT '{}'
         )"#,
         [],
     )?;
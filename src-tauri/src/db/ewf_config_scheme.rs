@@ -9,6 +9,8 @@ pub fn initialize_ewf_config_scheme(conn: &Connection) -> Result<()> {
             id INTEGER PRIMARY KEY,
             created DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
             active BOOLEAN NOT NULL DEFAULT true,
+            version INTEGER NOT NULL DEFAULT 1,
+            previous_version_id INTEGER REFERENCES ewf_config(id),
             confname TEXT NOT NULL,
             codepage TEXT NOT NULL DEFAULT 'ascii'
                 CHECK(codepage IN ('ascii','windows-874','windows-932','windows-936','windows-949','windows-950','windows-1250','windows-1251','windows-1252','windows-1253','windows-1254','windows-1255','windows-1256','windows-1257','windows-1258')),
@@ -33,7 +35,8 @@ pub fn initialize_ewf_config_scheme(conn: &Connection) -> Result<()> {
             swap_byte_pairs BOOLEAN NOT NULL DEFAULT false,
             segment_size TEXT NOT NULL DEFAULT '1.4 GiB',
             zero_on_read_error BOOLEAN NOT NULL DEFAULT false,
-            use_chunk_data BOOLEAN NOT NULL DEFAULT false
+            use_chunk_data BOOLEAN NOT NULL DEFAULT false,
+            field_policy TEXT NOT NULL DEFAULT '{}'
         )"#,
         [],
     )?;
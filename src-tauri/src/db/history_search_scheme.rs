@@ -0,0 +1,66 @@
+use rusqlite::{Connection, Result};
+
+/// Fulltextový index historie (FTS5) nad popisnými poli akvizice.
+///
+/// `rowid` je `copy_process.id`; index plní triggery nad `copy_process`.
+/// Pokud systémové SQLite nemá FTS5, index se nevytvoří a vyhledávání
+/// v historii použije `LIKE`.
+pub fn initialize_history_search_scheme(conn: &Connection) -> Result<()> {
+    if let Err(e) = conn.execute(
+        r#"
+        CREATE VIRTUAL TABLE IF NOT EXISTS history_fts USING fts5(
+            case_number,
+            evidence_number,
+            investigator_name,
+            description,
+            notes,
+            tokenize = 'unicode61 remove_diacritics 2'
+        )
+        "#,
+        [],
+    ) {
+        println!("FTS5 is not available, history search falls back to LIKE: {}", e);
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS trg_history_fts_insert
+        AFTER INSERT ON copy_process
+        BEGIN
+            INSERT INTO history_fts(rowid, case_number, evidence_number, investigator_name, description, notes)
+            SELECT NEW.id, case_number, evidence_number, investigator_name, description, COALESCE(notes, '')
+            FROM copy_log_ewf WHERE id = NEW.triggered_by_ewf
+            UNION ALL
+            SELECT NEW.id, case_number, evidence_number, investigator_name, description, COALESCE(notes, '')
+            FROM copy_log_dd WHERE id = NEW.triggered_by_dd AND NEW.triggered_by_ewf IS NULL;
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_history_fts_delete
+        AFTER DELETE ON copy_process
+        BEGIN
+            DELETE FROM history_fts WHERE rowid = OLD.id;
+        END;
+        "#,
+    )?;
+
+    // Doplnění indexu pro procesy vzniklé před jeho zavedením
+    conn.execute(
+        r#"
+        INSERT INTO history_fts(rowid, case_number, evidence_number, investigator_name, description, notes)
+        SELECT p.id,
+               COALESCE(e.case_number, d.case_number),
+               COALESCE(e.evidence_number, d.evidence_number),
+               COALESCE(e.investigator_name, d.investigator_name),
+               COALESCE(e.description, d.description),
+               COALESCE(e.notes, d.notes, '')
+        FROM copy_process p
+        LEFT JOIN copy_log_ewf e ON e.id = p.triggered_by_ewf
+        LEFT JOIN copy_log_dd d ON d.id = p.triggered_by_dd
+        WHERE p.id NOT IN (SELECT rowid FROM history_fts)
+        "#,
+        [],
+    )?;
+
+    Ok(())
+}
@@ -11,20 +11,12 @@ pub fn initialize_interface_scheme(conn: &Connection) -> Result<()> {
         [],
     )?;
 
-    let interfaces = [
-        ("pci-0000:03:00.0-ata-1", "input",  "IN 1"),
-        ("pci-0000:03:00.0-ata-2", "input",  "IN 2"),
-        ("pci-0000:03:00.0-ata-3", "output", "OUT 1"),
-        ("pci-0000:03:00.0-ata-4", "output", "OUT 2"),
-        ("pci-0000:00:14.0-usb-0:1:1.0-scsi-0:0:0:0", "input",  "USB IN"),
-        ("pci-0000:00:14.0-usb-0:2:1.0-scsi-0:0:0:0", "output", "USB OUT"),
-    ];
-
-    for (path, side, name) in interfaces {
+    // šachty dané sestavy jsou v nastavení (`[[bays]]`)
+    for bay in &crate::settings::settings().bays {
         conn.execute(
             "INSERT OR IGNORE INTO interface (interface_path, side, name)
              VALUES (?1, ?2, ?3)",
-            params![path, side, name],
+            params![bay.path, bay.side, bay.name],
         )?;
     }
 
@@ -0,0 +1,318 @@
+//! Dávkový zápis výstupu nástrojů do `process_log_lines`.
+//!
+//! Každý proces má vlastní vlákno s jedním spojením do databáze. Řádky
+//! přicházejí kanálem, čísla řádků se přidělují v paměti a zapisují se
+//! po dávkách v transakci. Každý řádek se před zařazením do dávky připíše
+//! do spool souboru v `PROCESS_LOG_SPOOL_DIR`; po pádu aplikace se
+//! nezapsané řádky při startu doplní ze spoolu (`recover_spools`).
+//! Selhání spoolu nebo databáze vrátí `finish` jako chybu; akvizice ji uloží
+//! do `copy_process.log_error`.
+use super::create_new_connection;
+use crate::config::PROCESS_LOG_SPOOL_DIR;
+use crate::logger::log_error;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Row};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Maximální počet řádků v jedné transakci.
+const BATCH_SIZE: usize = 256;
+/// Nejdelší doba, po kterou řádek čeká v paměti na zápis.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+/// Pokusy o zápis dávky (např. při `SQLITE_BUSY`) před vzdáním.
+const FLUSH_RETRIES: u32 = 5;
+
+struct LogLine {
+    number: i64,
+    timestamp: String,
+    content: String,
+}
+
+/// Zapisovač výstupu jednoho procesu. `push` neblokuje, `finish` dopíše
+/// zbývající řádky a ukončí vlákno.
+pub struct ProcessLogWriter {
+    process_id: i64,
+    tx: Sender<String>,
+    handle: JoinHandle<Result<i64, String>>,
+}
+
+pub(crate) fn spool_path(process_id: i64) -> String {
+    format!("{PROCESS_LOG_SPOOL_DIR}/process-{process_id}.spool")
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Text řádku výpisu; starší záznamy mají výstup uložený jako BLOB.
+pub fn line_text(row: &Row, idx: usize) -> rusqlite::Result<String> {
+    Ok(match row.get_ref(idx)? {
+        ValueRef::Text(s) | ValueRef::Blob(s) => String::from_utf8_lossy(s).to_string(),
+        ValueRef::Null => String::new(),
+        other => format!("{:?}", other),
+    })
+}
+
+fn last_line_number(conn: &Connection, process_id: i64) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(line_number), 0) FROM process_log_lines WHERE process_id = ?1",
+        params![process_id],
+        |r| r.get(0),
+    )
+}
+
+fn write_batch(conn: &mut Connection, process_id: i64, batch: &[LogLine]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO process_log_lines (process_id, line_content, timestamp, line_number)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for line in batch {
+            stmt.execute(params![
+                process_id,
+                line.content,
+                line.timestamp,
+                line.number
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+fn flush(conn: &mut Connection, process_id: i64, batch: &mut Vec<LogLine>) -> Result<(), String> {
+    if batch.is_empty() {
+        return Ok(());
+    }
+    let mut attempt = 0;
+    loop {
+        match write_batch(conn, process_id, batch) {
+            Ok(()) => {
+                batch.clear();
+                return Ok(());
+            }
+            Err(e) if attempt < FLUSH_RETRIES => {
+                attempt += 1;
+                eprintln!("Process {process_id} log batch failed (attempt {attempt}): {e}");
+                std::thread::sleep(FLUSH_INTERVAL);
+            }
+            // řádky zůstávají ve spoolu a doplní se při příštím startu
+            Err(e) => return Err(format!("Zápis výpisu procesu {process_id} selhal: {e}")),
+        }
+    }
+}
+
+impl ProcessLogWriter {
+    pub fn start(process_id: i64) -> Result<Self, String> {
+        let mut conn =
+            create_new_connection().map_err(|e| format!("(log writer) DB connection: {e}"))?;
+        let mut number = last_line_number(&conn, process_id).map_err(|e| e.to_string())?;
+
+        fs::create_dir_all(PROCESS_LOG_SPOOL_DIR).map_err(|e| e.to_string())?;
+        let mut spool = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(spool_path(process_id))
+            .map_err(|e| format!("(log writer) spool: {e}"))?;
+
+        let (tx, rx) = mpsc::channel::<String>();
+        let handle = std::thread::spawn(move || -> Result<i64, String> {
+            let mut batch: Vec<LogLine> = Vec::with_capacity(BATCH_SIZE);
+            let mut oldest = Instant::now();
+            let mut result = Ok(());
+            // první chyba zápisu spoolu; další řádky už se jen počítají
+            let mut spool_error: Option<String> = None;
+            let mut spool_lost = 0usize;
+            loop {
+                let timeout = FLUSH_INTERVAL.saturating_sub(oldest.elapsed());
+                let closed = match rx.recv_timeout(timeout) {
+                    Ok(content) => {
+                        number += 1;
+                        let line = LogLine {
+                            number,
+                            timestamp: chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                            content,
+                        };
+                        // spool: po pádu aplikace obsahuje i řádky z nezapsané dávky
+                        if let Err(e) = writeln!(
+                            spool,
+                            "{}\t{}\t{}",
+                            line.number,
+                            line.timestamp,
+                            escape(&line.content)
+                        ) {
+                            if spool_error.is_none() {
+                                let message = format!(
+                                    "Spool výpisu procesu {process_id} nelze zapsat od řádku {}: {e}",
+                                    line.number
+                                );
+                                log_error(&message);
+                                spool_error = Some(message);
+                            }
+                            spool_lost += 1;
+                        }
+                        if batch.is_empty() {
+                            oldest = Instant::now();
+                        }
+                        batch.push(line);
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+
+                if closed || batch.len() >= BATCH_SIZE || oldest.elapsed() >= FLUSH_INTERVAL {
+                    result = flush(&mut conn, process_id, &mut batch);
+                    if result.is_err() && !closed {
+                        // další pokus s příští dávkou, řádky se neztrácejí
+                        result = Ok(());
+                    }
+                    oldest = Instant::now();
+                }
+                if closed {
+                    break;
+                }
+            }
+            match (result, spool_error) {
+                (Ok(()), None) => {
+                    drop(spool);
+                    let _ = fs::remove_file(spool_path(process_id));
+                    Ok(number)
+                }
+                // výpis je v databázi celý, jen nebyl chráněný proti pádu
+                (Ok(()), Some(spool_error)) => {
+                    drop(spool);
+                    let _ = fs::remove_file(spool_path(process_id));
+                    Err(format!(
+                        "{spool_error} ({spool_lost} řádků bez zálohy, v databázi jsou všechny)"
+                    ))
+                }
+                (Err(e), None) => Err(e),
+                (Err(e), Some(spool_error)) => Err(format!(
+                    "{e}; {spool_error} – {spool_lost} řádků nelze obnovit"
+                )),
+            }
+        });
+
+        Ok(Self {
+            process_id,
+            tx,
+            handle,
+        })
+    }
+
+    /// Zařadí řádek k zápisu.
+    pub fn push(&self, line: String) {
+        if self.tx.send(line).is_err() {
+            eprintln!("Process {} log writer is not running", self.process_id);
+        }
+    }
+
+    /// Dopíše všechny řádky a vrátí číslo posledního řádku. Chyba znamená,
+    /// že výpis může být neúplný nebo nebyl zálohovaný ve spoolu.
+    pub async fn finish(self) -> Result<i64, String> {
+        let Self { tx, handle, .. } = self;
+        drop(tx);
+        tauri::async_runtime::spawn_blocking(move || {
+            handle
+                .join()
+                .map_err(|_| "Log writer thread panicked".to_string())?
+        })
+        .await
+        .map_err(|e| format!("Async error: {}", e))?
+    }
+}
+
+fn recover_spool(conn: &mut Connection, process_id: i64, file: File) -> Result<usize, String> {
+    let exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM copy_process WHERE id = ?1)",
+            params![process_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        // proces byl mezitím smazán (retence, purge případu)
+        return Ok(0);
+    }
+    let last = last_line_number(conn, process_id).map_err(|e| e.to_string())?;
+    let mut missing = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let mut parts = line.splitn(3, '\t');
+        let (Some(number), Some(timestamp), Some(content)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            // nedopsaný poslední řádek spoolu
+            continue;
+        };
+        match number.parse::<i64>() {
+            Ok(number) if number > last => missing.push(LogLine {
+                number,
+                timestamp: timestamp.to_string(),
+                content: unescape(content),
+            }),
+            _ => {}
+        }
+    }
+    let count = missing.len();
+    flush(conn, process_id, &mut missing)?;
+    Ok(count)
+}
+
+/// Doplní do databáze řádky, které zůstaly jen ve spoolu (pád aplikace
+/// uprostřed dávky). Volá se při startu před přijetím nových procesů.
+pub fn recover_spools() -> Result<usize, String> {
+    let Ok(entries) = fs::read_dir(PROCESS_LOG_SPOOL_DIR) else {
+        return Ok(0);
+    };
+    let mut conn = create_new_connection().map_err(|e| e.to_string())?;
+    let mut recovered = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let process_id = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .and_then(|n| n.strip_prefix("process-"))
+            .and_then(|n| n.strip_suffix(".spool"))
+            .and_then(|n| n.parse::<i64>().ok());
+        let Some(process_id) = process_id else {
+            continue;
+        };
+        let file = File::open(&path).map_err(|e| e.to_string())?;
+        match recover_spool(&mut conn, process_id, file) {
+            Ok(count) => {
+                recovered += count;
+                let _ = fs::remove_file(&path);
+            }
+            Err(e) => eprintln!("Recovery of {} failed: {}", path.display(), e),
+        }
+    }
+    Ok(recovered)
+}
@@ -0,0 +1,185 @@
+//! Verzované migrace schématu databáze.
+//!
+//! `*_scheme.rs` vytvářejí tabulky v aktuální podobě (nová jednotka).
+//! Existující databáze se na aktuální podobu dostane migracemi: každá změna
+//! schématu (nový sloupec, index, úprava dat) je nový krok na konci
+//! `MIGRATIONS` s další verzí. Kroky musí projít i na nové databázi, kde
+//! už tabulka v aktuální podobě existuje (viz `add_column_if_missing`).
+//! Indexy a triggery nad novými sloupci patří do migrace, ne do `*_scheme.rs`,
+//! protože schémata se vytvářejí ještě před migracemi.
+use rusqlite::{params, Connection, Result, Transaction};
+use std::error::Error;
+
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub up: fn(&Transaction) -> Result<()>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        up: |_| Ok(()),
+    },
+    Migration {
+        version: 2,
+        description: "copy_process: command_line, error_log_path, source_serial",
+        up: |tx| {
+            add_column_if_missing(tx, "copy_process", "command_line", "TEXT")?;
+            add_column_if_missing(tx, "copy_process", "error_log_path", "TEXT")?;
+            add_column_if_missing(tx, "copy_process", "source_serial", "TEXT")
+        },
+    },
+    Migration {
+        version: 3,
+        description: "ewf_config, dd_config: version, previous_version_id",
+        up: |tx| {
+            for table in ["ewf_config", "dd_config"] {
+                add_column_if_missing(tx, table, "version", "INTEGER NOT NULL DEFAULT 1")?;
+                add_column_if_missing(
+                    tx,
+                    table,
+                    "previous_version_id",
+                    &format!("INTEGER REFERENCES {table}(id)"),
+                )?;
+                tx.execute(
+                    &format!(
+                        "CREATE INDEX IF NOT EXISTS idx_{table}_previous_version
+                         ON {table}(previous_version_id)"
+                    ),
+                    [],
+                )?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 4,
+        description: "field_policy on configs, effective_config on copy logs",
+        up: |tx| {
+            for table in ["ewf_config", "dd_config"] {
+                add_column_if_missing(tx, table, "field_policy", "TEXT NOT NULL DEFAULT '{}'")?;
+            }
+            for table in ["copy_log_ewf", "copy_log_dd"] {
+                add_column_if_missing(tx, table, "effective_config", "TEXT DEFAULT NULL")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        version: 5,
+        description: "copy_process: log_error",
+        up: |tx| add_column_if_missing(tx, "copy_process", "log_error", "TEXT"),
+    },
+    Migration {
+        version: 6,
+        description: "copy_process: acquisition_snapshot",
+        up: |tx| add_column_if_missing(tx, "copy_process", "acquisition_snapshot", "TEXT"),
+    },
+];
+
+/// Verze schématu, kterou očekává tato verze aplikace.
+pub fn current_version() -> i64 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn initialize_schema_version_scheme(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            software_version TEXT NOT NULL,
+            applied_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Verze schématu databáze; 0 = databáze bez tabulky `schema_version`.
+pub fn db_version(conn: &Connection) -> Result<i64> {
+    let has_table: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+        [],
+        |r| r.get(0),
+    )?;
+    if !has_table {
+        return Ok(0);
+    }
+    conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        [],
+        |r| r.get(0),
+    )
+}
+
+/// `true`, pokud databáze už obsahuje data aplikace (není čerstvě vytvořená).
+pub fn has_app_tables(conn: &Connection) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'copy_process')",
+        [],
+        |r| r.get(0),
+    )
+}
+
+pub fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists: bool = conn.query_row(
+        &format!("SELECT EXISTS(SELECT 1 FROM pragma_table_info('{table}') WHERE name = ?1)"),
+        params![column],
+        |r| r.get(0),
+    )?;
+    if !exists {
+        conn.execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Odmítne databázi z novější verze aplikace (starší kód by jí nerozuměl).
+pub fn check_not_newer(conn: &Connection) -> std::result::Result<i64, Box<dyn Error>> {
+    let version = db_version(conn)?;
+    if version > current_version() {
+        return Err(format!(
+            "Database schema version {} is newer than supported version {} – refusing to start",
+            version,
+            current_version()
+        )
+        .into());
+    }
+    Ok(version)
+}
+
+/// Konzistentní kopie databáze (včetně obsahu WAL) do `path`.
+pub fn backup_to(conn: &Connection, path: &str) -> Result<()> {
+    conn.execute("VACUUM INTO ?1", params![path])?;
+    Ok(())
+}
+
+/// Provede chybějící migrace v jedné transakci. Vrací počet provedených kroků.
+pub fn run_migrations(conn: &mut Connection) -> Result<usize> {
+    let from = db_version(conn)?;
+    let tx = conn.transaction()?;
+    let mut applied = 0;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        println!(
+            "Applying DB migration {}: {}",
+            migration.version, migration.description
+        );
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, software_version) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.description, env!("CARGO_PKG_VERSION")],
+        )?;
+        applied += 1;
+    }
+    tx.commit()?;
+    Ok(applied)
+}
@@ -8,14 +8,20 @@ use std::collections::VecDeque;
 use std::time::{Instant, Duration, SystemTime, UNIX_EPOCH};
 use std::thread;
 
+pub mod backup;
 pub mod copy_log_scheme;
 pub mod dd_config_scheme;
 pub mod ewf_config_scheme;
+pub mod history_search_scheme;
 pub mod interface_scheme;
+pub mod log_writer;
 pub mod logging_scheme;
+pub mod migrations;
 pub mod process_log_scheme;
+pub mod report_archive_scheme;
+pub mod wipe_scheme;
 
-use crate::config::DATABASE_PATH;
+use crate::config::database_path;
 
 // Maximum number of connections in the pool
 const MAX_POOL_SIZE: usize = 10;
@@ -86,7 +92,7 @@ impl ConnectionPool {
         
         println!("[{}] [DB:{}] Creating new database connection...", get_timestamp(), connection_id);
         let conn = Connection::open_with_flags(
-            DATABASE_PATH,
+            database_path(),
             OpenFlags::SQLITE_OPEN_READ_WRITE |
             OpenFlags::SQLITE_OPEN_CREATE |
             OpenFlags::SQLITE_OPEN_FULL_MUTEX
@@ -229,7 +235,7 @@ pub fn initialize_db() -> Result<(), Box<dyn Error>> {
     println!("[{}] Initializing database schema...", get_timestamp());
     
     // Ujisti se, že existuje adresář pro databázi.
-    if let Some(parent_dir) = Path::new(DATABASE_PATH).parent() {
+    if let Some(parent_dir) = Path::new(database_path()).parent() {
         fs::create_dir_all(parent_dir)?;
     }
     
@@ -237,18 +243,44 @@ pub fn initialize_db() -> Result<(), Box<dyn Error>> {
     let mut conn = DB_POOL.get_connection()?;
     
     conn.execute(|conn| {
+        // Databázi z novější verze aplikace neotevíráme, před migrací ji zálohujeme.
+        let version = migrations::check_not_newer(conn)?;
+        if version < migrations::current_version() && migrations::has_app_tables(conn)? {
+            let backup_path = format!(
+                "{}.pre-v{}-{}.bak",
+                database_path(),
+                version,
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            );
+            println!("[{}] Backing up database to {} before migration", get_timestamp(), backup_path);
+            migrations::backup_to(conn, &backup_path)?;
+        }
+
         // Inicializace schémat v databázi.
+        migrations::initialize_schema_version_scheme(conn)?;
         logging_scheme::initialize_logging_scheme(conn)?;
         ewf_config_scheme::initialize_ewf_config_scheme(conn)?;
         dd_config_scheme::initialize_dd_config_scheme(conn)?;
         copy_log_scheme::initialize_copy_log_scheme(conn)?;
         interface_scheme::initialize_interface_scheme(conn)?;
         process_log_scheme::initialize_process_log_scheme(conn)?;
+        report_archive_scheme::initialize_report_archive_scheme(conn)?;
+        history_search_scheme::initialize_history_search_scheme(conn)?;
+        wipe_scheme::initialize_wipe_scheme(conn)?;
+
+        let applied = migrations::run_migrations(conn)?;
+        if applied > 0 {
+            println!("[{}] Applied {} DB migration(s), schema version {}", get_timestamp(), applied, migrations::current_version());
+        }
 
         // Nastav všechny záznamy, které mají status = running, na error
         conn.execute("UPDATE copy_log_ewf SET status='error' WHERE status='running'", [])?;
         conn.execute("UPDATE copy_log_dd SET status='error' WHERE status='running'", [])?;
         conn.execute("UPDATE copy_process SET status='error' WHERE status='running'", [])?;
+        conn.execute(
+            "UPDATE wipe_job SET status='error', error='Mazání přerušeno restartem aplikace' WHERE status='running'",
+            [],
+        )?;
 
         Ok(())
     })?;
@@ -276,7 +308,7 @@ pub fn create_new_connection() -> Result<Connection, Box<dyn Error>> {
     println!("[{}] [DB:{}] Creating standalone connection (not pooled)...", get_timestamp(), connection_id);
     
     let conn = Connection::open_with_flags(
-        DATABASE_PATH,
+        database_path(),
         OpenFlags::SQLITE_OPEN_READ_WRITE |
         OpenFlags::SQLITE_OPEN_CREATE |
         OpenFlags::SQLITE_OPEN_FULL_MUTEX
@@ -11,6 +11,11 @@ pub fn initialize_process_log_scheme(conn: &Connection) -> Result<()> {
                 CHECK(status IN ('running','done','error')),
             triggered_by_ewf INTEGER,
             triggered_by_dd INTEGER,
+            command_line TEXT,
+            error_log_path TEXT,
+            source_serial TEXT,
+            log_error TEXT,
+            acquisition_snapshot TEXT,
             FOREIGN KEY(triggered_by_ewf) REFERENCES copy_log_ewf(id),
             FOREIGN KEY(triggered_by_dd) REFERENCES copy_log_dd(id),
             CHECK(triggered_by_ewf IS NOT NULL OR triggered_by_dd IS NOT NULL)
@@ -18,7 +23,6 @@ pub fn initialize_process_log_scheme(conn: &Connection) -> Result<()> {
         "#,
         [],
     )?;
-
     conn.execute(
         r#"
         CREATE TABLE IF NOT EXISTS process_log_lines (
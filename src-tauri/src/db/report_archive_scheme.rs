@@ -0,0 +1,30 @@
+use rusqlite::{Connection, Result};
+
+pub fn initialize_report_archive_scheme(conn: &Connection) -> Result<()> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS report_archive (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            process_id INTEGER NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            process_status TEXT NOT NULL,
+            template_name TEXT NOT NULL,
+            template_sha256 TEXT NOT NULL,
+            software_version TEXT NOT NULL,
+            tex TEXT NOT NULL,
+            pdf BLOB NOT NULL,
+            pdf_size INTEGER NOT NULL,
+            pdf_sha256 TEXT NOT NULL,
+            FOREIGN KEY(process_id) REFERENCES copy_process(id) ON DELETE CASCADE
+        )
+        "#,
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_report_archive_process ON report_archive(process_id)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use rusqlite::{Connection, Result};
+
+pub fn initialize_wipe_scheme(conn: &Connection) -> Result<()> {
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS wipe_job (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bay_id INTEGER NOT NULL,
+            interface_path TEXT NOT NULL,
+            bay_name TEXT NOT NULL,
+            device TEXT NOT NULL,
+            serial TEXT,
+            model TEXT,
+            size_bytes INTEGER NOT NULL,
+            sector_size INTEGER NOT NULL,
+            method TEXT NOT NULL
+                CHECK(method IN ('zero','pattern','sanitize')),
+            sanitize_command TEXT,
+            pattern TEXT NOT NULL,
+            verify BOOLEAN NOT NULL,
+            operator TEXT NOT NULL,
+            start_datetime DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            end_datetime DATETIME,
+            status TEXT NOT NULL DEFAULT 'running'
+                CHECK(status IN ('running','done','error')),
+            bytes_written INTEGER NOT NULL DEFAULT 0,
+            bytes_verified INTEGER NOT NULL DEFAULT 0,
+            mismatch_offset INTEGER,
+            error TEXT,
+            certificate_pdf BLOB,
+            certificate_sha256 TEXT
+        )
+        "#,
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_wipe_job_bay ON wipe_job(bay_id, status)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -1,13 +1,18 @@
+use crate::copy_configs::policy::{apply_policy, effective_json};
+use crate::copy_configs::validation::{describe, parse_dd_config, DdSettings};
+use crate::copy_configs::NewDDConfig;
 use crate::disk_utils::{get_block_size, get_mountpoint_for_interface, get_total_blocks}; // Přidáno
+use crate::db::log_writer::ProcessLogWriter;
 use crate::led::LED_CONTROLLER;
 use crate::logger::{log_error, log_warn};
-use crate::report::generate_report_dcfldd;
+use crate::report::{snapshot, spawn_report_job};
 use crate::websocket;
 use chrono::Utc;
 use lazy_static::lazy_static;
 use regex::Regex;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use tauri_plugin_shell::process::CommandEvent;
@@ -23,6 +28,9 @@ pub struct DdParams {
     pub notes: String,
     pub offset: i64,
     pub limit: i64,
+    /// Hodnoty polí s politikou `ask` zadané operátorem
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
 }
 
 /// Struktura pro frontendu zasílané výstupy.
@@ -72,6 +80,9 @@ struct WsProcessDone {
     id: i64,
     status: String,
     end_datetime: String,
+    /// Výpis procesu není v databázi celý nebo nebyl zálohovaný
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_error: Option<String>,
 }
 
 fn execute_with_retry<T, F>(operation_name: &str, mut f: F, max_retries: usize) -> Result<T, String>
@@ -177,22 +188,9 @@ pub async fn run_dcfldd(
     let dd_params_db = dd_params.clone();
 
     // DB lookup na ID disků podle interface_path a INSERT do copy_log_dd
-    #[derive(Debug)]
-    struct DdConfigComplete {
-        confname: String,
-        format: String,
-        limit_mode: String,
-        offset: String,
-        hash_types: String,
-        hashwindow: String,
-        split: String,
-        vf: bool,
-        diffwr: bool,
-        notes: String,
-    }
-
-    let (config, process_id, copy_log_id) = tauri::async_runtime::spawn_blocking(
-        move || -> Result<(DdConfigComplete, i64, i64), String> {
+    let input_device = actual_input_device.clone();
+    let (config, settings, block_size, process_id, copy_log_id) = tauri::async_runtime::spawn_blocking(
+        move || -> Result<(NewDDConfig, DdSettings, u64, i64, i64), String> {
             let mut pooled_conn = execute_with_retry(
                 "Get connection from DB_POOL",
                 || {
@@ -212,14 +210,14 @@ pub async fn run_dcfldd(
                 let mut stmt = conn
                     .prepare(
                         "SELECT confname, format, limit_mode, offset, hash_types, 
-                         hashwindow, split, vf, diffwr, notes
+                         hashwindow, split, vf, diffwr, notes, field_policy
                          FROM dd_config
                          WHERE id = ?1 AND active = 1",
                     )
                     .map_err(|e| format!("(DB) Chyba při přípravě SQL dotazu: {}", e))?;
 
                 stmt.query_row([config_id], |row| {
-                    Ok(DdConfigComplete {
+                    Ok(NewDDConfig {
                         confname: row.get(0)?,
                         format: row.get(1)?,
                         limit_mode: row.get(2)?,
@@ -227,14 +225,40 @@ pub async fn run_dcfldd(
                         hash_types: row.get(4)?,
                         hashwindow: row.get(5)?,
                         split: row.get(6)?,
-                        vf: row.get::<_, i32>(7)? != 0,
-                        diffwr: row.get::<_, i32>(8)? != 0,
+                        vf: row.get(7)?,
+                        diffwr: row.get(8)?,
                         notes: row.get(9)?,
+                        field_policy: row.get(10)?,
                     })
                 })
                 .map_err(|e| format!("(DB) Chyba při získávání konfigurace: {}", e))?
             };
 
+            // neplatná konfigurace se nespustí, hodnoty se nenahrazují výchozími
+            let config = apply_policy(
+                &config,
+                &config.field_policy,
+                &dd_params_db.overrides,
+                true,
+            )
+            .map_err(|e| {
+                let msg = format!("Neplatné zadání pro konfiguraci {}: {}", config_id, describe(&e));
+                log_error(&msg);
+                msg
+            })?;
+            let settings = parse_dd_config(&config).map_err(|e| {
+                let msg = format!("Neplatná konfigurace {}: {}", config_id, describe(&e));
+                log_error(&msg);
+                msg
+            })?;
+            // bs=auto: logická velikost sektoru zdroje, jinak se akvizice nespustí
+            let block_size = match settings.block_size {
+                Some(bs) => bs,
+                None => get_block_size(&input_device).map_err(|e| {
+                    format!("Nelze zjistit velikost sektoru {}: {}", input_device, e)
+                })?,
+            };
+
             // Najdi ID source disku v tabulce `interfaces`, ve sloupci `interface_path`
             let source_disk_id: i64 = conn
                 .query_row(
@@ -288,12 +312,25 @@ pub async fn run_dcfldd(
                 .transaction()
                 .map_err(|e| format!("(DB) Nelze zahájit transakci: {}", e))?;
 
+            // skutečně použité hodnoty včetně zadání při spuštění
+            let mut runtime = vec![("format", serde_json::json!(block_size))];
+            if config.offset == "ask" {
+                runtime.push(("offset", serde_json::json!(dd_params_db.offset)));
+            }
+            if config.limit_mode == "ask" {
+                runtime.push(("limit_mode", serde_json::json!(dd_params_db.limit)));
+            }
+            if config.notes == "ask" {
+                runtime.push(("notes", serde_json::json!(dd_params_db.notes)));
+            }
+            let effective_config = effective_json(&config, &dd_params_db.overrides, &runtime);
+
             tx.execute(
                 "INSERT INTO copy_log_dd (
                     config_id, source, case_number, description, investigator_name, 
                     evidence_number, notes, offset, limit_value, source_disk_id, 
-                    dest_disk_id, second_dest_disk_id, start_datetime
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, DATETIME('now'))",
+                    dest_disk_id, second_dest_disk_id, effective_config, start_datetime
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, DATETIME('now'))",
                 rusqlite::params![
                     config_id,
                     "dcfldd",
@@ -306,7 +343,8 @@ pub async fn run_dcfldd(
                     dd_params_db.limit,
                     source_disk_id,
                     first_output_id,
-                    second_output_id
+                    second_output_id,
+                    effective_config
                 ],
             )
             .map_err(|e| format!("(DB) Chyba při insertu copy_log_dd: {}", e))?;
@@ -324,7 +362,7 @@ pub async fn run_dcfldd(
             tx.commit()
                 .map_err(|e| format!("(DB) Chyba při potvrzení transakce: {}", e))?;
 
-            Ok((config, pid, copy_log_id))
+            Ok((config, settings, block_size, pid, copy_log_id))
         },
     )
     .await
@@ -371,8 +409,8 @@ pub async fn run_dcfldd(
     push_key_val(&mut args_exec, &mut args_print, "if", &actual_input_device);
 
     // Split file option if specified
-    if config.split != "whole" {
-        push_key_val(&mut args_exec, &mut args_print, "split", &config.split);
+    if let Some(split) = settings.split {
+        push_key_val(&mut args_exec, &mut args_print, "split", &split.to_string());
     }
 
     let case_number = dd_params.case_number.trim();
@@ -393,20 +431,17 @@ pub async fn run_dcfldd(
     push_key_val(&mut args_exec, &mut args_print, "of", &primary_out);
 
     // Second output if available
-    if let Some(evidence_dir_2) = evidence_dir_2 {
+    if let Some(evidence_dir_2) = &evidence_dir_2 {
         let second_out = format!("{}/{}.img", evidence_dir_2, evidence_number);
         push_key_val(&mut args_exec, &mut args_print, "of2", &second_out);
     }
 
-    let block_size: u64 = config.format.trim().parse().unwrap_or(512);
-
-    push_key_val(&mut args_exec, &mut args_print, "bs", &config.format);
+    push_key_val(&mut args_exec, &mut args_print, "bs", &block_size.to_string());
 
     // Offset handling (skip parameter)
-    let offset_value = if config.offset == "ask" {
-        dd_params.offset.to_string()
-    } else {
-        config.offset.to_string()
+    let offset_value = match settings.offset {
+        Some(offset) => offset.to_string(),
+        None => dd_params.offset.to_string(),
     };
 
     if offset_value != "0" && !offset_value.is_empty() {
@@ -439,12 +474,12 @@ pub async fn run_dcfldd(
             push_key_val(&mut args_exec, &mut args_print, "hash", &hash_types);
 
             // Set hash window size if specified
-            if config.hashwindow != "whole" {
+            if let Some(hashwindow) = settings.hashwindow {
                 push_key_val(
                     &mut args_exec,
                     &mut args_print,
                     "hashwindow",
-                    &config.hashwindow,
+                    &hashwindow.to_string(),
                 );
             }
 
@@ -455,13 +490,13 @@ pub async fn run_dcfldd(
     }
 
     // Verify input option
-    if config.vf {
+    if config.vf != 0 {
         // This would need verification file path logic
         // push_key_val(&mut args_exec, &mut args_print, "vf", &verification_file_path);
     }
 
     // Diff write option
-    if config.diffwr {
+    if config.diffwr != 0 {
         push_key_val(&mut args_exec, &mut args_print, "diffwr", "on");
     }
 
@@ -479,6 +514,29 @@ pub async fn run_dcfldd(
     println!("{}", cmd_print);
     println!("================================\n");
 
+    // příkaz, chybový log, sériové číslo zdroje a snímek disků si pamatujeme
+    // pro report a historii
+    {
+        let output_devices: Vec<String> = output_interfaces
+            .iter()
+            .map(|i| format!("/dev/disk/by-path/{}", i))
+            .collect();
+        let conn = crate::db::create_new_connection()
+            .map_err(|e| format!("Failed to create connection: {}", e))?;
+        conn.execute(
+            "UPDATE copy_process SET command_line = ?1, error_log_path = ?2, source_serial = ?3,
+             acquisition_snapshot = ?4 WHERE id = ?5",
+            params![
+                cmd_print,
+                error_log_path,
+                crate::disk_utils::get_disk_serial(&actual_input_device),
+                snapshot::capture_disks(&actual_input_device, &output_devices).to_string(),
+                process_id
+            ],
+        )
+        .map_err(|e| format!("(DB) Chyba při zápisu příkazu do copy_process: {}", e))?;
+    }
+
     // Fyzická velikost sektoru na disku (většinou 512 B nebo 4096 B)
     let phys_bs: u64 = get_block_size(&actual_input_device).unwrap_or(512);
 
@@ -496,6 +554,12 @@ pub async fn run_dcfldd(
     println!("Celkem bloků podle bs={}: {}", block_size, total_blocks);
 
     // Execute the command
+    // složky s důkazem pro snímek souborů obrazu po skončení akvizice
+    let image_dirs: Vec<String> = std::iter::once(evidence_dir_1.clone())
+        .chain(evidence_dir_2.clone())
+        .collect();
+    let image_evidence_number = evidence_number.to_string();
+
     let shell = app_handle.shell();
     let (mut rx, _child) = shell
         .command("sudo")
@@ -513,6 +577,8 @@ pub async fn run_dcfldd(
     static mut WINDOW_START_TIME: Option<std::time::Instant> = None;
     static mut LAST_SPEED_MIB: f64 = 0.0;
 
+    let log_writer = ProcessLogWriter::start(process_id)?;
+
     // Process command output
     while let Some(event) = rx.recv().await {
         match event {
@@ -530,15 +596,7 @@ pub async fn run_dcfldd(
                 websocket::broadcast_message(&json_output).await;
 
                 // Log do databáze
-                {
-                    let conn = crate::db::create_new_connection()
-                        .map_err(|e| format!("(DB output) Failed to create connection: {}", e))?;
-                    conn.execute(
-                        "INSERT INTO process_log_lines (process_id, line_content, line_number)
-                         VALUES (?1, ?2, COALESCE((SELECT MAX(line_number) FROM process_log_lines WHERE process_id=?1),0)+1)",
-                        params![process_id, line_str],
-                    ).map_err(|e| format!("(DB output) Error writing log: {}", e))?;
-                }
+                log_writer.push(line_str.clone());
 
                 lazy_static! {
                     static ref PROGRESS_REGEX: Regex = Regex::new(r"(\d+)% done, .*").unwrap();
@@ -617,6 +675,15 @@ pub async fn run_dcfldd(
             CommandEvent::Terminated(exit_code) => {
                 LED_CONTROLLER.notify_process_end();
 
+                // výpis musí být celý v DB dřív, než se z něj vyrenderuje report
+                let log_failure = match log_writer.finish().await {
+                    Ok(_) => None,
+                    Err(e) => {
+                        log_error(&e);
+                        Some(e)
+                    }
+                };
+
                 let final_status = if exit_code.code.unwrap_or(-1) == 0 {
                     "done"
                 } else {
@@ -625,6 +692,7 @@ pub async fn run_dcfldd(
 
                 let end_time = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
                 let end_time_for_db = end_time.clone();
+                let log_failure_for_db = log_failure.clone();
 
                 let hash_log_path = format!("{}/hash.log", evidence_dir_1);
 
@@ -691,28 +759,37 @@ pub async fn run_dcfldd(
                         .map_err(|e| format!("Error updating copy_log_dd: {}", e))?;
 
                     conn.execute(
-                        "UPDATE copy_process SET status = ?, end_datetime = ? WHERE id = ?",
-                        params![final_status, end_time_for_db, process_id],
+                        "UPDATE copy_process SET status = ?, end_datetime = ?, log_error = ? WHERE id = ?",
+                        params![final_status, end_time_for_db, log_failure_for_db, process_id],
                     )
                     .map_err(|e| format!("Error updating copy_process: {}", e))?;
 
+                    // soubory obrazu patří do snímku akvizice, report je už nehledá na disku
+                    if let Err(e) = snapshot::record_image_files(
+                        &conn,
+                        process_id,
+                        &image_dirs,
+                        &image_evidence_number,
+                    ) {
+                        log_error(&format!(
+                            "Soubory obrazu procesu {process_id} nebyly uloženy: {e}"
+                        ));
+                    }
+
                     Ok(())
                 })
                 .await
                 .map_err(|e| e.to_string())??;
 
-                if final_status == "done" {
-                    let report_result = generate_report_dcfldd(process_id);
-                    if let Err(e) = report_result {
-                        eprintln!("Chyba při generování reportu: {}", e);
-                    }
-                }
+                // report vzniká i pro chybové a přerušené běhy, na pozadí
+                spawn_report_job(process_id);
 
                 let ws_done = WsProcessDone {
                     msg_type: "ProcessDone".to_string(),
                     id: process_id,
                     status: final_status.to_string(),
                     end_datetime: end_time,
+                    log_error: log_failure,
                 };
                 let done_msg = serde_json::to_string(&ws_done).unwrap();
                 websocket::broadcast_message(&done_msg).await;
@@ -2,7 +2,7 @@ use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
 use std::time::UNIX_EPOCH;
-use crate::config::MOUNT_ROOT;
+use crate::config::mount_root;
 
 
 #[derive(Serialize)]
@@ -17,7 +17,7 @@ pub struct FileItem {
 #[tauri::command]
 pub fn get_directory_contents(mountpoint: &str) -> Result<Vec<FileItem>, String> {
     // Bezpečnostní kontrola: povoleno pouze v MOUNT_ROOT
-    if !mountpoint.starts_with(MOUNT_ROOT) {
+    if !mountpoint.starts_with(mount_root()) {
         return Err(format!("Access denied: path {} is outside of MOUNT_ROOT", mountpoint));
     }
 
@@ -0,0 +1,142 @@
+//! Bezpečné odebrání cílového disku.
+//!
+//! Zapíše cache (`sync`), odpojí všechny oddíly, uspí disk (`hdparm -Y`)
+//! a pokud to můstek nepropustí, odebere jej z jádra přes sysfs
+//! (`/sys/block/<disk>/device/delete`). Registr zařízení pak disk do
+//! vytažení nepřipojí a klienti dostanou `DeviceEjected`. Disk, na který
+//! právě něco zapisuje (report, export, záloha), se neodebere.
+use super::writers::begin_eject;
+use super::{bay_device, disk_parts, get_disk_serial, part_mounts, run_sudo, unmount_parts};
+use crate::dashboard_layout::{monitor, DeviceUpdate};
+use crate::db::DB_POOL;
+use crate::interfaces::{bay_by_interface, in_use};
+use crate::logger::{log_warn, write_audit};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerOff {
+    /// Disk uspaný příkazem `hdparm -Y`, zůstává viditelný do vytažení
+    Sleep,
+    /// Disk odebraný z jádra přes sysfs
+    Delete,
+    /// Disk neumí uspat ani odebrat; je jen odpojený s vyprázdněnou cache
+    Unmounted,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EjectedDevice {
+    pub interface: String,
+    pub device: String,
+    pub serial: Option<String>,
+    pub power_off: PowerOff,
+}
+
+/// Zapíše `1` do souboru pod sysfs (přes `sudo tee`).
+fn sysfs_write_one(path: &str) -> Result<(), String> {
+    let mut child = Command::new("sudo")
+        .args(["tee", path])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run tee: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(b"1\n").map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "Zápis do {} selhal: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Uspí disk, případně jej odebere z jádra. Disk je v tu chvíli už
+/// odpojený, takže neúspěch jen zapíšeme do logu.
+fn power_off(device: &str) -> PowerOff {
+    match run_sudo(&["hdparm", "-Y", device]) {
+        Ok(_) => return PowerOff::Sleep,
+        Err(e) => log_warn(&format!(
+            "hdparm -Y {} selhal, zkouším sysfs: {}",
+            device, e
+        )),
+    }
+    let delete = format!(
+        "/sys/block/{}/device/delete",
+        device.trim_start_matches("/dev/")
+    );
+    if !Path::new(&delete).exists() {
+        return PowerOff::Unmounted;
+    }
+    match sysfs_write_one(&delete) {
+        Ok(()) => PowerOff::Delete,
+        Err(e) => {
+            log_warn(&e);
+            PowerOff::Unmounted
+        }
+    }
+}
+
+fn eject(interface: &str) -> Result<(EjectedDevice, Option<DeviceUpdate>), String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let bay = bay_by_interface(conn, interface)?;
+    if in_use(conn, bay.id)? {
+        return Err(format!(
+            "Na šachtě '{}' právě běží akvizice nebo mazání, disk nelze odebrat",
+            bay.name
+        ));
+    }
+    let device = bay_device(interface)?;
+    let serial = get_disk_serial(&device);
+    let parts = disk_parts(&device)?;
+    let _ejecting = begin_eject(&part_mounts(&parts))?;
+
+    let _hold = monitor::hold_auto_mount(&device);
+    run_sudo(&["sync"])?;
+    unmount_parts(&parts).map_err(|e| format!("Disk {} nelze odpojit: {}", device, e))?;
+    let _ = run_sudo(&["blockdev", "--flushbufs", &device]);
+    // mark_ejected musí proběhnout dřív, než udev po uspání / odebrání
+    // ohlásí změnu, jinak by se disk znovu připojil
+    let update = monitor::mark_ejected(&device);
+    let power_off = power_off(&device);
+
+    write_audit(
+        conn,
+        &format!(
+            "Disk bezpečně odebrán: šachta '{}' ({}), disk {} (SN {}), {:?}",
+            bay.name,
+            interface,
+            device,
+            serial.as_deref().unwrap_or("neznámé"),
+            power_off
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+    let ejected = EjectedDevice {
+        interface: interface.to_string(),
+        device,
+        serial,
+        power_off,
+    };
+    Ok((ejected, update))
+}
+
+/// Bezpečně odebere disk v šachtě `interface` (sync, odpojení oddílů,
+/// uspání / vypnutí). Odmítne, pokud na šachtě běží akvizice nebo mazání
+/// nebo se na disk právě zapisuje.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn eject_device(interface: String) -> Result<EjectedDevice, String> {
+    let (ejected, update) = tauri::async_runtime::spawn_blocking(move || eject(&interface))
+        .await
+        .map_err(|e| format!("Async error: {}", e))??;
+    monitor::publish(update.into_iter().collect()).await;
+    Ok(ejected)
+}
@@ -1,8 +1,13 @@
 use serde::Serialize;
 use serde_json::Value;
+use std::path::Path;
 use std::process::Command;
 use std::str;
 
+pub mod eject;
+pub mod prepare;
+pub mod writers;
+
 #[tauri::command(rename_all = "snake_case")]
 pub fn get_lsblk_json(device: &str) -> Result<Value, String> {
     println!("[DEBUG] Spouštím lsblk pro device: {}", device);
@@ -20,6 +25,15 @@ pub fn get_lsblk_json(device: &str) -> Result<Value, String> {
     Ok(json)
 }
 
+/// Získá sériové číslo disku z lsblk JSON.
+pub fn get_disk_serial(device: &str) -> Option<String> {
+    let lsblk_data = get_lsblk_json(device).ok()?;
+    lsblk_data["blockdevices"][0]["serial"]
+        .as_str()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
 /// Získá logickou velikost bloku ("log-sec") z lsblk JSON.
 pub fn get_block_size(device: &str) -> Result<u64, String> {
     let lsblk_data = get_lsblk_json(device)?;
@@ -303,3 +317,90 @@ pub fn get_disk_info(device: &str) -> Result<DiskInfo, String> {
         model,
     })
 }
+
+/// Disk nebo oddíl z lsblk s filesystemem a mountpointy.
+#[derive(Debug, Clone)]
+pub struct DiskPart {
+    pub path: String,
+    pub fstype: Option<String>,
+    pub mountpoints: Vec<String>,
+}
+
+/// Celý disk a jeho oddíly (první položka je disk).
+pub fn disk_parts(device: &str) -> Result<Vec<DiskPart>, String> {
+    let lsblk = get_lsblk_json(device)?;
+    let entry = |dev: &Value| DiskPart {
+        path: dev["path"].as_str().unwrap_or_default().to_string(),
+        fstype: dev["fstype"].as_str().map(|s| s.to_string()),
+        mountpoints: dev["mountpoints"]
+            .as_array()
+            .map(|m| {
+                m.iter()
+                    .filter_map(|v| v.as_str())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_string())
+                    .collect()
+            })
+            .unwrap_or_default(),
+    };
+    let disk = &lsblk["blockdevices"][0];
+    let mut parts = vec![entry(disk)];
+    if let Some(children) = disk["children"].as_array() {
+        parts.extend(children.iter().map(entry));
+    }
+    Ok(parts)
+}
+
+/// Spustí příkaz přes sudo; chyba obsahuje stderr příkazu.
+pub(crate) fn run_sudo(args: &[&str]) -> Result<String, String> {
+    crate::logger::log_debug(&format!("Spouštím příkaz: sudo {}", args.join(" ")));
+    let output = Command::new("sudo")
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run {}: {}", args[0], e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} selhal: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Devnode disku v šachtě (`/dev/disk/by-path/<interface>` -> `/dev/sdX`).
+pub fn bay_device(interface: &str) -> Result<String, String> {
+    std::fs::canonicalize(format!("/dev/disk/by-path/{}", interface))
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|_| format!("V šachtě {} není připojen disk", interface))
+}
+
+/// Všechny mountpointy oddílů disku.
+pub(crate) fn part_mounts(parts: &[DiskPart]) -> Vec<String> {
+    parts
+        .iter()
+        .flat_map(|p| p.mountpoints.iter().cloned())
+        .collect()
+}
+
+/// Odpojí všechny připojené oddíly disku. Disk připojený mimo `MOUNT_ROOT`
+/// (systémový) se neodpojuje a je to chyba.
+pub(crate) fn unmount_parts(parts: &[DiskPart]) -> Result<(), String> {
+    let root = crate::config::mount_root();
+    for part in parts {
+        if let Some(outside) = part
+            .mountpoints
+            .iter()
+            .find(|m| !Path::new(m).starts_with(root))
+        {
+            return Err(format!(
+                "{} je připojený mimo {} ({})",
+                part.path, root, outside
+            ));
+        }
+    }
+    for part in parts.iter().filter(|p| !p.mountpoints.is_empty()) {
+        run_sudo(&["umount", &part.path])?;
+    }
+    Ok(())
+}
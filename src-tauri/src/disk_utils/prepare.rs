@@ -0,0 +1,294 @@
+//! Příprava nového cílového disku: GPT, jeden oddíl, filesystem a připojení
+//! pod `MOUNT_ROOT`.
+//!
+//! Povoleno jen pro výstupní šachty a jen pro disk, na kterém není žádná
+//! složka s důkazy známá z `copy_log_*` (`<případ>/<číslo důkazu>`). Disk,
+//! jehož filesystém nejde prohlédnout, se nepřeformátuje. Label oddílu
+//! vychází z čísla případu.
+use super::writers::begin_eject;
+use super::{
+    bay_device, disk_parts, get_disk_serial, get_mountpoint_for_interface, part_mounts, run_sudo,
+    unmount_parts, DiskPart,
+};
+use crate::config::mount_root;
+use crate::copy_configs::validation::{ConfigError, FieldError};
+use crate::dashboard_layout::monitor;
+use crate::db::DB_POOL;
+use crate::interfaces::{bay_by_interface, in_use};
+use crate::lockscreen::require_admin;
+use crate::logger::write_audit;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DestinationFs {
+    Exfat,
+    Ntfs,
+    Ext4,
+}
+
+impl DestinationFs {
+    /// Nejdelší label, který daný filesystem unese
+    fn max_label(self) -> usize {
+        match self {
+            DestinationFs::Exfat => 11,
+            DestinationFs::Ntfs => 32,
+            DestinationFs::Ext4 => 16,
+        }
+    }
+
+    fn mkfs<'a>(self, partition: &'a str, label: &'a str) -> Vec<&'a str> {
+        let mut args = match self {
+            DestinationFs::Exfat => vec!["mkfs.exfat", "-n", label],
+            DestinationFs::Ntfs => vec!["mkfs.ntfs", "-f", "-L", label],
+            DestinationFs::Ext4 => vec!["mkfs.ext4", "-F", "-L", label],
+        };
+        args.push(partition);
+        args
+    }
+
+    /// Typ oddílu pro `parted mkpart` (exFAT i NTFS jsou "Microsoft basic data")
+    fn parted_type(self) -> &'static str {
+        match self {
+            DestinationFs::Exfat | DestinationFs::Ntfs => "ntfs",
+            DestinationFs::Ext4 => "ext4",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreparedDestination {
+    pub interface: String,
+    pub device: String,
+    pub partition: String,
+    pub fs: DestinationFs,
+    pub label: String,
+    pub mountpoint: String,
+}
+
+fn field_error(field: &str, message: &str) -> ConfigError {
+    vec![FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }]
+    .into()
+}
+
+/// Label oddílu z čísla případu: znaky mimo ASCII písmena, číslice, `-`
+/// a `_` nahradí `_` a zkrátí na délku, kterou filesystem unese.
+fn case_label(fs: DestinationFs, case_number: &str) -> Result<String, ConfigError> {
+    let label: String = case_number
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(fs.max_label())
+        .collect();
+    let label = label.trim_matches('_').to_string();
+    if !label.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return Err(field_error(
+            "case_number",
+            "Z čísla případu nelze sestavit label disku",
+        ));
+    }
+    Ok(label)
+}
+
+/// Složky `<případ>/<číslo důkazu>` z historie akvizic, které leží pod `root`.
+fn evidence_under(conn: &Connection, root: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT case_number, evidence_number FROM copy_log_ewf
+             UNION SELECT case_number, evidence_number FROM copy_log_dd",
+        )
+        .map_err(|e| e.to_string())?;
+    let known = stmt
+        .query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+    Ok(known
+        .into_iter()
+        .map(|(case, evidence)| Path::new(root).join(case.trim()).join(evidence.trim()))
+        .filter(|dir| dir.is_dir())
+        .map(|dir| dir.to_string_lossy().to_string())
+        .collect())
+}
+
+/// Projde všechny filesystémy disku (nepřipojené dočasně připojí jen pro
+/// čtení) a vrátí nalezené složky s důkazy. Filesystém, který nejde připojit,
+/// je chyba – důkazy na něm nelze vyloučit.
+fn find_evidence(conn: &Connection, parts: &[DiskPart]) -> Result<Vec<String>, String> {
+    let mut found = Vec::new();
+    for part in parts {
+        if !part.mountpoints.is_empty() {
+            for mount in &part.mountpoints {
+                found.extend(evidence_under(conn, mount)?);
+            }
+            continue;
+        }
+        if part.fstype.is_none() {
+            continue;
+        }
+        let path = &part.path;
+        let check_dir = format!(
+            "{}/.prepare-check-{}",
+            mount_root(),
+            path.trim_start_matches("/dev/")
+        );
+        std::fs::create_dir_all(&check_dir).map_err(|e| e.to_string())?;
+        if let Err(e) = run_sudo(&["mount", "-o", "ro", path, &check_dir]) {
+            let _ = std::fs::remove_dir(&check_dir);
+            return Err(format!(
+                "Oddíl {} ({}) nelze prohlédnout, disk nelze přeformátovat: {}",
+                path,
+                part.fstype.as_deref().unwrap_or("?"),
+                e
+            ));
+        }
+        let result = evidence_under(conn, &check_dir);
+        let _ = run_sudo(&["umount", &check_dir]);
+        let _ = std::fs::remove_dir(&check_dir);
+        found.extend(result?);
+    }
+    Ok(found)
+}
+
+/// `/dev/sdb` -> `/dev/sdb1`, `/dev/nvme0n1` -> `/dev/nvme0n1p1`
+fn first_partition(device: &str) -> String {
+    if device.ends_with(|c: char| c.is_ascii_digit()) {
+        format!("{device}p1")
+    } else {
+        format!("{device}1")
+    }
+}
+
+/// Rozdělí a naformátuje cílový disk. Vrací devnode disku a oddílu.
+fn prepare(
+    interface: &str,
+    fs: DestinationFs,
+    case_number: &str,
+    label: &str,
+) -> Result<(String, String), String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let bay = bay_by_interface(conn, interface)?;
+    if bay.side != "output" {
+        return Err(format!(
+            "Šachta '{}' je vstupní, připravit lze jen cílový disk",
+            bay.name
+        ));
+    }
+    if in_use(conn, bay.id)? {
+        return Err(format!("Šachta '{}' je právě používaná", bay.name));
+    }
+    let device = bay_device(interface)?;
+    let parts = disk_parts(&device)?;
+    let evidence = find_evidence(conn, &parts)?;
+    if !evidence.is_empty() {
+        return Err(format!(
+            "Disk {} obsahuje důkazy ({}), nelze jej přeformátovat",
+            device,
+            evidence.join(", ")
+        ));
+    }
+    let serial = get_disk_serial(&device).unwrap_or_else(|| "neznámé".into());
+
+    // zápis reportu nebo exportu na disk musí doběhnout, nové se nespustí
+    let _unmounting = begin_eject(&part_mounts(&parts))?;
+    let _hold = monitor::hold_auto_mount(&device);
+    unmount_parts(&parts)?;
+    run_sudo(&["wipefs", "-a", &device])?;
+    run_sudo(&[
+        "parted",
+        "-s",
+        &device,
+        "mklabel",
+        "gpt",
+        "mkpart",
+        label,
+        fs.parted_type(),
+        "1MiB",
+        "100%",
+    ])?;
+    run_sudo(&["partprobe", &device])?;
+    let _ = run_sudo(&["udevadm", "settle"]);
+    let partition = first_partition(&device);
+    for _ in 0..20 {
+        if Path::new(&partition).exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    if !Path::new(&partition).exists() {
+        return Err(format!(
+            "Oddíl {} se po rozdělení disku neobjevil",
+            partition
+        ));
+    }
+    // na začátku nového oddílu může zůstat podpis starého filesystému
+    run_sudo(&["wipefs", "-a", &partition])?;
+    run_sudo(&fs.mkfs(&partition, label))?;
+
+    write_audit(
+        conn,
+        &format!(
+            "Cílový disk připraven: šachta '{}' ({}), disk {} (SN {}), GPT, {:?}, label '{}' (případ {})",
+            bay.name, interface, device, serial, fs, label, case_number
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok((device, partition))
+}
+
+/// Připraví nový cílový disk ve výstupní šachtě `interface` (GPT, jeden oddíl
+/// s `fs` a labelem z `case_number`) a připojí jej pod `MOUNT_ROOT`. Jen pro
+/// administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn prepare_destination(
+    interface: String,
+    fs: DestinationFs,
+    case_number: String,
+    admin_code: String,
+) -> Result<PreparedDestination, ConfigError> {
+    require_admin(&admin_code)?;
+    let label = case_label(fs, &case_number)?;
+
+    let (interface_c, label_c) = (interface.clone(), label.clone());
+    let (device, partition) = tauri::async_runtime::spawn_blocking(move || {
+        prepare(&interface_c, fs, case_number.trim(), &label_c)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))??;
+
+    // připojení zajistí registr zařízení stejně jako u nově vloženého disku
+    monitor::rescan_and_publish().await;
+    let by_path = format!("/dev/disk/by-path/{}", interface);
+    let mountpoint =
+        tauri::async_runtime::spawn_blocking(move || get_mountpoint_for_interface(&by_path))
+            .await
+            .map_err(|e| format!("Async error: {}", e))?
+            .ok_or_else(|| {
+                format!(
+                    "Disk {} je naformátovaný, ale nepodařilo se jej připojit",
+                    device
+                )
+            })?;
+
+    Ok(PreparedDestination {
+        interface,
+        device,
+        partition,
+        fs,
+        label,
+        mountpoint,
+    })
+}
@@ -0,0 +1,94 @@
+//! Evidence probíhajících zápisů na připojené výstupy.
+//!
+//! Zápis na disk pod `mount_root()` (report, štítek, certifikát mazání,
+//! export presetů a historie, záloha DB) drží po celou dobu `WriteGuard`.
+//! Bezpečné odebrání disku s aktivním zápisem se odmítne a po dobu
+//! odebírání se nové zápisy na jeho mountpointy nepustí.
+use crate::config::mount_root;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Writers {
+    /// mountpoint -> počet aktivních zápisů
+    active: HashMap<String, usize>,
+    /// Mountpointy právě odebíraných disků
+    ejecting: Vec<String>,
+}
+
+static WRITERS: Lazy<Mutex<Writers>> = Lazy::new(|| Mutex::new(Writers::default()));
+
+/// Mountpoint pod `mount_root()`, na kterém leží `path`.
+fn mount_of(path: &str) -> Option<String> {
+    let root = Path::new(mount_root());
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    mounts
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .filter(|m| {
+            let m = Path::new(m);
+            m.starts_with(root) && m != root && Path::new(path).starts_with(m)
+        })
+        .max_by_key(|m| m.len())
+        .map(str::to_string)
+}
+
+/// Aktivní zápis na výstup; uvolní se při dropu.
+pub struct WriteGuard(String);
+
+impl Drop for WriteGuard {
+    fn drop(&mut self) {
+        let mut writers = WRITERS.lock().unwrap();
+        if let Some(count) = writers.active.get_mut(&self.0) {
+            *count -= 1;
+            if *count == 0 {
+                writers.active.remove(&self.0);
+            }
+        }
+    }
+}
+
+/// Zaregistruje zápis do `path` (soubor nebo složka na výstupu). Selže, pokud
+/// výstup není připojený (zápis by skončil v prázdném mountpointu) nebo se
+/// právě odebírá.
+pub fn write_guard(path: &str) -> Result<WriteGuard, String> {
+    let mount = mount_of(path).ok_or_else(|| format!("Výstup pro {path} není připojený"))?;
+    let mut writers = WRITERS.lock().unwrap();
+    if writers.ejecting.contains(&mount) {
+        return Err(format!(
+            "Disk {mount} se právě odebírá, zápis nelze spustit"
+        ));
+    }
+    *writers.active.entry(mount.clone()).or_default() += 1;
+    Ok(WriteGuard(mount))
+}
+
+/// Probíhající odebrání disku; po dropu se na jeho mountpointy zase smí zapisovat.
+pub struct EjectGuard(Vec<String>);
+
+impl Drop for EjectGuard {
+    fn drop(&mut self) {
+        let mut writers = WRITERS.lock().unwrap();
+        for mount in &self.0 {
+            if let Some(i) = writers.ejecting.iter().position(|m| m == mount) {
+                writers.ejecting.swap_remove(i);
+            }
+        }
+    }
+}
+
+/// Zahájí odebrání disku připojeného na `mounts`. Odmítne, pokud na některý
+/// z nich právě běží zápis.
+pub fn begin_eject(mounts: &[String]) -> Result<EjectGuard, String> {
+    let mut writers = WRITERS.lock().unwrap();
+    if let Some(busy) = mounts.iter().find(|m| writers.active.contains_key(*m)) {
+        return Err(format!(
+            "Na {busy} právě probíhá zápis (report, export, záloha), disk nelze odebrat"
+        ));
+    }
+    writers.ejecting.extend(mounts.iter().cloned());
+    Ok(EjectGuard(mounts.to_vec()))
+}
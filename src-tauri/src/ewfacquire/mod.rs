@@ -1,13 +1,18 @@
+use crate::copy_configs::policy::{apply_policy, effective_json};
+use crate::copy_configs::validation::{describe, parse_ewf_config, EwfSettings};
+use crate::copy_configs::NewConfig;
 use crate::disk_utils::get_mountpoint_for_interface;
+use crate::db::log_writer::ProcessLogWriter;
 use crate::led::LED_CONTROLLER;
 use crate::logger::{log_debug, log_error};
-use crate::report::generate_report_ewfacquire;
+use crate::report::{snapshot, spawn_report_job};
 use crate::websocket;
 use chrono::Utc;
 use lazy_static::lazy_static;
 use regex::Regex;
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::thread;
@@ -78,28 +83,9 @@ pub struct EwfParams {
     pub notes: String,
     pub offset: u64,
     pub bytes_to_read: u64,
-}
-
-#[derive(Debug)]
-struct EwfConfig {
-    confname: String,
-    codepage: String,
-    sectors_per_read: String,
-    bytes_to_read: String,
-    compression_method: String,
-    compression_level: String,
-    hash_types: String,
-    ewf_format: String,
-    granularity_sectors: String,
-    notes: String,
-    offset: String,
-    process_buffer_size: String,
-    bytes_per_sector: String,
-    read_retry_count: String,
-    swap_byte_pairs: bool,
-    segment_size: String,
-    zero_on_read_error: bool,
-    use_chunk_data: bool,
+    /// Hodnoty polí s politikou `ask` zadané operátorem
+    #[serde(default)]
+    pub overrides: HashMap<String, String>,
 }
 
 #[derive(Serialize)]
@@ -145,6 +131,9 @@ struct WsProcessDone {
     id: i64,
     status: String,
     end_datetime: String,
+    /// Výpis procesu není v databázi celý nebo nebyl zálohovaný
+    #[serde(skip_serializing_if = "Option::is_none")]
+    log_error: Option<String>,
 }
 
 #[tauri::command(rename_all = "snake_case")]
@@ -208,8 +197,8 @@ pub async fn run_ewfacquire(
         .map(|path| strip_dev_prefix(path))
         .collect::<Vec<_>>();
 
-    let (config, process_id) =
-        tauri::async_runtime::spawn_blocking(move || -> Result<(EwfConfig, i64), String> {
+    let (config, settings, process_id) =
+        tauri::async_runtime::spawn_blocking(move || -> Result<(NewConfig, EwfSettings, i64), String> {
             let mut conn = execute_with_retry(
                 "DB connection",
                 || {
@@ -235,7 +224,7 @@ pub async fn run_ewfacquire(
                          compression_method, compression_level, hash_types, ewf_format,
                          granularity_sectors, notes, offset, process_buffer_size,
                          bytes_per_sector, read_retry_count, swap_byte_pairs,
-                         segment_size, zero_on_read_error, use_chunk_data
+                         segment_size, zero_on_read_error, use_chunk_data, field_policy
                          FROM ewf_config
                          WHERE id = ?1 AND active = 1",
                     )
@@ -245,7 +234,7 @@ pub async fn run_ewfacquire(
                     })?;
 
                 stmt.query_row([config_id], |row| {
-                    Ok(EwfConfig {
+                    Ok(NewConfig {
                         confname: row.get(0)?,
                         codepage: row.get(1)?,
                         sectors_per_read: row.get(2)?,
@@ -264,6 +253,7 @@ pub async fn run_ewfacquire(
                         segment_size: row.get(15)?,
                         zero_on_read_error: row.get(16)?,
                         use_chunk_data: row.get(17)?,
+                        field_policy: row.get(18)?,
                     })
                 })
                 .map_err(|e| {
@@ -272,6 +262,24 @@ pub async fn run_ewfacquire(
                 })?
             };
 
+            // neplatná konfigurace se nespustí, hodnoty se nenahrazují výchozími
+            let config = apply_policy(
+                &config,
+                &config.field_policy,
+                &ewf_params_db.overrides,
+                true,
+            )
+            .map_err(|e| {
+                let msg = format!("Neplatné zadání pro konfiguraci {}: {}", config_id, describe(&e));
+                log_error(&msg);
+                msg
+            })?;
+            let settings = parse_ewf_config(&config).map_err(|e| {
+                let msg = format!("Neplatná konfigurace {}: {}", config_id, describe(&e));
+                log_error(&msg);
+                msg
+            })?;
+
             let source_disk_id: i64 = tx
                 .query_row(
                     "SELECT id FROM interface WHERE interface_path = ?1 LIMIT 1",
@@ -323,6 +331,19 @@ pub async fn run_ewfacquire(
                 None
             };
 
+            // skutečně použité hodnoty včetně zadání při spuštění
+            let mut runtime = vec![("segment_size", serde_json::json!(settings.segment_size))];
+            if config.offset == "ask" {
+                runtime.push(("offset", serde_json::json!(ewf_params_db.offset)));
+            }
+            if config.bytes_to_read == "ask" {
+                runtime.push(("bytes_to_read", serde_json::json!(ewf_params_db.bytes_to_read)));
+            }
+            if config.notes == "ask" {
+                runtime.push(("notes", serde_json::json!(ewf_params_db.notes)));
+            }
+            let effective_config = effective_json(&config, &ewf_params_db.overrides, &runtime);
+
             tx.execute(
                 "INSERT INTO copy_log_ewf (
                     config_id,
@@ -336,8 +357,9 @@ pub async fn run_ewfacquire(
                     notes,
                     offset,
                     bytes_to_read,
+                    effective_config,
                     start_datetime
-                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, DATETIME('now'))",
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, DATETIME('now'))",
                 rusqlite::params![
                     config_id,
                     ewf_params_db.case_number.replace("'", "''"),
@@ -349,7 +371,8 @@ pub async fn run_ewfacquire(
                     second_output_id.unwrap_or(0),
                     ewf_params_db.notes.replace("'", "''"),
                     ewf_params_db.offset,
-                    ewf_params_db.bytes_to_read
+                    ewf_params_db.bytes_to_read,
+                    effective_config
                 ],
             )
             .map_err(|e| {
@@ -385,7 +408,7 @@ pub async fn run_ewfacquire(
                 log_error(&format!("(DB) Chyba při potvrzení transakce: {}", e));
                 format!("(DB) Chyba při potvrzení transakce: {}", e)
             })?;
-            Ok((config, process_id))
+            Ok((config, settings, process_id))
         })
         .await
         .map_err(|e| format!("(async) Chyba při spawn_blocking: {}", e))??;
@@ -429,7 +452,7 @@ pub async fn run_ewfacquire(
         &mut args_exec,
         &mut args_print,
         "-b",
-        &config.sectors_per_read,
+        &settings.sectors_per_read.to_string(),
     );
     if config.bytes_to_read != "whole" {
         push_pair(
@@ -467,12 +490,11 @@ pub async fn run_ewfacquire(
         &ewf_params.evidence_number,
     );
     push_pair(&mut args_exec, &mut args_print, "-f", &config.ewf_format);
-    let granularity = config.granularity_sectors.parse::<u32>().unwrap_or(2);
     push_pair(
         &mut args_exec,
         &mut args_print,
         "-g",
-        &granularity.to_string(),
+        &settings.granularity_sectors.to_string(),
     );
 
     push_pair(&mut args_exec, &mut args_print, "-m", "fixed");
@@ -490,34 +512,40 @@ pub async fn run_ewfacquire(
     } else {
         push_pair(&mut args_exec, &mut args_print, "-o", &config.offset);
     }
-    if config.process_buffer_size != "auto" {
+    if let Some(process_buffer_size) = settings.process_buffer_size {
         push_pair(
             &mut args_exec,
             &mut args_print,
             "-p",
-            &config.process_buffer_size,
+            &process_buffer_size.to_string(),
         );
     }
-    if config.bytes_per_sector != "auto" {
+    if let Some(bytes_per_sector) = settings.bytes_per_sector {
         push_pair(
             &mut args_exec,
             &mut args_print,
             "-P",
-            &config.bytes_per_sector,
+            &bytes_per_sector.to_string(),
         );
     }
 
-    let mut retry_count = config.read_retry_count.parse::<u8>().unwrap_or(2);
-    if retry_count < 1 || retry_count > 5 { // uprav rozsah podle ewfacquire
-        retry_count = 2;
-    }
-    push_pair(&mut args_exec, &mut args_print, "-r", &retry_count.to_string());
+    push_pair(
+        &mut args_exec,
+        &mut args_print,
+        "-r",
+        &settings.read_retry_count.to_string(),
+    );
 
     if config.swap_byte_pairs {
         args_exec.push("-s".to_string());
         args_print.push("-s".to_string());
     }
-    push_pair(&mut args_exec, &mut args_print, "-S", &config.segment_size);
+    push_pair(
+        &mut args_exec,
+        &mut args_print,
+        "-S",
+        &settings.segment_size.to_string(),
+    );
 
     if !config.hash_types.is_empty() && config.hash_types != "[]" {
         let hash_types = config
@@ -578,7 +606,7 @@ pub async fn run_ewfacquire(
         &format!("{}/{}", evidence_dir_1, evidence_number),
     );
 
-    if let Some(evidence_dir_2) = evidence_dir_2 {
+    if let Some(evidence_dir_2) = &evidence_dir_2 {
         push_pair(
             &mut args_exec,
             &mut args_print,
@@ -606,6 +634,35 @@ pub async fn run_ewfacquire(
     log_debug(&format!("Spouštím příkaz: {}", full_command_print));
     println!("Spouštím příkaz: {}", full_command_print);
 
+    // příkaz, chybový log, sériové číslo zdroje a snímek disků si pamatujeme
+    // pro report a historii
+    {
+        let output_devices: Vec<String> = output_interfaces
+            .iter()
+            .map(|i| format!("/dev/disk/by-path/{}", strip_dev_prefix(i)))
+            .collect();
+        let conn = crate::db::create_new_connection()
+            .map_err(|e| format!("Failed to create connection: {}", e))?;
+        conn.execute(
+            "UPDATE copy_process SET command_line = ?1, error_log_path = ?2, source_serial = ?3,
+             acquisition_snapshot = ?4 WHERE id = ?5",
+            params![
+                full_command_print,
+                format!("{}/copy", evidence_dir_1),
+                crate::disk_utils::get_disk_serial(&actual_input_device),
+                snapshot::capture_disks(&actual_input_device, &output_devices).to_string(),
+                process_id
+            ],
+        )
+        .map_err(|e| format!("(DB) Chyba při zápisu příkazu do copy_process: {}", e))?;
+    }
+
+    // složky s důkazem pro snímek souborů obrazu po skončení akvizice
+    let image_dirs: Vec<String> = std::iter::once(evidence_dir_1.clone())
+        .chain(evidence_dir_2.clone())
+        .collect();
+    let image_evidence_number = evidence_number.to_string();
+
     let shell = app_handle.shell();
     let (mut rx, _child) = shell
         .command("sudo")
@@ -632,18 +689,12 @@ pub async fn run_ewfacquire(
         .map_err(|e| format!("Failed to get copy_log_id from copy_process: {}", e))?
     };
 
+    let log_writer = ProcessLogWriter::start(process_id)?;
+
     while let Some(event) = rx.recv().await {
         match event {
             CommandEvent::Stdout(line) => {
-                {
-                    let conn = crate::db::create_new_connection()
-                        .map_err(|e| format!("(DB stdout) Failed to create connection: {}", e))?;
-                    conn.execute(
-                        "INSERT INTO process_log_lines (process_id, line_content, line_number)
-                         VALUES (?1, ?2, COALESCE((SELECT MAX(line_number) FROM process_log_lines WHERE process_id=?1),0)+1)",
-                        params![process_id, line],
-                    ).map_err(|e| format!("(DB stdout) Error writing log: {}", e))?;
-                }
+                log_writer.push(String::from_utf8_lossy(&line).to_string());
                 let output_msg = WsProcessOutput {
                     msg_type: "ProcessOutput".to_string(),
                     id: process_id,
@@ -737,13 +788,7 @@ pub async fn run_ewfacquire(
             }
             CommandEvent::Stderr(line) => {
                 let log_line = format!("STDERR: {}", String::from_utf8_lossy(&line));
-                let conn = crate::db::create_new_connection()
-                    .map_err(|e| format!("(DB stderr) Failed to create connection: {}", e))?;
-                conn.execute(
-                    "INSERT INTO process_log_lines (process_id, line_content, line_number)
-                     VALUES (?1, ?2, COALESCE((SELECT MAX(line_number) FROM process_log_lines WHERE process_id=?1),0)+1)",
-                    params![process_id, log_line],
-                ).map_err(|e| format!("(DB stderr) Chyba při zápisu stderr: {}", e))?;
+                log_writer.push(log_line);
             }
             CommandEvent::Terminated(exit_code) => {
                 LED_CONTROLLER.notify_process_end();
@@ -759,8 +804,18 @@ pub async fn run_ewfacquire(
                     final_status
                 );
 
+                // výpis musí být celý v DB dřív, než se z něj vyrenderuje report
+                let log_failure = match log_writer.finish().await {
+                    Ok(_) => None,
+                    Err(e) => {
+                        log_error(&e);
+                        Some(e)
+                    }
+                };
+
                 let end_time = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
                 let end_time_for_db = end_time.clone();
+                let log_failure_for_db = log_failure.clone();
 
                 tauri::async_runtime::spawn_blocking(move || -> Result<(), String> {
                     let conn = crate::db::create_new_connection()
@@ -787,28 +842,37 @@ pub async fn run_ewfacquire(
                         .map_err(|e| format!("Error updating copy_log_ewf: {}", e))?;
 
                     conn.execute(
-                        "UPDATE copy_process SET status = ?, end_datetime = ? WHERE id = ?",
-                        params![final_status, end_time_for_db, process_id],
+                        "UPDATE copy_process SET status = ?, end_datetime = ?, log_error = ? WHERE id = ?",
+                        params![final_status, end_time_for_db, log_failure_for_db, process_id],
                     )
                     .map_err(|e| format!("Error updating copy_process: {}", e))?;
 
+                    // soubory obrazu patří do snímku akvizice, report je už nehledá na disku
+                    if let Err(e) = snapshot::record_image_files(
+                        &conn,
+                        process_id,
+                        &image_dirs,
+                        &image_evidence_number,
+                    ) {
+                        log_error(&format!(
+                            "Soubory obrazu procesu {process_id} nebyly uloženy: {e}"
+                        ));
+                    }
+
                     Ok(())
                 })
                 .await
                 .map_err(|e| e.to_string())??;
 
-                if final_status == "done" {
-                    let report_result = generate_report_ewfacquire(process_id);
-                    if let Err(e) = report_result {
-                        eprintln!("Chyba při generování reportu: {}", e);
-                    }
-                }
+                // report vzniká i pro chybové a přerušené běhy, na pozadí
+                spawn_report_job(process_id);
 
                 let process_done = WsProcessDone {
                     msg_type: "ProcessDone".to_string(),
                     id: process_id,
                     status: final_status.to_string(),
                     end_datetime: end_time,
+                    log_error: log_failure,
                 };
                 let json_done = serde_json::to_string(&process_done).unwrap_or_default();
                 websocket::broadcast_message(&json_done).await;
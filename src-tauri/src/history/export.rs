@@ -0,0 +1,360 @@
+//! Export vybraných záznamů historie na připojený výstup (USB flashka apod.).
+//!
+//! Struktura exportu `cratec-export-<YYYYmmdd-HHMMSS>/`:
+//!
+//! ```text
+//! export.json                 metadata exportu (filtr, čas, verze)
+//! history.csv, history.json   souhrn vybraných akvizic
+//! reports/<process_id>/audit-report-<archive_id>.pdf
+//! logs/process-<process_id>.log
+//! configs/<ewf|dd>_config-<config_id>.json
+//! configs/process-<process_id>-effective.json   skutečně použité hodnoty
+//! manifest-sha256.txt         SHA-256 všech souborů výše (formát sha256sum)
+//! ```
+use super::{history_page, row_to_json, HistoryEntry, HistoryQuery};
+use crate::db::log_writer::line_text;
+use crate::db::DB_POOL;
+use crate::disk_utils::writers::write_guard;
+use crate::logger::log_debug;
+use crate::report::{sha256_hex, tex::with_work_dir, validate_output_mount};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+pub const MANIFEST_FILE: &str = "manifest-sha256.txt";
+
+#[derive(Debug, Serialize)]
+pub struct HistoryExport {
+    /// Složka exportu na výstupu
+    pub path: String,
+    pub entries: usize,
+    pub reports: usize,
+    pub files: usize,
+    pub manifest_sha256: String,
+    /// Např. archivovaný report, jehož PDF neodpovídá uloženému hashi
+    pub warnings: Vec<String>,
+}
+
+/// Soubory zapsané do pracovní složky, relativní cesty pro manifest.
+struct ExportWriter<'a> {
+    root: &'a Path,
+    files: Vec<String>,
+}
+
+impl ExportWriter<'_> {
+    fn write(&mut self, rel_path: &str, data: impl AsRef<[u8]>) -> Result<(), String> {
+        let path = self.root.join(rel_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, data).map_err(|e| format!("{rel_path}: {e}"))?;
+        self.files.push(rel_path.to_string());
+        Ok(())
+    }
+
+    /// Manifest ve formátu `sha256sum -c`, vrací jeho vlastní SHA-256.
+    fn write_manifest(&self) -> Result<String, String> {
+        let mut manifest = String::new();
+        let mut files = self.files.clone();
+        files.sort();
+        for rel_path in files {
+            let data = fs::read(self.root.join(&rel_path)).map_err(|e| e.to_string())?;
+            manifest.push_str(&format!("{}  {}\n", sha256_hex(&data), rel_path));
+        }
+        fs::write(self.root.join(MANIFEST_FILE), &manifest).map_err(|e| e.to_string())?;
+        Ok(sha256_hex(manifest.as_bytes()))
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn history_csv(entries: &[HistoryEntry]) -> String {
+    let mut out = String::from(
+        "id,tool,status,start_datetime,end_datetime,case_number,evidence_number,\
+         investigator_name,description,notes,source_serial,config_id,\
+         md5_hash,sha1_hash,sha256_hash,sha384_hash,sha512_hash\n",
+    );
+    for e in entries {
+        let opt = |v: &Option<String>| v.clone().unwrap_or_default();
+        let row = [
+            e.id.to_string(),
+            e.tool.clone(),
+            e.status.clone(),
+            e.start_datetime.clone(),
+            opt(&e.end_datetime),
+            e.case_number.clone(),
+            e.evidence_number.clone(),
+            e.investigator_name.clone(),
+            e.description.clone(),
+            opt(&e.notes),
+            opt(&e.source_serial),
+            e.config_id.map(|v| v.to_string()).unwrap_or_default(),
+            opt(&e.md5_hash),
+            opt(&e.sha1_hash),
+            opt(&e.sha256_hash),
+            opt(&e.sha384_hash),
+            opt(&e.sha512_hash),
+        ];
+        out.push_str(
+            &row.iter()
+                .map(|f| csv_field(f))
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        out.push('\n');
+    }
+    out
+}
+
+/// Všechny záznamy odpovídající filtru (přes všechny stránky).
+fn load_entries(conn: &Connection, query: &HistoryQuery) -> Result<Vec<HistoryEntry>, String> {
+    let mut query = HistoryQuery {
+        cursor: None,
+        limit: Some(super::HISTORY_MAX_LIMIT),
+        ..query.clone()
+    };
+    let mut entries = Vec::new();
+    loop {
+        let page = history_page(conn, &query)?;
+        entries.extend(page.entries);
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return Ok(entries),
+        }
+    }
+}
+
+fn export_process(
+    conn: &Connection,
+    entry: &HistoryEntry,
+    out: &mut ExportWriter,
+    warnings: &mut Vec<String>,
+) -> Result<usize, String> {
+    let db = |e: rusqlite::Error| e.to_string();
+
+    // archivované reporty
+    let mut stmt = conn
+        .prepare("SELECT id, pdf, pdf_sha256 FROM report_archive WHERE process_id = ?1 ORDER BY id")
+        .map_err(db)?;
+    let reports = stmt
+        .query_map(params![entry.id], |r| {
+            Ok((
+                r.get::<_, i64>(0)?,
+                r.get::<_, Vec<u8>>(1)?,
+                r.get::<_, String>(2)?,
+            ))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(db)?;
+    for (archive_id, pdf, sha) in &reports {
+        if sha256_hex(pdf) != *sha {
+            warnings.push(format!(
+                "Archivovaný report {archive_id} (proces {}) neodpovídá uloženému SHA-256",
+                entry.id
+            ));
+        }
+        out.write(
+            &format!("reports/{}/audit-report-{archive_id}.pdf", entry.id),
+            pdf,
+        )?;
+    }
+
+    // skutečně použitá konfigurace (starší záznamy ji nemají, platí preset)
+    let effective: Option<String> = conn
+        .query_row(
+            "SELECT COALESCE(e.effective_config, d.effective_config) FROM copy_process p
+             LEFT JOIN copy_log_ewf e ON e.id = p.triggered_by_ewf
+             LEFT JOIN copy_log_dd d ON d.id = p.triggered_by_dd
+             WHERE p.id = ?1",
+            params![entry.id],
+            |r| r.get(0),
+        )
+        .map_err(db)?;
+    if let Some(effective) = effective {
+        let json = serde_json::from_str::<serde_json::Value>(&effective)
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .unwrap_or(effective);
+        out.write(
+            &format!("configs/process-{}-effective.json", entry.id),
+            json,
+        )?;
+    }
+
+    // výpis procesu
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, line_content FROM process_log_lines
+             WHERE process_id = ?1 ORDER BY line_number ASC",
+        )
+        .map_err(db)?;
+    let lines = stmt
+        .query_map(params![entry.id], |r| {
+            Ok(format!("{}\t{}", r.get::<_, String>(0)?, line_text(r, 1)?))
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(db)?;
+    if !lines.is_empty() {
+        out.write(
+            &format!("logs/process-{}.log", entry.id),
+            lines.join("\n") + "\n",
+        )?;
+    }
+
+    Ok(reports.len())
+}
+
+/// Snapshot konfigurace (i neaktivní – záznam historie na ni odkazuje).
+fn export_config(
+    conn: &Connection,
+    table: &str,
+    config_id: i64,
+    out: &mut ExportWriter,
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT * FROM {table} WHERE id = ?1"))
+        .map_err(|e| e.to_string())?;
+    let cols = stmt
+        .column_names()
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+    let config = stmt
+        .query_row(params![config_id], |r| Ok(row_to_json(r, &cols)))
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(config) = config {
+        let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        out.write(&format!("configs/{table}-{config_id}.json"), json)?;
+    }
+    Ok(())
+}
+
+fn build_export(
+    conn: &Connection,
+    query: &HistoryQuery,
+    root: &Path,
+    created_at: &str,
+) -> Result<HistoryExport, String> {
+    let entries = load_entries(conn, query)?;
+    if entries.is_empty() {
+        return Err("Filtru neodpovídá žádný záznam historie".into());
+    }
+
+    let mut out = ExportWriter {
+        root,
+        files: Vec::new(),
+    };
+    let mut warnings = Vec::new();
+    let mut reports = 0;
+    let mut configs = BTreeSet::new();
+    for entry in &entries {
+        reports += export_process(conn, entry, &mut out, &mut warnings)?;
+        if let Some(config_id) = entry.config_id {
+            let table = if entry.tool == "ewfacquire" {
+                "ewf_config"
+            } else {
+                "dd_config"
+            };
+            configs.insert((table, config_id));
+        }
+    }
+    for (table, config_id) in configs {
+        export_config(conn, table, config_id, &mut out)?;
+    }
+
+    out.write("history.csv", history_csv(&entries))?;
+    out.write(
+        "history.json",
+        serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?,
+    )?;
+    let info = json!({
+        "format": "cratec-history-export",
+        "version": 1,
+        "created_at": created_at,
+        "software_version": env!("CARGO_PKG_VERSION"),
+        "filter": {
+            "case_number": query.case_number,
+            "evidence_number": query.evidence_number,
+            "investigator": query.investigator,
+            "status": query.status,
+            "date_from": query.date_from,
+            "date_to": query.date_to,
+            "source_serial": query.source_serial,
+            "tool": query.tool,
+            "search": query.search,
+        },
+        "entries": entries.len(),
+        "reports": reports,
+    });
+    out.write(
+        "export.json",
+        serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?,
+    )?;
+    let manifest_sha256 = out.write_manifest()?;
+
+    Ok(HistoryExport {
+        path: String::new(),
+        entries: entries.len(),
+        reports,
+        files: out.files.len() + 1,
+        manifest_sha256,
+        warnings,
+    })
+}
+
+/// Exportuje záznamy historie odpovídající filtru (typicky případ nebo rozsah
+/// dat) do nové složky na připojeném výstupu pod `mount_root()`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_history(query: HistoryQuery, target: String) -> Result<HistoryExport, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<HistoryExport, String> {
+        let mount = validate_output_mount(&target)?;
+        let _writing = write_guard(&mount)?;
+        let now = chrono::Local::now();
+        let dir_name = format!("cratec-export-{}", now.format("%Y%m%d-%H%M%S"));
+        let dest = format!("{mount}/{dir_name}");
+        if Path::new(&dest).exists() {
+            return Err(format!("{dest} již existuje"));
+        }
+
+        with_work_dir("history_export", |work_dir| {
+            let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+            let mut export = build_export(
+                pooled.connection(),
+                &query,
+                work_dir,
+                &now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            )?;
+            drop(pooled);
+
+            let status = Command::new("sudo")
+                .arg("cp")
+                .arg("-r")
+                .arg(work_dir)
+                .arg(&dest)
+                .status()
+                .map_err(|e| e.to_string())?;
+            if !status.success() {
+                return Err(format!("sudo cp failed with status {:?}", status.code()));
+            }
+            let _ = Command::new("sync").status();
+            log_debug(&format!(
+                "Export historie: {} záznamů, {} reportů do {dest} (manifest sha256 {})",
+                export.entries, export.reports, export.manifest_sha256
+            ));
+            export.path = dest.clone();
+            Ok(export)
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -1,9 +1,14 @@
 use tauri::command;
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, Map, json};
-use rusqlite::{params, Row, OptionalExtension};
-use rusqlite::types::ValueRef;
+use rusqlite::{params, params_from_iter, Connection, Row, OptionalExtension};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use base64::engine::general_purpose;
+use base64::Engine;
 use crate::db::DB_POOL;
 
+pub mod export;
+
 /// Převod jednoho řádku na JSON objekt, používá předané názvy sloupců
 fn row_to_json(row: &Row, col_names: &[String]) -> Value {
     let mut obj = Map::new();
@@ -106,7 +111,8 @@ pub async fn get_config_entry(config_id: i64, config_type: String) -> Result<Val
         "dd"  | "dd_config"  => "dd_config",
         other => return Err(format!("Unknown config_type: {}", other))
     };
-    let sql = format!("SELECT * FROM {} WHERE id = ?1 AND active = 1", table);
+    // i neaktivní (starší) verze – historie musí ukázat nastavení, se kterým proces běžel
+    let sql = format!("SELECT * FROM {} WHERE id = ?1", table);
     let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
     pooled.execute(|conn| {
         let mut st = conn.prepare(&sql)?;
@@ -117,4 +123,337 @@ pub async fn get_config_entry(config_id: i64, config_type: String) -> Result<Val
             Ok(Value::Null)
         }
     }).map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+/// Filtry, řazení a stránkování pro `query_history`. Všechna pole jsou volitelná.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub case_number: Option<String>,
+    pub evidence_number: Option<String>,
+    pub investigator: Option<String>,
+    /// running | done | error
+    pub status: Option<String>,
+    /// YYYY-MM-DD, včetně
+    pub date_from: Option<String>,
+    /// YYYY-MM-DD, včetně
+    pub date_to: Option<String>,
+    pub source_serial: Option<String>,
+    /// ewfacquire | dcfldd
+    pub tool: Option<String>,
+    /// Fulltext nad číslem případu/důkazu, vyšetřovatelem, popisem a poznámkami
+    pub search: Option<String>,
+    /// start_datetime (výchozí) | end_datetime | case_number | evidence_number | status | id
+    pub sort: Option<String>,
+    /// Výchozí je sestupně (nejnovější nahoře)
+    pub descending: Option<bool>,
+    /// `next_cursor` z předchozí stránky
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Jeden záznam historie – proces a údaje z jeho copy logu.
+#[derive(Debug, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// ewfacquire | dcfldd
+    pub tool: String,
+    pub status: String,
+    pub start_datetime: String,
+    pub end_datetime: Option<String>,
+    pub case_number: String,
+    pub evidence_number: String,
+    pub investigator_name: String,
+    pub description: String,
+    pub notes: Option<String>,
+    pub source_serial: Option<String>,
+    pub copy_log_id: Option<i64>,
+    pub config_id: Option<i64>,
+    pub md5_hash: Option<String>,
+    pub sha1_hash: Option<String>,
+    pub sha256_hash: Option<String>,
+    pub sha384_hash: Option<String>,
+    pub sha512_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// `None` = poslední stránka
+    pub next_cursor: Option<String>,
+}
+
+/// Pozice v seřazeném výsledku: hodnota řadicího sloupce a id posledního záznamu.
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryCursor {
+    sort: String,
+    value: String,
+    id: i64,
+}
+
+const HISTORY_DEFAULT_LIMIT: u32 = 50;
+const HISTORY_MAX_LIMIT: u32 = 500;
+
+/// Procesy spojené s jejich copy logem (ewf nebo dd) do jednoho řádku.
+const HISTORY_BASE_SQL: &str = r#"
+    SELECT p.id AS id,
+           CASE WHEN p.triggered_by_ewf IS NOT NULL THEN 'ewfacquire' ELSE 'dcfldd' END AS tool,
+           p.status AS status,
+           p.start_datetime AS start_datetime,
+           p.end_datetime AS end_datetime,
+           COALESCE(e.case_number, d.case_number, '') AS case_number,
+           COALESCE(e.evidence_number, d.evidence_number, '') AS evidence_number,
+           COALESCE(e.investigator_name, d.investigator_name, '') AS investigator_name,
+           COALESCE(e.description, d.description, '') AS description,
+           COALESCE(e.notes, d.notes) AS notes,
+           p.source_serial AS source_serial,
+           COALESCE(p.triggered_by_ewf, p.triggered_by_dd) AS copy_log_id,
+           COALESCE(e.config_id, d.config_id) AS config_id,
+           COALESCE(e.md5_hash, d.md5_hash) AS md5_hash,
+           COALESCE(e.sha1_hash, d.sha1_hash) AS sha1_hash,
+           COALESCE(e.sha256_hash, d.sha256_hash) AS sha256_hash,
+           d.sha384_hash AS sha384_hash,
+           d.sha512_hash AS sha512_hash
+    FROM copy_process p
+    LEFT JOIN copy_log_ewf e ON e.id = p.triggered_by_ewf
+    LEFT JOIN copy_log_dd d ON d.id = p.triggered_by_dd
+"#;
+
+/// Řadicí výraz nad `HISTORY_BASE_SQL`; jen sloupce z tohoto seznamu se dostanou do SQL.
+fn history_sort_column(sort: &str) -> Result<&'static str, String> {
+    match sort {
+        "start_datetime" => Ok("h.start_datetime"),
+        "end_datetime" => Ok("COALESCE(h.end_datetime, '')"),
+        "case_number" => Ok("h.case_number"),
+        "evidence_number" => Ok("h.evidence_number"),
+        "status" => Ok("h.status"),
+        "id" => Ok("h.id"),
+        other => Err(format!("Unknown sort column: {}", other)),
+    }
+}
+
+/// Hodnota pro `LIKE ... ESCAPE '\'` hledající podřetězec.
+fn like_pattern(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Slova hledaného textu jako FTS5 dotaz: každé slovo v uvozovkách a jako prefix,
+/// takže uživatelský vstup nemůže obsahovat FTS operátory.
+fn fts_query(tokens: &[&str]) -> String {
+    tokens
+        .iter()
+        .map(|t| format!("\"{}\"*", t.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn encode_cursor(cursor: &HistoryCursor) -> String {
+    general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(cursor).unwrap_or_default())
+}
+
+fn decode_cursor(cursor: &str) -> Result<HistoryCursor, String> {
+    general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|b| serde_json::from_slice(&b).ok())
+        .ok_or_else(|| "Invalid history cursor".to_string())
+}
+
+fn history_entry(r: &Row) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: r.get("id")?,
+        tool: r.get("tool")?,
+        status: r.get("status")?,
+        start_datetime: r.get("start_datetime")?,
+        end_datetime: r.get("end_datetime")?,
+        case_number: r.get("case_number")?,
+        evidence_number: r.get("evidence_number")?,
+        investigator_name: r.get("investigator_name")?,
+        description: r.get("description")?,
+        notes: r.get("notes")?,
+        source_serial: r.get("source_serial")?,
+        copy_log_id: r.get("copy_log_id")?,
+        config_id: r.get("config_id")?,
+        md5_hash: r.get("md5_hash")?,
+        sha1_hash: r.get("sha1_hash")?,
+        sha256_hash: r.get("sha256_hash")?,
+        sha384_hash: r.get("sha384_hash")?,
+        sha512_hash: r.get("sha512_hash")?,
+    })
+}
+
+/// Hodnota řadicího sloupce záznamu pro kurzor (stejně jako v `history_sort_column`).
+fn history_sort_value(entry: &HistoryEntry, sort: &str) -> String {
+    match sort {
+        "end_datetime" => entry.end_datetime.clone().unwrap_or_default(),
+        "case_number" => entry.case_number.clone(),
+        "evidence_number" => entry.evidence_number.clone(),
+        "status" => entry.status.clone(),
+        "id" => entry.id.to_string(),
+        _ => entry.start_datetime.clone(),
+    }
+}
+
+/// Sestaví WHERE a parametry pro filtry dotazu (bez kurzoru).
+fn history_filters(conn: &Connection, q: &HistoryQuery) -> Result<(Vec<String>, Vec<SqlValue>), String> {
+    let mut clauses = Vec::new();
+    let mut args: Vec<SqlValue> = Vec::new();
+    let non_empty = |v: &Option<String>| v.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+
+    for (column, value) in [
+        ("h.case_number", &q.case_number),
+        ("h.evidence_number", &q.evidence_number),
+        ("h.investigator_name", &q.investigator),
+        ("h.source_serial", &q.source_serial),
+    ] {
+        if let Some(v) = non_empty(value) {
+            clauses.push(format!("{} LIKE ? ESCAPE '\\'", column));
+            args.push(SqlValue::Text(like_pattern(&v)));
+        }
+    }
+    if let Some(status) = non_empty(&q.status) {
+        if !["running", "done", "error"].contains(&status.as_str()) {
+            return Err(format!("Unknown status: {}", status));
+        }
+        clauses.push("h.status = ?".into());
+        args.push(SqlValue::Text(status));
+    }
+    if let Some(tool) = non_empty(&q.tool) {
+        let tool = match tool.as_str() {
+            "ewf" | "ewfacquire" => "ewfacquire",
+            "dd" | "dcfldd" => "dcfldd",
+            other => return Err(format!("Unknown tool: {}", other)),
+        };
+        clauses.push("h.tool = ?".into());
+        args.push(SqlValue::Text(tool.into()));
+    }
+    if let Some(from) = non_empty(&q.date_from) {
+        clauses.push("date(h.start_datetime) >= date(?)".into());
+        args.push(SqlValue::Text(from));
+    }
+    if let Some(to) = non_empty(&q.date_to) {
+        clauses.push("date(h.start_datetime) <= date(?)".into());
+        args.push(SqlValue::Text(to));
+    }
+    if let Some(search) = non_empty(&q.search) {
+        let tokens = search.split_whitespace().collect::<Vec<_>>();
+        let has_fts: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'history_fts')",
+                [],
+                |r| r.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if has_fts {
+            clauses.push("h.id IN (SELECT rowid FROM history_fts WHERE history_fts MATCH ?)".into());
+            args.push(SqlValue::Text(fts_query(&tokens)));
+        } else {
+            // bez FTS5: každé slovo musí být v některém z prohledávaných sloupců
+            for token in tokens {
+                clauses.push(
+                    "(h.case_number || ' ' || h.evidence_number || ' ' || h.investigator_name || ' ' || \
+                     h.description || ' ' || COALESCE(h.notes, '')) LIKE ? ESCAPE '\\'"
+                        .into(),
+                );
+                args.push(SqlValue::Text(like_pattern(token)));
+            }
+        }
+    }
+    Ok((clauses, args))
+}
+
+/// Jedna stránka výsledku `query` (viz `query_history`).
+fn history_page(conn: &Connection, query: &HistoryQuery) -> Result<HistoryPage, String> {
+    let sort = query.sort.clone().unwrap_or_else(|| "start_datetime".into());
+    let sort_column = history_sort_column(&sort)?;
+    let descending = query.descending.unwrap_or(true);
+    let limit = query.limit.unwrap_or(HISTORY_DEFAULT_LIMIT).clamp(1, HISTORY_MAX_LIMIT);
+    let cursor = query.cursor.as_deref().map(decode_cursor).transpose()?;
+    if cursor.as_ref().is_some_and(|c| c.sort != sort) {
+        return Err("History cursor does not match the requested sort".into());
+    }
+
+    let (mut clauses, mut args) = history_filters(conn, query)?;
+
+    // keyset stránkování: záznamy za posledním vráceným (řadicí hodnota, id)
+    let cmp = if descending { "<" } else { ">" };
+    if let Some(c) = &cursor {
+        let value = if sort == "id" {
+            SqlValue::Integer(c.id)
+        } else {
+            SqlValue::Text(c.value.clone())
+        };
+        clauses.push(format!(
+            "({col} {cmp} ? OR ({col} = ? AND h.id {cmp} ?))",
+            col = sort_column,
+            cmp = cmp
+        ));
+        args.push(value.clone());
+        args.push(value);
+        args.push(SqlValue::Integer(c.id));
+    }
+
+    let where_sql = if clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", clauses.join(" AND "))
+    };
+    let order = if descending { "DESC" } else { "ASC" };
+    let sql = format!(
+        "SELECT * FROM ({base}) h {where_sql} ORDER BY {col} {order}, h.id {order} LIMIT {limit}",
+        base = HISTORY_BASE_SQL,
+        where_sql = where_sql,
+        col = sort_column,
+        order = order,
+        limit = limit + 1
+    );
+
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let mut entries = stmt
+        .query_map(params_from_iter(args), history_entry)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+
+    let next_cursor = if entries.len() > limit as usize {
+        entries.truncate(limit as usize);
+        entries.last().map(|last| {
+            encode_cursor(&HistoryCursor {
+                sort: sort.clone(),
+                value: history_sort_value(last, &sort),
+                id: last.id,
+            })
+        })
+    } else {
+        None
+    };
+    Ok(HistoryPage { entries, next_cursor })
+}
+
+/// Vyhledá v historii akvizic podle filtrů, seřadí a vrátí jednu stránku.
+/// Další stránka se načte předáním `next_cursor` se stejnými filtry a řazením.
+#[command(rename_all = "snake_case")]
+pub async fn query_history(query: HistoryQuery) -> Result<HistoryPage, String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    pooled
+        .execute(|conn| Ok(history_page(conn, &query)?))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fts_query;
+
+    #[test]
+    fn fts_query_quotes_every_token_as_prefix() {
+        assert_eq!(fts_query(&["novak", "disk"]), "\"novak\"* \"disk\"*");
+        assert_eq!(fts_query(&[]), "");
+    }
+
+    #[test]
+    fn fts_query_neutralises_operators() {
+        assert_eq!(fts_query(&["a\"b"]), "\"a\"\"b\"*");
+        assert_eq!(fts_query(&["OR", "NEAR(x"]), "\"OR\"* \"NEAR(x\"*");
+        assert_eq!(fts_query(&["col:val*"]), "\"col:val*\"*");
+    }
+}
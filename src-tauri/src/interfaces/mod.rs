@@ -0,0 +1,525 @@
+//! Správa šachet (tabulka `interface`) a režim učení nových šachet.
+//!
+//! Učení: administrátor spustí `start_bay_learn`, aplikace si zapamatuje
+//! `ID_PATH` všech právě připojených disků a `poll_bay_learn` pak vrátí
+//! návrh šachty pro první nově připojený disk, jehož cesta ještě není
+//! v tabulce `interface`. Návrh se uloží až přes `add_bay`.
+//!
+//! Šachty z nastavení (`[[bays]]`) se při startu znovu vkládají, proto je
+//! nelze odebrat zde – jen přejmenovat nebo změnit stranu.
+use crate::copy_configs::validation::{ConfigError, FieldError};
+use crate::dashboard_layout::{monitor, normalize_interface_path};
+use crate::db::DB_POOL;
+use crate::lockscreen::require_admin;
+use crate::logger::write_audit;
+use crate::settings::settings;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Jak dlouho režim učení čeká na připojení disku.
+const LEARN_TIMEOUT: Duration = Duration::from_secs(300);
+
+static LEARN: Mutex<Option<LearnSession>> = Mutex::new(None);
+
+const LOG_TABLES: &[&str] = &["copy_log_ewf", "copy_log_dd"];
+
+struct LearnSession {
+    started: Instant,
+    /// `ID_PATH` disků připojených při spuštění učení
+    known: HashSet<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Bay {
+    pub id: i64,
+    pub interface_path: String,
+    pub side: String,
+    pub name: String,
+    /// Šachta je definovaná v nastavení jednotky
+    pub from_settings: bool,
+    /// Počet akvizic, které šachtu používají
+    pub used_by: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BayProposal {
+    pub interface_path: String,
+    pub side: String,
+    pub name: String,
+    pub devnode: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum LearnStatus {
+    Inactive,
+    Waiting { remaining_secs: u64 },
+    Found { proposal: BayProposal },
+}
+
+fn error(field: &str, message: &str) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+fn validate_side(side: &str) -> Result<(), Vec<FieldError>> {
+    if ["input", "output"].contains(&side) {
+        Ok(())
+    } else {
+        Err(vec![error("side", "Musí být input nebo output")])
+    }
+}
+
+fn validate_name(conn: &Connection, name: &str, except_id: i64) -> Result<(), ConfigError> {
+    if name.trim().is_empty() {
+        return Err(vec![error("name", "Název nesmí být prázdný")].into());
+    }
+    let taken: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM interface WHERE name = ?1 AND id != ?2)",
+            params![name.trim(), except_id],
+            |r| r.get(0),
+        )
+        .map_err(db_err)?;
+    if taken {
+        return Err(vec![error("name", "Název je použitý u jiné šachty")].into());
+    }
+    Ok(())
+}
+
+fn load_bay(conn: &Connection, id: i64) -> Result<Bay, String> {
+    let settings_paths: HashSet<&str> = settings().bays.iter().map(|b| b.path.as_str()).collect();
+    conn.query_row(
+        "SELECT i.id, i.interface_path, i.side, i.name,
+                (SELECT COUNT(*) FROM copy_log_ewf l
+                  WHERE i.id IN (l.source_disk_id, l.dest_disk_id, l.second_dest_disk_id))
+              + (SELECT COUNT(*) FROM copy_log_dd l
+                  WHERE i.id IN (l.source_disk_id, l.dest_disk_id, l.second_dest_disk_id))
+         FROM interface i WHERE i.id = ?1",
+        params![id],
+        |r| {
+            let interface_path: String = r.get(1)?;
+            Ok(Bay {
+                id: r.get(0)?,
+                from_settings: settings_paths.contains(interface_path.as_str()),
+                interface_path,
+                side: r.get(2)?,
+                name: r.get(3)?,
+                used_by: r.get(4)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(db_err)?
+    .ok_or_else(|| format!("Šachta {id} neexistuje"))
+}
+
+/// Šachta podle `interface_path` (i v zápisu z registru zařízení).
+pub(crate) fn bay_by_interface(conn: &Connection, interface: &str) -> Result<Bay, String> {
+    let wanted = normalize_interface_path(interface);
+    let mut stmt = conn
+        .prepare("SELECT id, interface_path FROM interface")
+        .map_err(db_err)?;
+    let rows = stmt
+        .query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(db_err)?;
+    let id = rows
+        .into_iter()
+        .find(|(_, path)| normalize_interface_path(path) == wanted)
+        .map(|(id, _)| id)
+        .ok_or_else(|| format!("Šachta {} není v tabulce interface", interface))?;
+    load_bay(conn, id)
+}
+
+fn all_bays(conn: &Connection) -> Result<Vec<Bay>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM interface ORDER BY side, name")
+        .map_err(db_err)?;
+    let ids = stmt
+        .query_map([], |r| r.get::<_, i64>(0))
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(db_err)?;
+    ids.into_iter().map(|id| load_bay(conn, id)).collect()
+}
+
+/// Šachta právě slouží běžící akvizici nebo mazání disku.
+pub(crate) fn in_use(conn: &Connection, id: i64) -> Result<bool, String> {
+    let wiping: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM wipe_job WHERE status = 'running' AND bay_id = ?1)",
+            params![id],
+            |r| r.get(0),
+        )
+        .map_err(db_err)?;
+    if wiping {
+        return Ok(true);
+    }
+    for table in LOG_TABLES {
+        let running: bool = conn
+            .query_row(
+                &format!(
+                    "SELECT EXISTS(SELECT 1 FROM {table} WHERE status = 'running'
+                      AND ?1 IN (source_disk_id, dest_disk_id, second_dest_disk_id))"
+                ),
+                params![id],
+                |r| r.get(0),
+            )
+            .map_err(db_err)?;
+        if running {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Po odebrání šachty `id` (nebo změně její strany) musí zůstat alespoň
+/// jedna šachta na straně `side`.
+fn keeps_side(conn: &Connection, id: i64, side: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM interface WHERE side = ?1 AND id != ?2)",
+        params![side, id],
+        |r| r.get(0),
+    )
+    .map_err(db_err)
+}
+
+/// `ID_PATH` a údaje o discích, které jsou právě připojené.
+fn connected_disks() -> Result<Vec<BayProposal>, String> {
+    let mut enumerator =
+        udev::Enumerator::new().map_err(|e| format!("Failed to create udev enumerator: {}", e))?;
+    enumerator
+        .match_subsystem("block")
+        .map_err(|e| format!("Failed to match block subsystem: {}", e))?;
+    let devices = enumerator
+        .scan_devices()
+        .map_err(|e| format!("Failed to scan block devices: {}", e))?;
+
+    let property = |dev: &udev::Device, key: &str| {
+        dev.property_value(key)
+            .and_then(|v| v.to_str())
+            .map(|s| s.to_string())
+    };
+    Ok(devices
+        .filter(|dev| property(dev, "DEVTYPE").as_deref() == Some("disk"))
+        .filter_map(|dev| {
+            Some(BayProposal {
+                interface_path: property(&dev, "ID_PATH")?,
+                side: String::new(),
+                name: String::new(),
+                devnode: dev.devnode().map(|p| p.to_string_lossy().to_string()),
+                model: property(&dev, "ID_MODEL"),
+                serial: property(&dev, "ID_SERIAL_SHORT"),
+            })
+        })
+        .collect())
+}
+
+/// Navrhne stranu a volný název pro novou šachtu (USB jako vstup, ostatní
+/// podle strany, které je méně).
+fn propose(conn: &Connection, mut disk: BayProposal) -> Result<BayProposal, String> {
+    let count = |side: &str| -> Result<i64, String> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM interface WHERE side = ?1",
+            params![side],
+            |r| r.get(0),
+        )
+        .map_err(db_err)
+    };
+    let (inputs, outputs) = (count("input")?, count("output")?);
+    disk.side = if disk.interface_path.contains("-usb-") || inputs <= outputs {
+        "input".into()
+    } else {
+        "output".into()
+    };
+    let prefix = if disk.side == "input" { "IN" } else { "OUT" };
+    let mut n = if disk.side == "input" {
+        inputs
+    } else {
+        outputs
+    } + 1;
+    loop {
+        let name = format!("{prefix} {n}");
+        let taken: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM interface WHERE name = ?1)",
+                params![name],
+                |r| r.get(0),
+            )
+            .map_err(db_err)?;
+        if !taken {
+            disk.name = name;
+            return Ok(disk);
+        }
+        n += 1;
+    }
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_bays() -> Result<Vec<Bay>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        all_bays(pooled.connection())
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Přidá šachtu. `interface_path` je název z `/dev/disk/by-path`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn add_bay(
+    interface_path: String,
+    side: String,
+    name: String,
+    admin_code: String,
+) -> Result<Bay, ConfigError> {
+    require_admin(&admin_code)?;
+    let path = interface_path.trim().to_string();
+    if path.is_empty() || path.contains('/') {
+        return Err(vec![error(
+            "interface_path",
+            "Musí být název z /dev/disk/by-path",
+        )]
+        .into());
+    }
+    validate_side(&side)?;
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<Bay, ConfigError> {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let tx = pooled.connection().transaction().map_err(db_err)?;
+        let exists: bool = tx
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM interface WHERE interface_path = ?1)",
+                params![path],
+                |r| r.get(0),
+            )
+            .map_err(db_err)?;
+        if exists {
+            return Err(vec![error("interface_path", "Šachta s touto cestou už existuje")].into());
+        }
+        validate_name(&tx, &name, 0)?;
+        tx.execute(
+            "INSERT INTO interface (interface_path, side, name) VALUES (?1, ?2, ?3)",
+            params![path, side, name.trim()],
+        )
+        .map_err(db_err)?;
+        let bay = load_bay(&tx, tx.last_insert_rowid())?;
+        write_audit(
+            &tx,
+            &format!(
+                "Přidána šachta '{}' ({}, {})",
+                bay.name, bay.side, bay.interface_path
+            ),
+        )
+        .map_err(db_err)?;
+        tx.commit().map_err(db_err)?;
+        *LEARN.lock().unwrap() = None;
+        Ok(bay)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))??;
+    monitor::rescan_and_publish().await;
+    Ok(result)
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn rename_bay(id: i64, name: String, admin_code: String) -> Result<Bay, ConfigError> {
+    require_admin(&admin_code)?;
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<Bay, ConfigError> {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let tx = pooled.connection().transaction().map_err(db_err)?;
+        let old = load_bay(&tx, id)?;
+        validate_name(&tx, &name, id)?;
+        tx.execute(
+            "UPDATE interface SET name = ?1 WHERE id = ?2",
+            params![name.trim(), id],
+        )
+        .map_err(db_err)?;
+        write_audit(
+            &tx,
+            &format!(
+                "Šachta {} přejmenována: '{}' -> '{}'",
+                old.interface_path,
+                old.name,
+                name.trim()
+            ),
+        )
+        .map_err(db_err)?;
+        let bay = load_bay(&tx, id)?;
+        tx.commit().map_err(db_err)?;
+        Ok(bay)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))??;
+    monitor::rescan_and_publish().await;
+    Ok(result)
+}
+
+/// Změní stranu šachty (input / output). Nelze během akvizice na šachtě.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_bay_side(id: i64, side: String, admin_code: String) -> Result<Bay, ConfigError> {
+    require_admin(&admin_code)?;
+    validate_side(&side)?;
+    let result = tauri::async_runtime::spawn_blocking(move || -> Result<Bay, ConfigError> {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let tx = pooled.connection().transaction().map_err(db_err)?;
+        let old = load_bay(&tx, id)?;
+        if old.side == side {
+            return Ok(old);
+        }
+        if in_use(&tx, id)? {
+            return Err("Na šachtě právě běží akvizice nebo mazání".to_string().into());
+        }
+        if !keeps_side(&tx, id, &old.side)? {
+            return Err(vec![error(
+                "side",
+                &format!("Musí zůstat alespoň jedna šachta typu {}", old.side),
+            )]
+            .into());
+        }
+        tx.execute(
+            "UPDATE interface SET side = ?1 WHERE id = ?2",
+            params![side, id],
+        )
+        .map_err(db_err)?;
+        write_audit(
+            &tx,
+            &format!(
+                "Šachta '{}' ({}) změněna: {} -> {}",
+                old.name, old.interface_path, old.side, side
+            ),
+        )
+        .map_err(db_err)?;
+        let bay = load_bay(&tx, id)?;
+        tx.commit().map_err(db_err)?;
+        Ok(bay)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))??;
+    monitor::rescan_and_publish().await;
+    Ok(result)
+}
+
+/// Odebere šachtu. Šachtu použitou v historii akvizic nelze odebrat –
+/// smazání by kaskádově smazalo i záznamy `copy_log_*`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn remove_bay(id: i64, admin_code: String) -> Result<(), String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let tx = pooled.connection().transaction().map_err(db_err)?;
+        let bay = load_bay(&tx, id)?;
+        if bay.from_settings {
+            return Err(format!(
+                "Šachta '{}' je definovaná v nastavení jednotky, odeberte ji tam",
+                bay.name
+            ));
+        }
+        if bay.used_by > 0 {
+            return Err(format!(
+                "Šachta '{}' je použitá v {} akvizicích a nelze ji odebrat",
+                bay.name, bay.used_by
+            ));
+        }
+        if !keeps_side(&tx, id, &bay.side)? {
+            return Err(format!(
+                "Musí zůstat alespoň jedna šachta typu {}",
+                bay.side
+            ));
+        }
+        tx.execute("DELETE FROM interface WHERE id = ?1", params![id])
+            .map_err(db_err)?;
+        write_audit(
+            &tx,
+            &format!("Odebrána šachta '{}' ({})", bay.name, bay.interface_path),
+        )
+        .map_err(db_err)?;
+        tx.commit().map_err(db_err)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))??;
+    monitor::rescan_and_publish().await;
+    Ok(())
+}
+
+/// Spustí režim učení: zapamatuje si právě připojené disky a čeká na nový.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn start_bay_learn(admin_code: String) -> Result<LearnStatus, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(|| {
+        let known = connected_disks()?
+            .into_iter()
+            .map(|d| d.interface_path)
+            .collect();
+        *LEARN.lock().unwrap() = Some(LearnSession {
+            started: Instant::now(),
+            known,
+        });
+        Ok(LearnStatus::Waiting {
+            remaining_secs: LEARN_TIMEOUT.as_secs(),
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Stav učení; po připojení nového disku vrátí návrh šachty.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn poll_bay_learn() -> Result<LearnStatus, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let known = {
+            let mut session = LEARN.lock().unwrap();
+            match session.as_ref() {
+                Some(s) if s.started.elapsed() < LEARN_TIMEOUT => s.known.clone(),
+                Some(_) => {
+                    *session = None;
+                    return Ok(LearnStatus::Inactive);
+                }
+                None => return Ok(LearnStatus::Inactive),
+            }
+        };
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let conn = pooled.connection();
+        for disk in connected_disks()? {
+            if known.contains(&disk.interface_path) {
+                continue;
+            }
+            let registered: bool = conn
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM interface WHERE interface_path = ?1)",
+                    params![disk.interface_path],
+                    |r| r.get(0),
+                )
+                .map_err(db_err)?;
+            if !registered {
+                return Ok(LearnStatus::Found {
+                    proposal: propose(conn, disk)?,
+                });
+            }
+        }
+        let remaining = LEARN.lock().unwrap().as_ref().map_or(Duration::ZERO, |s| {
+            LEARN_TIMEOUT.saturating_sub(s.started.elapsed())
+        });
+        Ok(LearnStatus::Waiting {
+            remaining_secs: remaining.as_secs(),
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn stop_bay_learn() -> Result<(), String> {
+    *LEARN.lock().unwrap() = None;
+    Ok(())
+}
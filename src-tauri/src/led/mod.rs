@@ -8,7 +8,8 @@ use once_cell::sync::Lazy;
 
 // Globální instance LED kontroléru dostupná z celé aplikace
 pub static LED_CONTROLLER: Lazy<LedController> = Lazy::new(|| {
-    LedController::new("/dev/ttyS0", 9600)
+    let led = &crate::settings::settings().led;
+    LedController::new(&led.port, led.baud_rate)
 });
 
 pub struct LedController {
@@ -1,5 +1,6 @@
 use once_cell::sync::Lazy;
 use std::sync::RwLock;
+use crate::config::ADMIN_CODE_HASH_PATH;
 use crate::logger::{log_debug, log_warn};
 
 
@@ -36,4 +37,16 @@ pub fn unlock_system(code: &str) -> bool {
             false
         }
     }
-}
\ No newline at end of file
+}
+
+/// Ověří servisní kód pro administrátorské akce proti SHA-256 v `ADMIN_CODE_HASH_PATH`.
+pub fn require_admin(admin_code: &str) -> Result<(), String> {
+    let stored = std::fs::read_to_string(ADMIN_CODE_HASH_PATH)
+        .map_err(|_| "Administrátorské akce nejsou na této jednotce povoleny.".to_string())?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, admin_code.as_bytes());
+    if hex::encode(digest.as_ref()) != stored.trim().to_lowercase() {
+        log_warn("neplatný administrátorský kód");
+        return Err("Neplatný administrátorský kód.".into());
+    }
+    Ok(())
+}
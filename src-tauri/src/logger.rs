@@ -15,6 +15,16 @@ pub async fn log_to_db(level: &str, message: &str) -> Result<(), Box<dyn std::er
     log::debug!("Log successfully written to DB.");
     Ok(())
 }
+/// Zapíše záznam auditní stopy (úroveň AUDIT) přes předané spojení,
+/// typicky ve stejné transakci jako auditovaná změna.
+pub fn write_audit(conn: &rusqlite::Connection, message: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO logs (level, message) VALUES ('AUDIT', ?1)",
+        rusqlite::params![message],
+    )?;
+    Ok(())
+}
+
 /// Loguje chybové zprávy do databáze.
 pub fn log_error(message: &str) {
     let message = message.to_string(); // Zkopírujeme hodnotu do String
@@ -17,10 +17,29 @@ mod lockscreen;
 mod history;
 mod system_info;
 mod integrity_check;
+mod retention;
+mod settings;
+mod interfaces;
+mod wipe;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    settings::init()?;
     db::initialize_db()?;
+    match db::log_writer::recover_spools() {
+        Ok(0) => {}
+        Ok(n) => println!("Recovered {} process log line(s) from spool", n),
+        Err(e) => eprintln!("Process log spool recovery failed: {}", e),
+    }
+    if let Err(e) = retention::apply_retention_policy() {
+        eprintln!("Retention policy failed: {}", e);
+    }
+    if let Err(e) = db::backup::run_integrity_check() {
+        eprintln!("Database integrity check failed: {}", e);
+    }
+    if let Err(e) = report::tex::verify_bundle() {
+        eprintln!("PDF reports unavailable: {}", e);
+    }
  
     Builder::default()
         .plugin(tauri_plugin_websocket::init())
@@ -32,6 +51,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             copy_configs::get_all_active_configs,
             copy_configs::delete_or_deactivate_config,
             copy_configs::save_new_dd_config,
+            copy_configs::presets::export_presets,
+            copy_configs::presets::import_presets,
+            copy_configs::versions::edit_ewf_config,
+            copy_configs::versions::edit_dd_config,
+            copy_configs::versions::get_config_history,
+            copy_configs::versions::diff_config_versions,
             ewfacquire::run_ewfacquire,
             dcfldd::run_dcfldd,
             disk_manager::get_directory_contents,
@@ -39,15 +64,53 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             power_actions::restart_system,
             disk_utils::get_lsblk_json,
             disk_utils::get_disk_info,
+            disk_utils::prepare::prepare_destination,
+            disk_utils::eject::eject_device,
             lockscreen::lock_system,
             lockscreen::unlock_system,
             history::get_history,
+            history::query_history,
+            history::export::export_history,
+            retention::get_retention_policy,
+            retention::run_retention_policy,
+            retention::purge_case,
+            db::backup::backup_database,
+            db::backup::restore_database,
+            db::backup::check_database_integrity,
+            system_info::get_database_info,
+            settings::get_settings,
+            settings::update_settings,
+            interfaces::list_bays,
+            interfaces::add_bay,
+            interfaces::rename_bay,
+            interfaces::set_bay_side,
+            interfaces::remove_bay,
+            interfaces::start_bay_learn,
+            interfaces::poll_bay_learn,
+            interfaces::stop_bay_learn,
+            wipe::probe_wipe_target,
+            wipe::start_wipe,
+            wipe::list_wipe_jobs,
+            report::wipe_certificate::export_wipe_certificate,
             history::get_config_entry,
             history::get_process_log_lines_texts,
             system_info::get_program_versions,
             system_info::get_system_logs,
             system_info::get_short_hw_id,
             integrity_check::run_aide_check_json,
+            report::generate_report,
+            report::export_case_uco,
+            report::archive::list_archived_reports,
+            report::archive::preview_archived_report,
+            report::archive::export_archived_report,
+            report::template::list_report_templates,
+            report::template::validate_report_template,
+            report::template::install_report_template,
+            report::template::remove_report_template,
+            report::label::list_label_sizes,
+            report::label::list_printers,
+            report::label::save_label,
+            report::label::print_label,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,196 @@
+//! Archiv vygenerovaných reportů (`report_archive`).
+//!
+//! Jednotka si ponechává každé vyrenderované PDF i jeho LaTeX zdroj, takže
+//! historie zůstává autoritativním záznamem i po předání cílových disků.
+use super::{
+    copy_to_dirs, get_report_json_data, gs, sha256_hex, validate_output_mount, RenderedReport,
+};
+use crate::db::DB_POOL;
+use crate::logger::log_debug;
+use base64::Engine;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+
+#[derive(Debug, Serialize)]
+pub struct ArchivedReport {
+    pub id: i64,
+    pub process_id: i64,
+    pub created_at: String,
+    pub process_status: String,
+    pub template_name: String,
+    pub template_sha256: String,
+    pub software_version: String,
+    pub pdf_size: i64,
+    pub pdf_sha256: String,
+    pub case_number: Option<String>,
+    pub evidence_number: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchivedReportPreview {
+    pub id: i64,
+    pub pdf_base64: String,
+    pub pdf_sha256: String,
+    /// `false`, pokud uložené PDF neodpovídá zaznamenanému hashi
+    pub integrity_ok: bool,
+}
+
+/// Uloží vyrenderovaný report do archivu. Vrací ID záznamu.
+pub fn archive_report(process_id: i64, rendered: &RenderedReport) -> Result<i64, String> {
+    let pdf_sha256 = sha256_hex(&rendered.pdf);
+
+    let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pool.connection();
+    let status: String = conn
+        .query_row(
+            "SELECT status FROM copy_process WHERE id = ?1",
+            [process_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO report_archive (process_id, process_status, template_name, template_sha256,
+                                     software_version, tex, pdf, pdf_size, pdf_sha256)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            process_id,
+            status,
+            rendered.template_name,
+            rendered.template_sha256,
+            env!("CARGO_PKG_VERSION"),
+            rendered.tex,
+            rendered.pdf,
+            rendered.pdf.len() as i64,
+            pdf_sha256
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    log_debug(&format!(
+        "Report procesu {process_id} archivován jako {id} (sha256 {pdf_sha256})"
+    ));
+    Ok(id)
+}
+
+/// Zda už má proces v archivu nějaký report.
+pub fn has_report(process_id: i64) -> Result<bool, String> {
+    let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    pool.connection()
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM report_archive WHERE process_id = ?1)",
+            [process_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())
+}
+
+/// Načte PDF z archivu a ověří jeho hash. Vrací (process_id, pdf, sha256, integrity_ok).
+fn load_pdf(archive_id: i64) -> Result<(i64, Vec<u8>, String, bool), String> {
+    let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let (process_id, pdf, sha): (i64, Vec<u8>, String) = pool
+        .connection()
+        .query_row(
+            "SELECT process_id, pdf, pdf_sha256 FROM report_archive WHERE id = ?1",
+            [archive_id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Archivovaný report {archive_id} neexistuje"))?;
+    let ok = sha256_hex(&pdf) == sha;
+    Ok((process_id, pdf, sha, ok))
+}
+
+/// Seznam archivovaných reportů (bez obsahu PDF), volitelně jen pro jeden proces.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_archived_reports(process_id: Option<i64>) -> Result<Vec<ArchivedReport>, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<ArchivedReport>, String> {
+        let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let conn = pool.connection();
+        let mut stmt = conn
+            .prepare(
+                "SELECT a.id, a.process_id, a.created_at, a.process_status, a.template_name,
+                        a.template_sha256, a.software_version, a.pdf_size, a.pdf_sha256,
+                        COALESCE(e.case_number, d.case_number),
+                        COALESCE(e.evidence_number, d.evidence_number)
+                 FROM report_archive a
+                 JOIN copy_process p ON p.id = a.process_id
+                 LEFT JOIN copy_log_ewf e ON p.triggered_by_ewf = e.id
+                 LEFT JOIN copy_log_dd d ON p.triggered_by_dd = d.id
+                 WHERE ?1 IS NULL OR a.process_id = ?1
+                 ORDER BY a.id DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([process_id], |r| {
+                Ok(ArchivedReport {
+                    id: r.get(0)?,
+                    process_id: r.get(1)?,
+                    created_at: r.get(2)?,
+                    process_status: r.get(3)?,
+                    template_name: r.get(4)?,
+                    template_sha256: r.get(5)?,
+                    software_version: r.get(6)?,
+                    pdf_size: r.get(7)?,
+                    pdf_sha256: r.get(8)?,
+                    case_number: r.get(9)?,
+                    evidence_number: r.get(10)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Vrátí archivované PDF jako base64 pro náhled v historii.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn preview_archived_report(archive_id: i64) -> Result<ArchivedReportPreview, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let (_, pdf, pdf_sha256, integrity_ok) = load_pdf(archive_id)?;
+        Ok(ArchivedReportPreview {
+            id: archive_id,
+            pdf_base64: base64::engine::general_purpose::STANDARD.encode(pdf),
+            pdf_sha256,
+            integrity_ok,
+        })
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Zapíše archivované PDF do složky case/evidence na připojeném výstupu
+/// pod `mount_root()`. PDF s nesouhlasícím hashem se neexportuje.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_archived_report(
+    archive_id: i64,
+    target: String,
+) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<Vec<String>, String> {
+        let mount = validate_output_mount(&target)?;
+        let (process_id, pdf, _, integrity_ok) = load_pdf(archive_id)?;
+        if !integrity_ok {
+            return Err(format!(
+                "Archivovaný report {archive_id} neodpovídá uloženému SHA-256"
+            ));
+        }
+
+        let data = get_report_json_data(process_id)?;
+        let log = data["log_record"].as_object().cloned().unwrap_or_default();
+        let dir = format!(
+            "{mount}/{}/{}/",
+            gs(&log, "case_number"),
+            gs(&log, "evidence_number")
+        );
+        let file_name = format!("audit-report-{archive_id}.pdf");
+        let tmp_path = format!("/tmp/report_archive_{archive_id}.pdf");
+        fs::write(&tmp_path, &pdf).map_err(|e| e.to_string())?;
+        let written = copy_to_dirs(&[dir], &tmp_path, &file_name);
+        let _ = fs::remove_file(&tmp_path);
+        written.map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -0,0 +1,302 @@
+//! Export akvizic do ontologie CASE/UCO (JSON-LD).
+//!
+//! Jeden `copy_process` se mapuje na `case-investigation:InvestigativeAction`
+//! (kdo, čím, s jakým nastavením, ze kterého zdroje a jaké soubory vznikly).
+//! Bundle pro celý případ obsahuje akce všech procesů s daným číslem případu
+//! a jeden objekt `case-investigation:Investigation`, který je spojuje.
+//!
+//! Identifikátory objektů jsou deterministické (HW ID jednotky + ID procesu),
+//! takže opakovaný export téže akvizice vytvoří stejné uzly grafu.
+use super::report_json::ReportJson;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+
+/// Název souboru, pod kterým se bundle ukládá do složky s důkazem.
+pub const CASE_UCO_FILE: &str = "case-uco.jsonld";
+
+fn context() -> Value {
+    json!({
+        "kb": "urn:cratec:",
+        "case-investigation": "https://ontology.caseontology.org/case/investigation/",
+        "uco-action": "https://ontology.unifiedcyberontology.org/uco/action/",
+        "uco-configuration": "https://ontology.unifiedcyberontology.org/uco/configuration/",
+        "uco-core": "https://ontology.unifiedcyberontology.org/uco/core/",
+        "uco-identity": "https://ontology.unifiedcyberontology.org/uco/identity/",
+        "uco-observable": "https://ontology.unifiedcyberontology.org/uco/observable/",
+        "uco-tool": "https://ontology.unifiedcyberontology.org/uco/tool/",
+        "uco-types": "https://ontology.unifiedcyberontology.org/uco/types/",
+        "uco-vocabulary": "https://ontology.unifiedcyberontology.org/uco/vocabulary/",
+        "xsd": "http://www.w3.org/2001/XMLSchema#"
+    })
+}
+
+/// Převede libovolný text na bezpečnou část IRI.
+fn slug(s: &str) -> String {
+    let out: String = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    if out.is_empty() {
+        "unknown".into()
+    } else {
+        out
+    }
+}
+
+fn id_ref(id: &str) -> Value {
+    json!({ "@id": id })
+}
+
+fn date_time(s: &str) -> Value {
+    json!({ "@type": "xsd:dateTime", "@value": s })
+}
+
+fn hash_value(alg: &str, digest: &str) -> Value {
+    json!({
+        "@type": "uco-types:Hash",
+        "uco-types:hashMethod": {
+            "@type": "uco-vocabulary:HashNameVocab",
+            "@value": alg.to_uppercase()
+        },
+        "uco-types:hashValue": { "@type": "xsd:hexBinary", "@value": digest }
+    })
+}
+
+fn config_value(v: &Value) -> String {
+    match v {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Objekty grafu pro jednu akvizici. Vrací ID investigativní akce.
+fn process_objects(r: &ReportJson, objects: &mut BTreeMap<String, Value>) -> String {
+    let unit = slug(&r.unit.hw_id);
+    let pid = r.process.id;
+    let prefix = format!("kb:{unit}-process-{pid}");
+
+    // vyšetřovatel
+    let examiner_id = format!("kb:identity-{}", slug(&r.case.examiner));
+    objects.insert(
+        examiner_id.clone(),
+        json!({
+            "@id": examiner_id,
+            "@type": "uco-identity:Identity",
+            "uco-core:name": r.case.examiner
+        }),
+    );
+
+    // CRATEC jako nástroj, který akvizici řídí
+    let cratec_id = format!("kb:tool-cratec-{}", slug(&r.unit.build_date));
+    objects.insert(
+        cratec_id.clone(),
+        json!({
+            "@id": cratec_id,
+            "@type": "uco-tool:Tool",
+            "uco-core:name": r.unit.software,
+            "uco-tool:version": r.unit.software_version,
+            "uco-tool:toolType": "acquisition",
+            "uco-core:description": format!(
+                "build {}, md5 {}", r.unit.build_date, r.unit.software_md5
+            )
+        }),
+    );
+
+    // použitý nástroj s konkrétní konfigurací
+    let tool_version = r
+        .unit
+        .tool_versions
+        .get(&r.acquisition.tool)
+        .cloned()
+        .unwrap_or_default();
+    let entries: Vec<Value> = r
+        .acquisition
+        .parameters
+        .iter()
+        .map(|(k, v)| {
+            json!({
+                "@type": "uco-configuration:ConfigurationEntry",
+                "uco-configuration:itemName": k,
+                "uco-configuration:itemValue": config_value(v)
+            })
+        })
+        .chain([
+            json!({
+                "@type": "uco-configuration:ConfigurationEntry",
+                "uco-configuration:itemName": "run_offset",
+                "uco-configuration:itemValue": r.acquisition.offset.to_string()
+            }),
+            json!({
+                "@type": "uco-configuration:ConfigurationEntry",
+                "uco-configuration:itemName": "run_length",
+                "uco-configuration:itemValue": r
+                    .acquisition
+                    .bytes_to_read
+                    .or(r.acquisition.limit_blocks)
+                    .unwrap_or(0)
+                    .to_string()
+            }),
+        ])
+        .collect();
+    let tool_id = format!("{prefix}-tool");
+    objects.insert(
+        tool_id.clone(),
+        json!({
+            "@id": tool_id,
+            "@type": "uco-tool:ConfiguredTool",
+            "uco-core:name": r.acquisition.tool,
+            "uco-tool:version": tool_version,
+            "uco-tool:toolType": "acquisition",
+            "uco-tool:creator": id_ref(&cratec_id),
+            "uco-configuration:usesConfiguration": {
+                "@id": format!("{prefix}-configuration"),
+                "@type": "uco-configuration:Configuration",
+                "uco-core:name": r.acquisition.config_name,
+                "uco-configuration:configurationEntry": entries
+            }
+        }),
+    );
+
+    // zdrojové médium – hashe popisují přečtená data zdroje, ne jednotlivé segmenty
+    let hashes: Vec<Value> = r
+        .hashes
+        .iter()
+        .map(|(alg, digest)| hash_value(alg, digest))
+        .collect();
+    let mut action_objects = Vec::new();
+    if let Some(src) = &r.source {
+        let source_id = format!("kb:device-{}", slug(&src.serial));
+        objects.insert(
+            source_id.clone(),
+            json!({
+                "@id": source_id,
+                "@type": "uco-observable:ObservableObject",
+                "uco-core:hasFacet": [
+                    {
+                        "@type": "uco-observable:DeviceFacet",
+                        "uco-observable:serialNumber": src.serial,
+                        "uco-observable:model": src.model
+                    },
+                    {
+                        "@type": "uco-observable:ContentDataFacet",
+                        "uco-observable:sizeInBytes": src.capacity_bytes,
+                        "uco-observable:hash": hashes
+                    }
+                ]
+            }),
+        );
+        action_objects.push(id_ref(&source_id));
+    } else if !hashes.is_empty() {
+        // disk už není připojený – hashe přečtených dat zachováme i bez DeviceFacet
+        let source_id = format!("{prefix}-source");
+        objects.insert(
+            source_id.clone(),
+            json!({
+                "@id": source_id,
+                "@type": "uco-observable:ObservableObject",
+                "uco-core:hasFacet": [{
+                    "@type": "uco-observable:ContentDataFacet",
+                    "uco-observable:hash": hashes
+                }]
+            }),
+        );
+        action_objects.push(id_ref(&source_id));
+    }
+
+    // výsledné soubory obrazu
+    let mut results = Vec::new();
+    for (di, dest) in r.destinations.iter().enumerate() {
+        for (fi, file) in dest.files.iter().enumerate() {
+            let file_id = format!("{prefix}-dest{di}-file{fi}");
+            let facets = vec![json!({
+                "@type": "uco-observable:FileFacet",
+                "uco-observable:fileName": file.name,
+                "uco-observable:filePath": format!("{}/{}", dest.evidence_dir, file.name),
+                "uco-observable:sizeInBytes": file.size
+            })];
+            objects.insert(
+                file_id.clone(),
+                json!({
+                    "@id": file_id,
+                    "@type": "uco-observable:ObservableObject",
+                    "uco-core:description": format!("{} ({})", dest.role, dest.serial),
+                    "uco-core:hasFacet": facets
+                }),
+            );
+            results.push(id_ref(&file_id));
+        }
+    }
+
+    let provenance_id = format!("{prefix}-provenance");
+    objects.insert(
+        provenance_id.clone(),
+        json!({
+            "@id": provenance_id,
+            "@type": "case-investigation:ProvenanceRecord",
+            "case-investigation:exhibitNumber": r.case.evidence_number,
+            "uco-core:object": results
+        }),
+    );
+
+    let mut action = Map::new();
+    let action_id = format!("{prefix}-action");
+    action.insert("@id".into(), json!(action_id));
+    action.insert(
+        "@type".into(),
+        json!("case-investigation:InvestigativeAction"),
+    );
+    action.insert(
+        "uco-core:name".into(),
+        json!(format!("acquisition {}", r.case.evidence_number)),
+    );
+    action.insert("uco-core:description".into(), json!(r.case.description));
+    action.insert("uco-action:actionStatus".into(), json!(r.process.status));
+    if let Some(start) = &r.process.start_datetime {
+        action.insert("uco-action:startTime".into(), date_time(start));
+    }
+    if let Some(end) = &r.process.end_datetime {
+        action.insert("uco-action:endTime".into(), date_time(end));
+    }
+    action.insert("uco-action:performer".into(), id_ref(&examiner_id));
+    action.insert("uco-action:instrument".into(), id_ref(&tool_id));
+    action.insert("uco-action:object".into(), Value::Array(action_objects));
+    action.insert("uco-action:result".into(), json!([id_ref(&provenance_id)]));
+    objects.insert(action_id.clone(), Value::Object(action));
+
+    action_id
+}
+
+/// Sestaví CASE/UCO bundle pro jeden případ z reportů jeho akvizic.
+pub fn build_case_bundle(case_number: &str, reports: &[ReportJson]) -> Value {
+    let mut objects = BTreeMap::new();
+    let mut actions = Vec::new();
+    let mut notes = Vec::new();
+    for r in reports {
+        actions.push(id_ref(&process_objects(r, &mut objects)));
+        if !r.case.notes.is_empty() {
+            notes.push(r.case.notes.clone());
+        }
+    }
+
+    let investigation_id = format!("kb:investigation-{}", slug(case_number));
+    objects.insert(
+        investigation_id.clone(),
+        json!({
+            "@id": investigation_id,
+            "@type": "case-investigation:Investigation",
+            "uco-core:name": case_number,
+            "case-investigation:focus": "forensic acquisition",
+            "uco-core:description": notes.join("\n"),
+            "uco-core:object": actions
+        }),
+    );
+
+    json!({
+        "@context": context(),
+        "@id": format!("kb:bundle-{}", uuid::Uuid::new_v4()),
+        "@type": "uco-core:Bundle",
+        "uco-core:name": format!("CRATEC export {case_number}"),
+        "uco-core:object": objects.into_values().collect::<Vec<_>>()
+    })
+}
@@ -0,0 +1,154 @@
+//! DFXML popis akvizice (`report.dfxml`), ukládaný vedle `audit-report.pdf`.
+//!
+//! Vychází ze stejných dat jako `report.json`, takže obě exportní varianty
+//! vždy popisují tutéž akvizici.
+use super::report_json::ReportJson;
+use std::fmt::Write;
+
+/// Název souboru, pod kterým se DFXML ukládá do složky s důkazem.
+pub const DFXML_FILE: &str = "report.dfxml";
+
+const DFXML_VERSION: &str = "1.2.0";
+
+/// Escapování textu pro XML elementy i atributy.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c if (c as u32) < 0x20 && c != '\n' && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn element(out: &mut String, indent: usize, name: &str, value: &str) {
+    let _ = writeln!(
+        out,
+        "{:indent$}<{name}>{}</{name}>",
+        "",
+        xml_escape(value),
+        indent = indent
+    );
+}
+
+/// Vyrenderuje DFXML dokument z dat `report.json`.
+pub fn render_dfxml(report: &ReportJson) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        out,
+        "<dfxml xmlns=\"http://www.forensicswiki.org/wiki/Category:Digital_Forensics_XML\" \
+         xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+         xmlns:cratec=\"https://github.com/cyber-rangers/CRATEC\" version=\"{DFXML_VERSION}\">"
+    );
+
+    // metadata
+    out.push_str("  <metadata>\n");
+    element(&mut out, 4, "dc:type", "Disk Image");
+    element(&mut out, 4, "cratec:case_number", &report.case.case_number);
+    element(
+        &mut out,
+        4,
+        "cratec:evidence_number",
+        &report.case.evidence_number,
+    );
+    element(&mut out, 4, "cratec:examiner", &report.case.examiner);
+    element(&mut out, 4, "cratec:description", &report.case.description);
+    element(&mut out, 4, "cratec:notes", &report.case.notes);
+    out.push_str("  </metadata>\n");
+
+    // creator – CRATEC + použitý nástroj
+    out.push_str("  <creator version=\"1.0\">\n");
+    element(&mut out, 4, "program", report.unit.software);
+    element(&mut out, 4, "version", report.unit.software_version);
+    out.push_str("    <build_environment>\n");
+    element(&mut out, 6, "compilation_date", &report.unit.build_date);
+    for (tool, version) in &report.unit.tool_versions {
+        let _ = writeln!(
+            out,
+            "      <library name=\"{}\" version=\"{}\"/>",
+            xml_escape(tool),
+            xml_escape(version)
+        );
+    }
+    out.push_str("    </build_environment>\n");
+    out.push_str("    <execution_environment>\n");
+    element(&mut out, 6, "host", &report.unit.hw_id);
+    element(
+        &mut out,
+        6,
+        "cratec:software_md5",
+        &report.unit.software_md5,
+    );
+    element(&mut out, 6, "cratec:tool", &report.acquisition.tool);
+    element(
+        &mut out,
+        6,
+        "cratec:config_name",
+        &report.acquisition.config_name,
+    );
+    if let Some(start) = &report.process.start_datetime {
+        element(&mut out, 6, "start_time", start);
+    }
+    if let Some(end) = &report.process.end_datetime {
+        element(&mut out, 6, "end_time", end);
+    }
+    element(&mut out, 6, "cratec:status", &report.process.status);
+    out.push_str("    </execution_environment>\n");
+    out.push_str("  </creator>\n");
+
+    // source – zdrojové médium a hashe obrazu
+    out.push_str("  <source>\n");
+    if let Some(src) = &report.source {
+        element(&mut out, 4, "device_model", &src.model);
+        element(&mut out, 4, "device_sn", &src.serial);
+        element(
+            &mut out,
+            4,
+            "sectorsize",
+            &src.logical_sector_size.to_string(),
+        );
+        element(&mut out, 4, "devicesize", &src.capacity_bytes.to_string());
+    }
+    element(
+        &mut out,
+        4,
+        "cratec:offset",
+        &report.acquisition.offset.to_string(),
+    );
+    for (alg, digest) in &report.hashes {
+        let _ = writeln!(
+            out,
+            "    <hashdigest type=\"{}\">{}</hashdigest>",
+            xml_escape(alg),
+            xml_escape(digest)
+        );
+    }
+    out.push_str("  </source>\n");
+
+    // segmenty obrazu na jednotlivých cílových discích
+    for dest in &report.destinations {
+        for file in &dest.files {
+            out.push_str("  <fileobject>\n");
+            element(
+                &mut out,
+                4,
+                "filename",
+                &format!("{}/{}", dest.evidence_dir, file.name),
+            );
+            element(&mut out, 4, "filesize", &file.size.to_string());
+            element(&mut out, 4, "cratec:role", &dest.role);
+            element(&mut out, 4, "cratec:device_sn", &dest.serial);
+            out.push_str("  </fileobject>\n");
+        }
+    }
+
+    out.push_str("</dfxml>\n");
+    out
+}
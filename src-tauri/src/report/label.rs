@@ -0,0 +1,234 @@
+//! Štítek důkazu (cílový disk, obálka) ve formátu pro štítkové tiskárny.
+//!
+//! Štítek obsahuje číslo případu a důkazu, sériové číslo zdroje, datum
+//! akvizice, vyšetřovatele, zkrácený hash a QR kód ve stejném formátu
+//! jako audit report (viz [`super::qr`]).
+use super::report_json::{build_report_json, ReportJson};
+use super::{
+    copy_to_dirs, gs, latex_escape, load_report_data, qr, snapshot, tex, validate_output_mount,
+};
+use crate::logger::log_debug;
+use serde::Serialize;
+use std::fs;
+use std::process::Command;
+use tera::{Context, Tera};
+
+static TEMPLATE_LABEL: &str = include_str!("./templates/label.tex");
+
+/// Podporované rozměry štítků (šířka × výška v mm).
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LabelSize {
+    pub id: &'static str,
+    pub description: &'static str,
+    pub width_mm: u32,
+    pub height_mm: u32,
+}
+
+pub const LABEL_SIZES: &[LabelSize] = &[
+    LabelSize {
+        id: "62x29",
+        description: "Brother DK-11209 (62 × 29 mm)",
+        width_mm: 62,
+        height_mm: 29,
+    },
+    LabelSize {
+        id: "62x100",
+        description: "Brother DK-11202 (62 × 100 mm)",
+        width_mm: 62,
+        height_mm: 100,
+    },
+    LabelSize {
+        id: "89x36",
+        description: "DYMO 99012 (89 × 36 mm)",
+        width_mm: 89,
+        height_mm: 36,
+    },
+    LabelSize {
+        id: "102x51",
+        description: "Zebra 4 × 2 in (102 × 51 mm)",
+        width_mm: 102,
+        height_mm: 51,
+    },
+];
+
+fn label_size(id: &str) -> Result<LabelSize, String> {
+    LABEL_SIZES
+        .iter()
+        .find(|s| s.id == id)
+        .copied()
+        .ok_or_else(|| format!("Unknown label size: {}", id))
+}
+
+/// Nejsilnější spočítaný hash zkrácený na začátek a konec (`abcd1234...89abcdef`).
+fn short_hash(data: &ReportJson) -> (String, String) {
+    for alg in ["sha512", "sha384", "sha256", "sha1", "md5"] {
+        if let Some(h) = data.hashes.get(alg) {
+            let short = if h.len() > 16 {
+                format!("{}...{}", &h[..8], &h[h.len() - 8..])
+            } else {
+                h.clone()
+            };
+            return (alg.to_uppercase(), short);
+        }
+    }
+    ("Hash".into(), "N/A".into())
+}
+
+/// Data štítku s údaji uloženými při akvizici.
+pub struct LabelData {
+    pub report: ReportJson,
+    /// `copy_process.source_serial`, jinak sériové číslo ze snímku disků
+    pub source_serial: String,
+    /// Soubor obrazu pro QR kód (ze snímku akvizice)
+    pub image_file: String,
+}
+
+fn load_label_data(process_id: i64) -> Result<LabelData, String> {
+    let report = load_report_data(process_id)?;
+    let stored = report["copy_process"]
+        .as_object()
+        .map(|p| gs(p, "source_serial"))
+        .unwrap_or("");
+    let recorded = report["source_disk"]
+        .as_object()
+        .map(|d| gs(d, "serial"))
+        .unwrap_or("");
+    let source_serial = [stored, recorded]
+        .into_iter()
+        .find(|s| !s.is_empty())
+        .unwrap_or("N/A")
+        .to_string();
+    Ok(LabelData {
+        report: build_report_json(&report),
+        source_serial,
+        image_file: snapshot::first_image_file(&report),
+    })
+}
+
+/// Vyrenderuje štítek procesu jako PDF.
+pub fn render_label(
+    process_id: i64,
+    label: &LabelData,
+    size: LabelSize,
+) -> Result<Vec<u8>, String> {
+    let data = &label.report;
+    let mut ctx = Context::new();
+    ctx.insert("width_mm", &size.width_mm);
+    ctx.insert("height_mm", &size.height_mm);
+    // QR zabírá skoro celou výšku štítku, písmo se přizpůsobí výšce
+    let qr_mm = size.height_mm.min(size.width_mm / 2).saturating_sub(3);
+    let font_pt = (size.height_mm as f64 / 6.0).clamp(4.5, 9.0);
+    ctx.insert("qr_mm", &qr_mm);
+    ctx.insert("font_pt", &format!("{font_pt:.1}"));
+    ctx.insert("line_pt", &format!("{:.1}", font_pt * 1.2));
+    ctx.insert("case_number", &latex_escape(&data.case.case_number));
+    ctx.insert("evidence_number", &latex_escape(&data.case.evidence_number));
+    ctx.insert("source_serial", &latex_escape(&label.source_serial));
+    ctx.insert(
+        "acquisition_date",
+        data.process
+            .start_datetime
+            .as_deref()
+            .and_then(|d| d.get(..10))
+            .unwrap_or("N/A"),
+    );
+    ctx.insert("examiner", &latex_escape(&data.case.examiner));
+    let (hash_name, hash) = short_hash(data);
+    ctx.insert("hash_name", &hash_name);
+    ctx.insert("short_hash", &hash);
+    ctx.insert("qr_code", qr::QR_IMAGE_FILE);
+
+    tex::with_work_dir(&format!("label_{process_id}"), |work_dir| {
+        let payload = qr::qr_payload(data, &label.image_file);
+        fs::write(work_dir.join(qr::QR_IMAGE_FILE), qr::qr_png(&payload)?)
+            .map_err(|e| e.to_string())?;
+        let latex = Tera::one_off(TEMPLATE_LABEL, &ctx, false)
+            .map_err(|e| format!("Render štítku selhal: {e}"))?;
+        tex::latex_to_pdf(&latex, Some(work_dir))
+    })
+}
+
+/// Rozměry štítků, které umí `save_label` / `print_label`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_label_sizes() -> Result<Vec<LabelSize>, String> {
+    Ok(LABEL_SIZES.to_vec())
+}
+
+/// Vrátí názvy tiskových front CUPS (`lpstat -e`).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_printers() -> Result<Vec<String>, String> {
+    let output = Command::new("lpstat")
+        .arg("-e")
+        .output()
+        .map_err(|e| format!("Failed to run lpstat: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect())
+}
+
+/// Uloží štítek jako `label-<size>.pdf` do složky case/evidence na připojeném výstupu.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn save_label(process_id: i64, size: String, target: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+        let mount = validate_output_mount(&target)?;
+        let label = load_label_data(process_id)?;
+        let pdf = render_label(process_id, &label, label_size(&size)?)?;
+        let data = &label.report;
+        let dir = format!(
+            "{mount}/{}/{}/",
+            data.case.case_number, data.case.evidence_number
+        );
+        let file_name = format!("label-{size}.pdf");
+        let tmp_path = format!("/tmp/label_{process_id}.pdf");
+        fs::write(&tmp_path, pdf).map_err(|e| e.to_string())?;
+        let written = copy_to_dirs(&[dir], &tmp_path, &file_name);
+        let _ = fs::remove_file(&tmp_path);
+        written
+            .map_err(|e| e.to_string())?
+            .pop()
+            .ok_or_else(|| "Štítek nebyl zapsán".to_string())
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Vytiskne štítek na tiskové frontě CUPS `printer` (`lp`).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn print_label(process_id: i64, size: String, printer: String) -> Result<String, String> {
+    if !list_printers().await?.contains(&printer) {
+        return Err(format!("Unknown printer: {}", printer));
+    }
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+        let label = label_size(&size)?;
+        let pdf = render_label(process_id, &load_label_data(process_id)?, label)?;
+        let tmp_path = format!("/tmp/label_{process_id}.pdf");
+        fs::write(&tmp_path, pdf).map_err(|e| e.to_string())?;
+        let output = Command::new("lp")
+            .arg("-d")
+            .arg(&printer)
+            .arg("-o")
+            .arg(format!(
+                "media=Custom.{}x{}mm",
+                label.width_mm, label.height_mm
+            ))
+            .arg(&tmp_path)
+            .output();
+        let _ = fs::remove_file(&tmp_path);
+        let output = output.map_err(|e| format!("Failed to run lp: {e}"))?;
+        if !output.status.success() {
+            return Err(format!(
+                "lp failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let job = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        log_debug(&format!(
+            "Štítek procesu {process_id} odeslán na {printer}: {job}"
+        ));
+        Ok(job)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
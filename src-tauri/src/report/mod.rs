@@ -1,15 +1,29 @@
-use crate::logger::{log_debug, log_error};
+use crate::config::{mount_root, report_template_dir, REPORT_ERROR_LOG_MAX_BYTES};
+use crate::copy_configs::policy::apply_effective_config;
+use crate::db::log_writer::line_text;
+use crate::disk_utils::writers::write_guard;
+use crate::logger::{log_debug, log_error, log_warn};
 use crate::system_info::get_report_system_info;
-use crate::{db::DB_POOL, disk_utils};
+use crate::{db::DB_POOL, disk_utils, websocket};
 use chrono::{Local, NaiveDateTime, TimeZone, Utc};
 use once_cell::sync::Lazy;
 use rusqlite::Row;
+use serde::Serialize;
 use serde_json::{Map, Value};
 use std::fs;
+use template::TemplateKind;
 use tera::{Context, Tera};
 
-static TEMPLATE_EN_EWF: &str = include_str!("./templates/en_ewf.tex");
-static TEMPLATE_EN_DD: &str = include_str!("./templates/en_dd.tex");
+pub mod archive;
+pub mod case_uco;
+pub mod dfxml;
+pub mod label;
+pub mod qr;
+pub mod report_json;
+pub mod snapshot;
+pub mod template;
+pub mod tex;
+pub mod wipe_certificate;
 
 /// ----------------- malé pomůcky ------------------------------------------
 fn vstr<S: Into<String>>(s: S) -> Value {
@@ -33,6 +47,11 @@ impl StrExt for str {
     }
 }
 
+/// SHA-256 jako hex řetězec.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, data))
+}
+
 /// Vypůjčené &str přímo z JSONu
 fn gs<'a>(o: &'a Map<String, Value>, k: &str) -> &'a str {
     o.get(k).and_then(Value::as_str).unwrap_or("")
@@ -71,48 +90,581 @@ fn get_interface_path(conn: &rusqlite::Connection, id: i64) -> Option<String> {
     .ok()
 }
 
-/// --------------------------------------------------------------------------
-pub fn generate_report_ewfacquire(id: i64) -> Result<(), String> {
-    println!("▶️  generate_report({id}) – START");
-    println!("Current dir: {:?}", std::env::current_dir());
-    println!("USER: {:?}", std::env::var("USER"));
-    println!("HOME: {:?}", std::env::var("HOME"));
-    println!("PATH: {:?}", std::env::var("PATH"));
+/// Načte informace o disku podle ID v logu (`source_disk_id`, `dest_disk_id` …).
+fn load_disk(log_map: &Map<String, Value>, key: &str) -> Result<Value, String> {
+    let disk_id = log_map.get(key).and_then(Value::as_i64).unwrap_or(0);
+    if disk_id == 0 {
+        return Ok(Value::Null);
+    }
+    let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let path = get_interface_path(pool.connection(), disk_id)
+        .map(|p| format!("/dev/disk/by-path/{p}"))
+        .ok_or_else(|| format!("interface_path chybí pro disk {disk_id}"))?;
+    println!("🔍  {key}: disk_utils::get_disk_info({path})");
+    disk_utils::get_disk_info(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|d| serde_json::to_value(d).map_err(|e| e.to_string()))
+}
 
-    // 1️⃣  Načti agregovaný JSON z DB
+/// Načte data reportu včetně disků pro historický proces.
+///
+/// Disky se berou ze snímku pořízeného při spuštění akvizice (viz
+/// [`snapshot`]). Jen u běžícího procesu bez snímku se čtou disky v pozicích;
+/// dokončený proces bez snímku (starší záznam) má u zdroje jen uložené
+/// sériové číslo a cílové disky prázdné.
+pub fn load_report_data(id: i64) -> Result<Map<String, Value>, String> {
     let mut report = get_report_json_data(id)?
         .as_object()
         .cloned()
         .ok_or("Report JSON není objekt")?;
-    let log_map = report["log_record"]
+    let proc = report["copy_process"]
         .as_object()
         .cloned()
         .unwrap_or_default();
-    println!(
-        "✅  data z DB – klíče: {:?}",
-        report.keys().collect::<Vec<_>>()
-    );
 
-    // --- helper: načti disk podle ID v logu
-    let load_disk = |key: &str| -> Result<Value, String> {
-        let disk_id = log_map.get(key).and_then(Value::as_i64).unwrap_or(0);
-        if disk_id == 0 {
-            return Ok(Value::Null);
+    if let Some(mut snap) = snapshot::parse(&proc) {
+        for key in snapshot::DISK_KEYS {
+            report.insert(key.into(), snap.remove(key).unwrap_or(Value::Null));
+        }
+        if let Some(files) = snap.remove("image_files") {
+            report.insert("image_files".into(), files);
+        }
+    } else if gs(&proc, "status") == "running" {
+        let log_map = report["log_record"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        for (target, key) in [
+            ("source_disk", "source_disk_id"),
+            ("dest_disk", "dest_disk_id"),
+            ("second_dest_disk", "second_dest_disk_id"),
+        ] {
+            let disk = load_disk(&log_map, key).unwrap_or_else(|e| {
+                log_warn(&format!("Disk {key} pro proces {id} není dostupný: {e}"));
+                Value::Null
+            });
+            report.insert(target.into(), disk);
+        }
+    } else {
+        log_warn(&format!(
+            "Proces {id} nemá snímek disků akvizice, report obsahuje jen uložené sériové číslo zdroje"
+        ));
+        let source = match gs(&proc, "source_serial") {
+            "" => Value::Null,
+            serial => serde_json::json!({ "serial": serial }),
+        };
+        report.insert("source_disk".into(), source);
+        report.insert("dest_disk".into(), Value::Null);
+        report.insert("second_dest_disk".into(), Value::Null);
+    }
+    if let Some(proc) = report["copy_process"].as_object_mut() {
+        // snímek je v datech reportu rozložený výše
+        proc.remove("acquisition_snapshot");
+    }
+    Ok(report)
+}
+
+/// Najde první neprázdný mountpoint mezi oddíly disku (výstup `get_disk_info`).
+fn disk_mountpoint(disk: &Map<String, Value>) -> Option<&str> {
+    disk.get("partitions")
+        .and_then(Value::as_array)
+        .and_then(|parts| {
+            parts.iter().find_map(|p| {
+                p.get("mountpoint")
+                    .and_then(Value::as_str)
+                    .filter(|s| !s.is_empty())
+            })
+        })
+}
+
+/// Výsledek renderu PDF reportu.
+pub struct RenderedReport {
+    pub pdf: Vec<u8>,
+    /// Vyrenderovaný LaTeX zdroj
+    pub tex: String,
+    pub template_name: String,
+    pub template_sha256: String,
+}
+
+/// Ověří, že `target` je existující složka pod `mount_root()`, a vrátí kanonickou cestu.
+pub(crate) fn validate_output_mount(target: &str) -> Result<String, String> {
+    let mp = fs::canonicalize(target).map_err(|e| format!("Výstup {target} není dostupný: {e}"))?;
+    if !mp.starts_with(mount_root()) || !mp.is_dir() {
+        return Err(format!(
+            "Access denied: path {target} is outside of MOUNT_ROOT"
+        ));
+    }
+    Ok(mp.to_string_lossy().to_string())
+}
+
+/// Složky case/evidence, do kterých se report zapíše.
+///
+/// Bez `target` jsou to cílové disky akvizice (`dest_disk`, případně
+/// `second_dest_disk`) – jen pokud je v jejich pozicích pořád tentýž disk
+/// (sériové číslo podle snímku akvizice). Jinak zadaný připojený výstup pod
+/// `mount_root()` (např. USB flashka).
+fn output_dirs(report: &Map<String, Value>, target: Option<&str>) -> Result<Vec<String>, String> {
+    let log_map = report["log_record"].as_object().unwrap_or(&EMPTY_MAP);
+    let case_dir = |mp: &str| {
+        format!(
+            "{mp}/{}/{}/",
+            gs(log_map, "case_number"),
+            gs(log_map, "evidence_number")
+        )
+    };
+
+    if let Some(target) = target {
+        return Ok(vec![case_dir(&validate_output_mount(target)?)]);
+    }
+
+    let mut dirs = Vec::new();
+    for (key, id_key) in [
+        ("dest_disk", "dest_disk_id"),
+        ("second_dest_disk", "second_dest_disk_id"),
+    ] {
+        log_debug(&format!("Zpracovávám klíč: {key}"));
+        let recorded = report
+            .get(key)
+            .and_then(Value::as_object)
+            .unwrap_or(&EMPTY_MAP);
+        if recorded.is_empty() {
+            // druhý cílový disk je volitelný, chybějící disky hlásíme níže
+            continue;
+        }
+        let current = load_disk(log_map, id_key)?;
+        let current = current.as_object().unwrap_or(&EMPTY_MAP);
+        if gs(current, "serial") != gs(recorded, "serial") {
+            return Err(format!(
+                "V pozici cílového disku je jiný disk ({}) než při akvizici ({})",
+                gs(current, "serial"),
+                gs(recorded, "serial")
+            ));
+        }
+        let Some(mp) = disk_mountpoint(current) else {
+            log_error("Cílová cesta nebyla nalezena (žádný mountpoint).");
+            return Err("Cílová cesta nebyla nalezena.".into());
+        };
+        dirs.push(case_dir(mp));
+    }
+    if dirs.is_empty() {
+        return Err("Cílové disky akvizice nejsou připojené".into());
+    }
+    Ok(dirs)
+}
+
+/// Název souboru reportu. Znovu vygenerovaný report dostane příponu s ID
+/// archivu (`audit-report.pdf` → `audit-report-regenerated-7.pdf`), takže
+/// nikdy nepřepíše původní report ani předchozí regeneraci.
+fn report_file_name(file_name: &str, regenerated: Option<i64>) -> String {
+    match (regenerated, file_name.split_once('.')) {
+        (Some(archive_id), Some((stem, ext))) => format!("{stem}-regenerated-{archive_id}.{ext}"),
+        _ => file_name.to_string(),
+    }
+}
+
+/// Odmítne zápis, pokud některý ze souborů reportu ve složkách už existuje.
+fn ensure_not_written(dirs: &[String], file_names: &[String]) -> Result<(), String> {
+    for dir in dirs {
+        for name in file_names {
+            let path = format!("{dir}{name}");
+            if std::path::Path::new(&path).exists() {
+                return Err(format!("{path} už existuje, report se nepřepíše"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Zkopíruje soubor `src_path` pod rootem do všech složek `dirs`.
+/// Vrací cesty zapsaných souborů.
+fn copy_to_dirs(dirs: &[String], src_path: &str, file_name: &str) -> std::io::Result<Vec<String>> {
+    // po dobu zápisu nejde disk bezpečně odebrat
+    let _writing = dirs
+        .iter()
+        .map(|dir| write_guard(dir))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(std::io::Error::other)?;
+    let mut written = Vec::new();
+    for target_dir in dirs {
+        let target_path = format!("{target_dir}{file_name}");
+        log_debug(&format!("Cílová cesta sestavena: {target_path}"));
+
+        // Vytvoř adresář, pokud ještě neexistuje
+        std::process::Command::new("sudo")
+            .arg("mkdir")
+            .arg("-p")
+            .arg(target_dir)
+            .status()?;
+
+        // Kopíruj soubor pod rootem
+        let status = std::process::Command::new("sudo")
+            .arg("cp")
+            .arg(src_path)
+            .arg(&target_path)
+            .status()?;
+        if !status.success() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                format!("sudo cp failed with status {:?}", status.code()),
+            ));
+        }
+        log_debug(&format!("Soubor úspěšně zkopírován na {target_path}"));
+        written.push(target_path);
+    }
+    Ok(written)
+}
+
+/// Zapíše `report.json`, `report.dfxml` a CASE/UCO bundle do složek `dirs`.
+fn write_machine_reports(
+    id: i64,
+    report: &Map<String, Value>,
+    rendered: &RenderedReport,
+    dirs: &[String],
+    regenerated: Option<i64>,
+) -> Result<Vec<String>, String> {
+    let mut data = report_json::build_report_json(report);
+    data.template = Some(report_json::TemplateInfo {
+        name: rendered.template_name.clone(),
+        sha256: rendered.template_sha256.clone(),
+    });
+    let json = serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?;
+    let dfxml = dfxml::render_dfxml(&data);
+    let case_number = data.case.case_number.clone();
+    let case_uco = case_uco::build_case_bundle(&case_number, std::slice::from_ref(&data));
+    let case_uco = serde_json::to_string_pretty(&case_uco).map_err(|e| e.to_string())?;
+
+    let mut written = Vec::new();
+    for (content, file_name) in [
+        (json, report_json::REPORT_JSON_FILE),
+        (dfxml, dfxml::DFXML_FILE),
+        (case_uco, case_uco::CASE_UCO_FILE),
+    ] {
+        let file_name = report_file_name(file_name, regenerated);
+        let tmp_path = format!("/tmp/report_{id}_{file_name}");
+        fs::write(&tmp_path, content).map_err(|e| {
+            log_error(&format!("Chyba při zápisu {file_name}: {e}"));
+            e.to_string()
+        })?;
+        let saved = copy_to_dirs(dirs, &tmp_path, &file_name);
+        let _ = fs::remove_file(&tmp_path);
+        written.extend(saved.map_err(|e| {
+            log_error(&format!("Chyba při ukládání {file_name} na výstup: {e}"));
+            e.to_string()
+        })?);
+    }
+    Ok(written)
+}
+
+/// Vygeneruje audit report (PDF + strojové exporty) pro libovolný proces
+/// z historie, bez ohledu na jeho stav. Vrací cesty zapsaných souborů.
+///
+/// Bez `target` jde o první report po akvizici: zapisuje se na cílové disky
+/// akvizice a jen tehdy, když proces ještě žádný report nemá. Znovu
+/// vygenerovaný report se zapisuje jen na zadaný výstup, pod názvy
+/// s ID archivu (viz [`report_file_name`]), a bez úspěšné archivace se
+/// nezapíše vůbec. Existující soubory se nikdy nepřepisují.
+///
+/// Každý vyrenderovaný report se nejdřív uloží do `report_archive`, takže
+/// nezmizí ani při selhání zápisu na výstup.
+pub fn generate_report_files(id: i64, target: Option<&str>) -> Result<Vec<String>, String> {
+    log_debug(&format!("generate_report({id}, {target:?}) – START"));
+    if target.is_none() && archive::has_report(id)? {
+        return Err(format!(
+            "Proces {id} už report má, znovu vygenerovaný report je nutné zapsat na zvolený výstup"
+        ));
+    }
+    let report = load_report_data(id)?;
+
+    let rendered = render_pdf(id, &report)?;
+
+    log_debug(&format!("Archivuji report procesu {id}..."));
+    let archived = archive::archive_report(id, &rendered);
+    if let Err(e) = &archived {
+        log_error(&format!("Chyba při archivaci reportu: {e}"));
+    }
+
+    let pdf_path = format!("/tmp/report_{id}.pdf");
+    let regenerated = match (target, &archived) {
+        (None, _) => Ok(None),
+        (Some(_), Ok(archive_id)) => Ok(Some(*archive_id)),
+        (Some(_), Err(e)) => Err(format!(
+            "Znovu vygenerovaný report se nepodařilo archivovat, na výstup se nezapíše: {e}"
+        )),
+    };
+    let result = regenerated.and_then(|regenerated| {
+        let dirs = output_dirs(&report, target)?;
+        let pdf_name = report_file_name("audit-report.pdf", regenerated);
+        let file_names = [
+            pdf_name.clone(),
+            report_file_name(report_json::REPORT_JSON_FILE, regenerated),
+            report_file_name(dfxml::DFXML_FILE, regenerated),
+            report_file_name(case_uco::CASE_UCO_FILE, regenerated),
+        ];
+        ensure_not_written(&dirs, &file_names)?;
+
+        fs::write(&pdf_path, &rendered.pdf).map_err(|e| e.to_string())?;
+        log_debug("Ukládám PDF na výstup...");
+        let saved = copy_to_dirs(&dirs, &pdf_path, &pdf_name);
+        let _ = fs::remove_file(&pdf_path);
+        let mut written = saved.map_err(|e| {
+            log_error(&format!("Chyba při ukládání PDF na výstup: {e}"));
+            e.to_string()
+        })?;
+        log_debug("Ukládám report.json, report.dfxml a CASE/UCO na výstup...");
+        written.extend(write_machine_reports(
+            id,
+            &report,
+            &rendered,
+            &dirs,
+            regenerated,
+        )?);
+        Ok(written)
+    });
+
+    if archived.is_err() && result.is_err() {
+        // poslední záchrana – PDF necháme alespoň v /tmp
+        let _ = fs::write(&pdf_path, &rendered.pdf);
+        log_error(&format!("Report nebyl uložen, PDF ponecháno v {pdf_path}"));
+    }
+
+    log_debug(&format!("generate_report({id}) – HOTOVO"));
+    result
+}
+
+#[derive(Serialize)]
+struct WsReportStatus {
+    #[serde(rename = "type")]
+    msg_type: String,
+    id: i64,
+    status: String,
+    message: Option<String>,
+    files: Vec<String>,
+}
+
+async fn broadcast_report_status(
+    id: i64,
+    status: &str,
+    message: Option<String>,
+    files: Vec<String>,
+) {
+    let msg = WsReportStatus {
+        msg_type: "ReportStatus".to_string(),
+        id,
+        status: status.to_string(),
+        message,
+        files,
+    };
+    let json = serde_json::to_string(&msg).unwrap_or_default();
+    websocket::broadcast_message(&json).await;
+}
+
+/// Vygeneruje report mimo async runtime a průběh hlásí do UI zprávou `ReportStatus`
+/// (`running` → `done` / `error`).
+pub async fn run_report_job(id: i64, target: Option<String>) -> Result<Vec<String>, String> {
+    broadcast_report_status(id, "running", None, Vec::new()).await;
+    let result =
+        tauri::async_runtime::spawn_blocking(move || generate_report_files(id, target.as_deref()))
+            .await
+            .map_err(|e| format!("Async error: {}", e))
+            .and_then(|r| r);
+
+    match &result {
+        Ok(files) => broadcast_report_status(id, "done", None, files.clone()).await,
+        Err(e) => {
+            log_error(&format!("Report procesu {id} selhal: {e}"));
+            broadcast_report_status(id, "error", Some(e.clone()), Vec::new()).await;
         }
-        let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
-        let path = get_interface_path(pool.connection(), disk_id)
-            .map(|p| format!("/dev/disk/by-path/{p}"))
-            .ok_or_else(|| format!("interface_path chybí pro disk {disk_id}"))?;
-        println!("🔍  {key}: disk_utils::get_disk_info({path})");
-        disk_utils::get_disk_info(&path)
-            .map_err(|e| e.to_string())
-            .and_then(|d| serde_json::to_value(d).map_err(|e| e.to_string()))
+    }
+    result
+}
+
+/// Spustí generování reportu na pozadí (po skončení akvizice).
+pub fn spawn_report_job(id: i64) {
+    tauri::async_runtime::spawn(async move {
+        let _ = run_report_job(id, None).await;
+    });
+}
+
+/// Znovu vygeneruje report procesu z historie (např. po opravě šablony nebo
+/// pro chybový běh) na zvolený připojený výstup `target`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn generate_report(process_id: i64, target: String) -> Result<Vec<String>, String> {
+    run_report_job(process_id, Some(target)).await
+}
+
+/// Escapování textu pro vložení do LaTeXu.
+fn latex_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            '<' => out.push_str("\\textless{}"),
+            '>' => out.push_str("\\textgreater{}"),
+            '|' => out.push_str("\\textbar{}"),
+            c if c.is_control() && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Doplní do contextu přílohu s kompletním výstupem nástroje (`process_log_lines`),
+/// spuštěným příkazem a obsahem chybového logu. Vše je escapované pro LaTeX.
+fn insert_log_appendix(
+    ctx: &mut Context,
+    id: i64,
+    report: &Map<String, Value>,
+) -> Result<(), String> {
+    let enabled = crate::settings::settings().report.log_appendix;
+    ctx.insert("appendix_enabled", &enabled);
+    if !enabled {
+        ctx.insert("command_line", "");
+        ctx.insert("log_lines", &Vec::<Value>::new());
+        ctx.insert("error_log", &Vec::<String>::new());
+        return Ok(());
+    }
+
+    let proc = report["copy_process"].as_object().unwrap_or(&EMPTY_MAP);
+    ctx.insert(
+        "command_line",
+        &latex_escape(gs(proc, "command_line").if_empty_then("N/A")),
+    );
+
+    let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pool.connection();
+    let mut stmt = conn
+        .prepare(
+            "SELECT timestamp, line_content FROM process_log_lines
+             WHERE process_id = ?1 ORDER BY line_number ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let lines = stmt
+        .query_map([id], |r| {
+            let ts: String = r.get(0)?;
+            let text = line_text(r, 1)?;
+            Ok(serde_json::json!({
+                "timestamp": ts,
+                "text": latex_escape(text.trim_end()),
+            }))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    ctx.insert("log_lines", &lines);
+
+    let error_log = match gs(proc, "error_log_path") {
+        "" => vec!["N/A".to_string()],
+        path => match fs::File::open(path) {
+            Ok(f) => {
+                use std::io::Read;
+                let mut buf = Vec::new();
+                f.take(REPORT_ERROR_LOG_MAX_BYTES)
+                    .read_to_end(&mut buf)
+                    .map_err(|e| e.to_string())?;
+                let mut lines: Vec<String> = String::from_utf8_lossy(&buf)
+                    .lines()
+                    // prázdný řádek by v LaTeXu skončil chybou u `\\`
+                    .map(|l| match latex_escape(l) {
+                        e if e.trim().is_empty() => "~".to_string(),
+                        e => e,
+                    })
+                    .collect();
+                if buf.len() as u64 == REPORT_ERROR_LOG_MAX_BYTES {
+                    lines.push(format!(
+                        "[truncated after {REPORT_ERROR_LOG_MAX_BYTES} bytes]"
+                    ));
+                }
+                if lines.is_empty() {
+                    lines.push("(empty)".into());
+                }
+                lines
+            }
+            Err(e) => vec![latex_escape(&format!("{path}: {e}"))],
+        },
     };
-    report.insert("source_disk".into(), load_disk("source_disk_id")?);
-    report.insert("dest_disk".into(), load_disk("dest_disk_id")?);
-    report.insert("second_dest_disk".into(), load_disk("second_dest_disk_id")?);
-    println!("✅  disky načteny");
+    ctx.insert("error_log", &error_log);
+    Ok(())
+}
+
+/// Tera context reportu podle typu procesu.
+fn report_context(kind: TemplateKind, report: &Map<String, Value>) -> Result<Context, String> {
+    match kind {
+        TemplateKind::Ewf => context_ewfacquire(report),
+        TemplateKind::Dd => context_dcfldd(report),
+    }
+}
+
+/// Vyrenderuje PDF report aktivní šablonou (z disku, jinak vestavěnou).
+///
+/// Obrázky pro šablonu (QR kód, loga šablony z disku) se připraví do dočasné
+/// pracovní složky, ze které je tectonic načítá.
+fn render_pdf(id: i64, report: &Map<String, Value>) -> Result<RenderedReport, String> {
+    let kind = TemplateKind::of_report(report);
+    let mut ctx = report_context(kind, report)?;
+    insert_log_appendix(&mut ctx, id, report)?;
+    let tpl = template::active_template(kind);
+    ctx.insert("template_name", &latex_escape(&tpl.name));
+    ctx.insert("template_hash", &tpl.sha256);
+
+    let (latex, pdf) = tex::with_work_dir(&format!("report_{id}"), |work_dir| {
+        if !tpl.builtin {
+            for entry in fs::read_dir(report_template_dir())
+                .map_err(|e| e.to_string())?
+                .flatten()
+            {
+                if entry.path().is_file() {
+                    fs::copy(entry.path(), work_dir.join(entry.file_name()))
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+        }
+
+        let payload = qr::qr_payload(
+            &report_json::build_report_json(report),
+            &snapshot::first_image_file(report),
+        );
+        fs::write(work_dir.join(qr::QR_IMAGE_FILE), qr::qr_png(&payload)?)
+            .map_err(|e| e.to_string())?;
+        ctx.insert("qr_code", qr::QR_IMAGE_FILE);
+        ctx.insert("qr_payload", &latex_escape(&payload));
+
+        log_debug(&format!("Renderuji šablonu {}...", tpl.name));
+        let latex = Tera::one_off(&tpl.source, &ctx, false).map_err(|err| {
+            log_error(&format!("Tera render error: {:#?}", err));
+            format!("Render selhal: {err}")
+        })?;
+
+        log_debug("Překládám LaTeX do PDF (tectonic)...");
+        let pdf = tex::latex_to_pdf(&latex, Some(work_dir)).map_err(|e| {
+            log_error(&format!("Chyba při generování PDF reportu {id}: {e}"));
+            e
+        })?;
+        Ok((latex, pdf))
+    })?;
+
+    Ok(RenderedReport {
+        pdf,
+        tex: latex,
+        template_name: tpl.name,
+        template_sha256: tpl.sha256,
+    })
+}
 
+/// Sestaví Tera context reportu pro ewfacquire.
+fn context_ewfacquire(report: &Map<String, Value>) -> Result<Context, String> {
+    println!("▶️  context_ewfacquire – START");
+    println!("Current dir: {:?}", std::env::current_dir());
+    println!("USER: {:?}", std::env::var("USER"));
+    println!("HOME: {:?}", std::env::var("HOME"));
+    println!("PATH: {:?}", std::env::var("PATH"));
+
+    let log_map = report["log_record"].as_object().unwrap_or(&EMPTY_MAP);
     let root_value = Value::Object(report.clone());
     // debug‐print celého JSON
     println!(
@@ -257,6 +809,8 @@ pub fn generate_report_ewfacquire(id: i64) -> Result<(), String> {
         .map(|dt| dt.with_timezone(&Local));
     if let Some(s) = t_start {
         ctx.insert("time_started", &s.format("%H:%M:%S %-d.%-m.%Y").to_string());
+    } else {
+        ctx.insert("time_started", "N/A");
     }
     let t_end = NaiveDateTime::parse_from_str(gs(&log_map, "end_datetime"), fmt)
         .ok()
@@ -267,6 +821,9 @@ pub fn generate_report_ewfacquire(id: i64) -> Result<(), String> {
             "time_complete",
             &c.format("%H:%M:%S %-d.%-m.%Y").to_string(),
         );
+    } else {
+        // proces ukončený pádem aplikace nemá čas konce
+        ctx.insert("time_complete", "N/A");
     }
     let duration = t_start
         .zip(t_end)
@@ -479,159 +1036,14 @@ pub fn generate_report_ewfacquire(id: i64) -> Result<(), String> {
     ctx.insert("source_partitions", &parts);
     println!("✅  Source partitions – {}", parts.len());
 
-    println!("🚧  Renderuji Tera …");
-
-    let latex = match Tera::one_off(TEMPLATE_EN_EWF, &ctx, false) {
-        Ok(l) => l,
-        Err(err) => {
-            eprintln!("❌  Tera render error: {:#?}", err);
-            eprintln!("└── {}", err);
-            return Err(format!("Render selhal: {err}"));
-        }
-    };
-
-    // Unikátní dočasné cesty podle id
-    let tex_path = format!("/tmp/report_{id}.tex");
-    let pdf_path = format!("/tmp/report_{id}.pdf");
-
-    // Zápis .tex
-    fs::write(&tex_path, &latex).map_err(|e| e.to_string())?;
-
-    // Spuštění tectonic
-    let tectonic_status = std::process::Command::new("tectonic")
-        .arg("--outdir")
-        .arg("/tmp")
-        .arg(&tex_path)
-        .status()
-        .map_err(|e| format!("Failed to run tectonic: {e}"))?;
-
-    if !tectonic_status.success() {
-        // Úklid
-        let _ = fs::remove_file(&tex_path);
-        return Err(format!(
-            "Tectonic failed with exit code: {:?}",
-            tectonic_status.code()
-        ));
-    }
-
-    // Načtení PDF
-    log_debug(&format!("Načítám PDF soubor z cesty: {}", pdf_path));
-    let pdf = fs::read(&pdf_path).map_err(|e| {
-        log_error(&format!("Chyba při načítání PDF souboru: {e}"));
-        e.to_string()
-    })?;
-
-    // Uložení na cílové zařízení
-    log_debug("Ukládám PDF na cílové zařízení...");
-    let save_pdf = |key: &str, pdf_path: &str| -> std::io::Result<()> {
-        log_debug(&format!("Zpracovávám klíč: {key}"));
-        let disk = report[key].as_object().unwrap_or(&EMPTY_MAP);
-
-        // Najdi oddíl s platným mountpointem (string a není prázdný)
-        let mount_opt = disk
-            .get("partitions")
-            .and_then(Value::as_array)
-            .and_then(|parts| {
-                parts.iter().find_map(|p| {
-                    p.get("mountpoint")
-                        .and_then(Value::as_str)
-                        .filter(|s| !s.is_empty())
-                })
-            });
-
-        if let Some(mp) = mount_opt {
-            // Sestav konečnou cestu
-            let target_dir = format!(
-                "{mp}/{}/{}/",
-                gs(&log_map, "case_number"),
-                gs(&log_map, "evidence_number")
-            );
-            let target_path = format!("{target_dir}audit-report.pdf");
-            log_debug(&format!("Cílová cesta sestavena: {target_path}"));
-
-            // Vytvoř adresář, pokud ještě neexistuje
-            std::process::Command::new("sudo")
-                .arg("mkdir")
-                .arg("-p")
-                .arg(&target_dir)
-                .status()?;
-
-            // Kopíruj soubor pod rootem
-            let status = std::process::Command::new("sudo")
-                .arg("cp")
-                .arg(pdf_path)
-                .arg(&target_path)
-                .status()?;
-            if !status.success() {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("sudo cp failed with status {:?}", status.code()),
-                ));
-            }
-            log_debug(&format!("PDF úspěšně přesunuto na {target_path}"));
-        } else {
-            log_error("Cílová cesta nebyla nalezena (žádný mountpoint).");
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Cílová cesta nebyla nalezena.",
-            ));
-        }
-        Ok(())
-    };
-
-    save_pdf("dest_disk", &pdf_path)
-        .and(save_pdf("second_dest_disk", &pdf_path))
-        .map_err(|e| {
-            log_error(&format!("Chyba při ukládání PDF na cílové zařízení: {e}"));
-            e.to_string()
-        })?;
-
-    log_debug(&format!("Mažu dočasný soubor: {tex_path}"));
-    let _ = fs::remove_file(&tex_path);
-    log_debug(&format!("Mažu dočasný soubor: {pdf_path}"));
-    let _ = fs::remove_file(&pdf_path);
-
-    log_debug(&format!("generate_report({id}) – HOTOVO"));
-    Ok(())
+    Ok(ctx)
 }
 
-pub fn generate_report_dcfldd(id: i64) -> Result<(), String> {
-    println!("▶️  generate_report_dcfldd({id}) – START");
-
-    // 1️⃣  Načti agregovaný JSON z DB
-    let mut report = get_report_json_data(id)?
-        .as_object()
-        .cloned()
-        .ok_or("Report JSON není objekt")?;
-    let log_map = report["log_record"]
-        .as_object()
-        .cloned()
-        .unwrap_or_default();
-    println!(
-        "✅  data z DB – klíče: {:?}",
-        report.keys().collect::<Vec<_>>()
-    );
-
-    // --- helper: načti disk podle ID v logu
-    let load_disk = |key: &str| -> Result<Value, String> {
-        let disk_id = log_map.get(key).and_then(Value::as_i64).unwrap_or(0);
-        if disk_id == 0 {
-            return Ok(Value::Null);
-        }
-        let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
-        let path = get_interface_path(pool.connection(), disk_id)
-            .map(|p| format!("/dev/disk/by-path/{p}"))
-            .ok_or_else(|| format!("interface_path chybí pro disk {disk_id}"))?;
-        println!("🔍  {key}: disk_utils::get_disk_info({path})");
-        disk_utils::get_disk_info(&path)
-            .map_err(|e| e.to_string())
-            .and_then(|d| serde_json::to_value(d).map_err(|e| e.to_string()))
-    };
-    report.insert("source_disk".into(), load_disk("source_disk_id")?);
-    report.insert("dest_disk".into(), load_disk("dest_disk_id")?);
-    report.insert("second_dest_disk".into(), load_disk("second_dest_disk_id")?);
-    println!("✅  disky načteny");
+/// Sestaví Tera context reportu pro dcfldd.
+fn context_dcfldd(report: &Map<String, Value>) -> Result<Context, String> {
+    println!("▶️  context_dcfldd – START");
 
+    let log_map = report["log_record"].as_object().unwrap_or(&EMPTY_MAP);
     let root_value = Value::Object(report.clone());
     println!(
         "DEBUG root JSON:\n{}",
@@ -767,6 +1179,8 @@ pub fn generate_report_dcfldd(id: i64) -> Result<(), String> {
         .map(|dt| dt.with_timezone(&Local));
     if let Some(s) = t_start {
         ctx.insert("time_started", &s.format("%H:%M:%S %-d.%-m.%Y").to_string());
+    } else {
+        ctx.insert("time_started", "N/A");
     }
     let t_end = NaiveDateTime::parse_from_str(gs(&log_map, "end_datetime"), fmt)
         .ok()
@@ -777,6 +1191,9 @@ pub fn generate_report_dcfldd(id: i64) -> Result<(), String> {
             "time_complete",
             &c.format("%H:%M:%S %-d.%-m.%Y").to_string(),
         );
+    } else {
+        // proces ukončený pádem aplikace nemá čas konce
+        ctx.insert("time_complete", "N/A");
     }
     let duration = t_start
         .zip(t_end)
@@ -977,125 +1394,44 @@ pub fn generate_report_dcfldd(id: i64) -> Result<(), String> {
     }
     ctx.insert("source_partitions", &parts);
 
-    log_debug("Renderuji Tera šablonu...");
-    let latex = match Tera::one_off(TEMPLATE_EN_DD, &ctx, false) {
-        Ok(l) => l,
-        Err(err) => {
-            log_error(&format!("Tera render error: {:#?}", err));
-            return Err(format!("Render selhal: {err}"));
-        }
-    };
-
-    // Unikátní dočasné cesty podle id
-    let tex_path = format!("/tmp/report_{id}.tex");
-    let pdf_path = format!("/tmp/report_{id}.pdf");
-
-    log_debug(&format!("Zapisuje se .tex soubor na cestu: {tex_path}"));
-    fs::write(&tex_path, &latex).map_err(|e| {
-        log_error(&format!("Chyba při zápisu .tex souboru: {e}"));
-        e.to_string()
-    })?;
-
-    log_debug("Spouštím tectonic pro generování PDF...");
-    let tectonic_status = std::process::Command::new("tectonic")
-        .arg("--outdir")
-        .arg("/tmp")
-        .arg(&tex_path)
-        .status()
-        .map_err(|e| {
-            log_error(&format!("Chyba při spuštění tectonic: {e}"));
-            format!("Failed to run tectonic: {e}")
-        })?;
-
-    if !tectonic_status.success() {
-        log_error(&format!(
-            "Tectonic selhal s kódem: {:?}",
-            tectonic_status.code()
-        ));
-        // Úklid
-        // let _ = fs::remove_file(&tex_path);
-        return Err(format!(
-            "Tectonic failed with exit code: {:?}",
-            tectonic_status.code()
-        ));
-    }
-
-    log_debug(&format!("Načítám PDF soubor z cesty: {pdf_path}"));
-    let pdf = fs::read(&pdf_path).map_err(|e| {
-        log_error(&format!("Chyba při načítání PDF souboru: {e}"));
-        e.to_string()
-    })?;
-
-    log_debug("Ukládám PDF na cílové zařízení...");
-    let save_pdf = |key: &str, pdf_path: &str| -> std::io::Result<()> {
-        let disk = report[key].as_object().unwrap_or(&EMPTY_MAP);
-
-        // Najdi oddíl s platným mountpointem (string a není prázdný)
-        let mount_opt = disk
-            .get("partitions")
-            .and_then(Value::as_array)
-            .and_then(|parts| {
-                parts.iter().find_map(|p| {
-                    p.get("mountpoint")
-                        .and_then(Value::as_str)
-                        .filter(|s| !s.is_empty())
-                })
-            });
+    Ok(ctx)
+}
 
-        if let Some(mp) = mount_opt {
-            // Sestav konečnou cestu
-            let target_dir = format!(
-                "{mp}/{}/{}/",
-                gs(&log_map, "case_number"),
-                gs(&log_map, "evidence_number")
-            );
-            let target_path = format!("{target_dir}audit-report.pdf");
-            log_debug(&format!("Cílová cesta sestavena: {target_path}"));
-
-            // Vytvoř adresář, pokud ještě neexistuje
-            std::process::Command::new("sudo")
-                .arg("mkdir")
-                .arg("-p")
-                .arg(&target_dir)
-                .status()?;
-
-            // Kopíruj soubor pod rootem
-            let status = std::process::Command::new("sudo")
-                .arg("cp")
-                .arg(pdf_path)
-                .arg(&target_path)
-                .status()?;
-            if !status.success() {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("sudo cp failed with status {:?}", status.code()),
-                ));
-            }
-            log_debug(&format!("PDF úspěšně přesunuto na {target_path}"));
-        } else {
-            log_error("Cílová cesta nebyla nalezena (žádný mountpoint).");
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Cílová cesta nebyla nalezena.",
-            ));
+/// Vrátí CASE/UCO JSON-LD bundle se všemi akvizicemi daného případu. Zdroj
+/// i soubory obrazu pocházejí ze snímku uloženého při akvizici.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_case_uco(case_number: String) -> Result<Value, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<Value, String> {
+        let ids: Vec<i64> = {
+            let mut pool = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+            let conn = pool.connection();
+            let mut stmt = conn
+                .prepare(
+                    "SELECT p.id FROM copy_process p
+                     LEFT JOIN copy_log_ewf e ON p.triggered_by_ewf = e.id
+                     LEFT JOIN copy_log_dd d ON p.triggered_by_dd = d.id
+                     WHERE COALESCE(e.case_number, d.case_number) = ?1
+                     ORDER BY p.id",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([&case_number], |r| r.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        if ids.is_empty() {
+            return Err(format!("Případ {case_number} nemá žádné akvizice"));
         }
-        Ok(())
-    };
-
-    save_pdf("dest_disk", &pdf_path)
-        .and(save_pdf("second_dest_disk", &pdf_path))
-        .map_err(|e| {
-            log_error(&format!("Chyba při ukládání PDF na cílové zařízení: {e}"));
-            e.to_string()
-        })?;
 
-    log_debug(&format!("Mažu dočasný soubor: {tex_path}"));
-    let _ = fs::remove_file(&tex_path);
-    log_debug(&format!("Mažu dočasný soubor: {pdf_path}"));
-    let _ = fs::remove_file(&pdf_path);
-
-    log_debug(&format!("generate_report({id}) – HOTOVO"));
-    Ok(())
+        let mut reports = Vec::with_capacity(ids.len());
+        for id in ids {
+            let report = load_report_data(id)?;
+            reports.push(report_json::build_report_json(&report));
+        }
+        Ok(case_uco::build_case_bundle(&case_number, &reports))
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
 }
 
 /// ------------ Načtení všech potřebných dat z DB --------------------------
@@ -1167,13 +1503,18 @@ pub fn get_report_json_data(copy_id: i64) -> Result<Value, String> {
             |r| r.get(0),
         )
         .map_err(|e| e.to_string())?;
-    let cfg_record = conn
+    let mut cfg_record = conn
         .query_row(
             &format!("SELECT * FROM {cfg_table} WHERE id=?"),
             [cfg_id],
             |r| Ok(row_to_json_with_cols(r, &cfg_cols)),
         )
         .map_err(|e| e.to_string())?;
+    // preset mohl mít pole `ask` nebo se mezitím změnit – report uvádí
+    // hodnoty, se kterými akvizice skutečně běžela
+    if let Value::Object(cfg) = &mut cfg_record {
+        apply_effective_config(cfg, log_record["effective_config"].as_str());
+    }
 
     let mut root = Map::new();
     root.insert("copy_process".into(), copy_process);
@@ -0,0 +1,79 @@
+//! QR kód s identifikátory a hashi akvizice, vkládaný do PDF reportu.
+//!
+//! Formát obsahu (verze 1) je jeden řádek ASCII, pole oddělená `|`:
+//!
+//! ```text
+//! CRATEC1|C:<case_number>|E:<evidence_number>|F:<image_file>|MD5:<hex>|SHA1:<hex>|SHA256:<hex>
+//! ```
+//!
+//! - první pole je vždy `CRATEC1` (identifikátor formátu a jeho verze),
+//! - `C`, `E` a `F` jsou vždy přítomné (případně prázdné),
+//! - hashe následují v abecedním pořadí algoritmu, jen ty, které byly spočítány,
+//!   název algoritmu velkými písmeny, hodnota v malých hex číslicích,
+//! - znaky `|` a `\` v hodnotách jsou escapované zpětným lomítkem (`\|`, `\\`).
+use super::report_json::ReportJson;
+use qrcode::{Color, EcLevel, QrCode};
+
+pub const QR_FORMAT_ID: &str = "CRATEC1";
+
+/// Název PNG souboru ve složce, ze které tectonic načítá obrázky.
+pub const QR_IMAGE_FILE: &str = "qr.png";
+
+/// Velikost jednoho modulu v pixelech a šířka tiché zóny v modulech.
+const MODULE_PX: usize = 8;
+const QUIET_ZONE: usize = 4;
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+/// Sestaví obsah QR kódu podle formátu popsaného výše. `image_file` je
+/// soubor obrazu uložený u procesu (viz [`super::snapshot::first_image_file`]).
+pub fn qr_payload(report: &ReportJson, image_file: &str) -> String {
+    let mut fields = vec![
+        QR_FORMAT_ID.to_string(),
+        format!("C:{}", escape(&report.case.case_number)),
+        format!("E:{}", escape(&report.case.evidence_number)),
+        format!("F:{}", escape(image_file)),
+    ];
+    for (alg, digest) in &report.hashes {
+        fields.push(format!(
+            "{}:{}",
+            alg.to_uppercase(),
+            escape(&digest.to_lowercase())
+        ));
+    }
+    fields.join("|")
+}
+
+/// Zakóduje `payload` do QR kódu a vrátí ho jako černobílé PNG.
+pub fn qr_png(payload: &str) -> Result<Vec<u8>, String> {
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), EcLevel::M)
+        .map_err(|e| format!("QR kód: {e}"))?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let size = (modules + 2 * QUIET_ZONE) * MODULE_PX;
+
+    let mut pixels = vec![255u8; size * size];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark {
+            continue;
+        }
+        let (mx, my) = (i % modules + QUIET_ZONE, i / modules + QUIET_ZONE);
+        for y in my * MODULE_PX..(my + 1) * MODULE_PX {
+            pixels[y * size + mx * MODULE_PX..y * size + (mx + 1) * MODULE_PX].fill(0);
+        }
+    }
+
+    let mut out = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut out, size as u32, size as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        writer
+            .write_image_data(&pixels)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(out)
+}
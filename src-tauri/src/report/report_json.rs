@@ -0,0 +1,353 @@
+//! Strojově čitelný report `report.json`, ukládaný vedle `audit-report.pdf`.
+//!
+//! Schéma je verzované (`schema` + `schema_version`). Nová pole se smí přidávat
+//! bez změny verze, přejmenování nebo odebrání pole vyžaduje zvýšení
+//! `SCHEMA_VERSION`.
+//!
+//! Schéma verze 1:
+//!
+//! ```text
+//! {
+//!   "schema": "cratec-report",
+//!   "schema_version": 1,
+//!   "generated_at": "2025-05-19T10:00:00Z",            // UTC, RFC 3339
+//!   "unit": {
+//!     "software": "CRATEC",
+//!     "software_version": "1.0.0",
+//!     "build_date": "...",
+//!     "software_md5": "...",                            // MD5 binárky cratec
+//!     "hw_id": "...",                                   // krátké HW ID jednotky
+//!     "tool_versions": { "ewfacquire": "...", "dcfldd": "..." }
+//!   },
+//!   "process": {
+//!     "id": 1, "status": "done|error|running",
+//!     "start_datetime": "...Z", "end_datetime": "...Z" | null
+//!   },
+//!   "case": {
+//!     "case_number": "...", "evidence_number": "...", "examiner": "...",
+//!     "description": "...", "notes": "..."
+//!   },
+//!   "acquisition": {
+//!     "tool": "ewfacquire|dcfldd",
+//!     "config_id": 1, "config_name": "...",
+//!     "parameters": { ... },                            // řádek ewf_config / dd_config
+//!     "offset": 0,
+//!     "bytes_to_read": 0 | null,                        // jen ewfacquire, 0 = celý disk
+//!     "limit_blocks": 0 | null                          // jen dcfldd, 0 = celý disk
+//!   },
+//!   "source": { "role": "Source", "serial": "...", "model": "...",
+//!               "capacity_bytes": 0, "logical_sector_size": 512,
+//!               "hpa": false, "dco_real_max_sectors": 0 } | null,
+//!   "destinations": [
+//!     { "role": "Destination", "serial": "...", "model": "...",
+//!       "mountpoint": "...", "evidence_dir": "...",
+//!       "files": [ { "name": "EV1.E01", "size": 0 } ] }
+//!   ],
+//!   "hashes": { "md5": "...", "sha1": "...", "sha256": "..." },
+//!   "template": { "name": "builtin:en_ewf.tex", "sha256": "..." }  // jen u reportu s PDF
+//! }
+//! ```
+use super::{disk_mountpoint, gs, gu, EMPTY_MAP};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const SCHEMA_ID: &str = "cratec-report";
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Název souboru, pod kterým se report ukládá do složky s důkazem.
+pub const REPORT_JSON_FILE: &str = "report.json";
+
+#[derive(Debug, Serialize)]
+pub struct ReportJson {
+    pub schema: &'static str,
+    pub schema_version: u32,
+    pub generated_at: String,
+    pub unit: UnitInfo,
+    pub process: ProcessInfo,
+    pub case: CaseInfo,
+    pub acquisition: AcquisitionInfo,
+    pub source: Option<DiskEntry>,
+    pub destinations: Vec<DestinationEntry>,
+    pub hashes: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<TemplateInfo>,
+}
+
+/// Šablona, kterou byl vyrenderován PDF report.
+#[derive(Debug, Serialize)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub sha256: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnitInfo {
+    pub software: &'static str,
+    pub software_version: &'static str,
+    pub build_date: String,
+    pub software_md5: String,
+    pub hw_id: String,
+    pub tool_versions: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessInfo {
+    pub id: i64,
+    pub status: String,
+    pub start_datetime: Option<String>,
+    pub end_datetime: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CaseInfo {
+    pub case_number: String,
+    pub evidence_number: String,
+    pub examiner: String,
+    pub description: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AcquisitionInfo {
+    pub tool: String,
+    pub config_id: i64,
+    pub config_name: String,
+    pub parameters: Map<String, Value>,
+    pub offset: u64,
+    pub bytes_to_read: Option<u64>,
+    pub limit_blocks: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiskEntry {
+    pub role: String,
+    pub serial: String,
+    pub model: String,
+    pub capacity_bytes: u64,
+    pub logical_sector_size: u64,
+    pub hpa: bool,
+    pub dco_real_max_sectors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DestinationEntry {
+    pub role: String,
+    pub serial: String,
+    pub model: String,
+    pub mountpoint: String,
+    pub evidence_dir: String,
+    pub files: Vec<ImageFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageFile {
+    pub name: String,
+    pub size: u64,
+}
+
+/// Převod času z DB (`YYYY-MM-DD HH:MM:SS`, UTC) na RFC 3339.
+fn db_time_to_rfc3339(s: &str) -> Option<String> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc().format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+fn disk_entry(disk: &Map<String, Value>, role: &str) -> Option<DiskEntry> {
+    if disk.is_empty() {
+        return None;
+    }
+    Some(DiskEntry {
+        role: role.into(),
+        serial: gs(disk, "serial").into(),
+        model: gs(disk, "model").into(),
+        capacity_bytes: gu(disk, "capacity_bytes"),
+        logical_sector_size: gu(disk, "logical_sector_size"),
+        hpa: disk
+            .get("has_hpa")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        dco_real_max_sectors: gu(disk, "dco"),
+    })
+}
+
+/// Přípona segmentu obrazu: `img` a `img.000…` (dcfldd, `split`), `E01`…`E99`,
+/// `EAA`… a varianty `Ex01`, `s01`, `L01` (ewfacquire).
+fn is_image_extension(ext: &str) -> bool {
+    if ext == "img" {
+        return true;
+    }
+    if let Some(n) = ext.strip_prefix("img.") {
+        return !n.is_empty() && n.chars().all(|c| c.is_ascii_digit());
+    }
+    let mut chars = ext.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    let rest = chars.as_str();
+    let rest = rest
+        .strip_prefix('x')
+        .or_else(|| rest.strip_prefix('X'))
+        .unwrap_or(rest);
+    matches!(first.to_ascii_uppercase(), 'E' | 'S' | 'L')
+        && rest.len() == 2
+        && rest.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Vypíše soubory obrazu ve složce s důkazem (`EV.E01`, `EV.E02`, `EV.img` …).
+/// Název musí být přesně `<číslo důkazu>.<přípona segmentu>`, takže `EV1`
+/// nezachytí `EV10.E01`.
+pub(crate) fn list_image_files(dir: &str, evidence_number: &str) -> Vec<ImageFile> {
+    let mut files = Vec::new();
+    if evidence_number.is_empty() {
+        return files;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let is_segment = name
+            .strip_prefix(evidence_number)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(is_image_extension);
+        if !is_segment {
+            continue;
+        }
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        files.push(ImageFile { name, size });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    files
+}
+
+/// Sestaví `report.json` z agregovaných dat reportu (výstup `get_report_json_data`
+/// doplněný o `source_disk`, `dest_disk`, `second_dest_disk` a případně
+/// `image_files` ze snímku akvizice).
+///
+/// Soubory obrazu se berou ze snímku; složka s důkazem se čte jen u procesu,
+/// který je ještě nemá uložené (běžící akvizice).
+pub fn build_report_json(report: &Map<String, Value>) -> ReportJson {
+    let proc = report["copy_process"].as_object().unwrap_or(&EMPTY_MAP);
+    let log = report["log_record"].as_object().unwrap_or(&EMPTY_MAP);
+    let cfg = report["config_record"].as_object().unwrap_or(&EMPTY_MAP);
+
+    let is_ewf = proc
+        .get("triggered_by_ewf")
+        .map(|v| !v.is_null())
+        .unwrap_or(false);
+
+    let sysinfo = crate::system_info::get_report_system_info().ok();
+    let mut tool_versions = BTreeMap::new();
+    if let Ok(v) = crate::system_info::get_program_versions() {
+        tool_versions.insert("ewfacquire".to_string(), v.ewfacquire);
+        tool_versions.insert("dcfldd".to_string(), v.dcfldd);
+    }
+
+    let mut parameters = cfg.clone();
+    parameters.remove("active");
+
+    let mut hashes = BTreeMap::new();
+    for (name, key) in [
+        ("md5", "md5_hash"),
+        ("sha1", "sha1_hash"),
+        ("sha256", "sha256_hash"),
+        ("sha384", "sha384_hash"),
+        ("sha512", "sha512_hash"),
+    ] {
+        let h = gs(log, key);
+        if !h.is_empty() {
+            hashes.insert(name.to_string(), h.to_string());
+        }
+    }
+
+    let case_number = gs(log, "case_number");
+    let evidence_number = gs(log, "evidence_number");
+    let stored_files = report.get("image_files").and_then(Value::as_object);
+    let mut destinations = Vec::new();
+    for (key, role) in [
+        ("dest_disk", "Destination"),
+        ("second_dest_disk", "Secondary Destination"),
+    ] {
+        let disk = report
+            .get(key)
+            .and_then(Value::as_object)
+            .unwrap_or(&EMPTY_MAP);
+        let Some(mount) = disk_mountpoint(disk) else {
+            continue;
+        };
+        let evidence_dir = Path::new(mount)
+            .join(case_number)
+            .join(evidence_number)
+            .to_string_lossy()
+            .to_string();
+        let files = match stored_files {
+            Some(stored) => stored
+                .get(key)
+                .cloned()
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            None => list_image_files(&evidence_dir, evidence_number),
+        };
+        destinations.push(DestinationEntry {
+            role: role.into(),
+            serial: gs(disk, "serial").into(),
+            model: gs(disk, "model").into(),
+            mountpoint: mount.into(),
+            evidence_dir,
+            files,
+        });
+    }
+
+    ReportJson {
+        schema: SCHEMA_ID,
+        schema_version: SCHEMA_VERSION,
+        generated_at: Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        unit: UnitInfo {
+            software: "CRATEC",
+            software_version: env!("CARGO_PKG_VERSION"),
+            build_date: sysinfo
+                .as_ref()
+                .map(|s| s.build_date.clone())
+                .unwrap_or_default(),
+            software_md5: sysinfo
+                .as_ref()
+                .map(|s| s.cratec_hash.clone())
+                .unwrap_or_default(),
+            hw_id: sysinfo.map(|s| s.short_hw_id).unwrap_or_default(),
+            tool_versions,
+        },
+        process: ProcessInfo {
+            id: proc.get("id").and_then(Value::as_i64).unwrap_or(0),
+            status: gs(proc, "status").into(),
+            start_datetime: db_time_to_rfc3339(gs(proc, "start_datetime")),
+            end_datetime: db_time_to_rfc3339(gs(proc, "end_datetime")),
+        },
+        case: CaseInfo {
+            case_number: case_number.into(),
+            evidence_number: evidence_number.into(),
+            examiner: gs(log, "investigator_name").into(),
+            description: gs(log, "description").into(),
+            notes: gs(log, "notes").into(),
+        },
+        acquisition: AcquisitionInfo {
+            tool: if is_ewf { "ewfacquire" } else { "dcfldd" }.into(),
+            config_id: log.get("config_id").and_then(Value::as_i64).unwrap_or(0),
+            config_name: gs(cfg, "confname").into(),
+            parameters,
+            offset: gu(log, "offset"),
+            bytes_to_read: is_ewf.then(|| gu(log, "bytes_to_read")),
+            limit_blocks: (!is_ewf).then(|| gu(log, "limit_value")),
+        },
+        source: report
+            .get("source_disk")
+            .and_then(Value::as_object)
+            .and_then(|d| disk_entry(d, "Source")),
+        destinations,
+        hashes,
+        template: None,
+    }
+}
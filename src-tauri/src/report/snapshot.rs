@@ -0,0 +1,101 @@
+//! Snímek disků akvizice uložený v `copy_process.acquisition_snapshot`.
+//!
+//! Při spuštění akvizice se do něj zapíše zdroj a cílové disky (sériové
+//! číslo, model, velikost, velikost sektoru, oddíly a jejich mountpointy),
+//! tak jak je vrací `get_disk_info`. Report dokončeného procesu se skládá
+//! jen z tohoto snímku – disky, které jsou v pozicích teď, s akvizicí
+//! nemusí mít nic společného. Po skončení akvizice se do snímku doplní
+//! soubory obrazu, které na cílových discích vznikly.
+//!
+//! ```text
+//! { "source_disk": {...} | null, "dest_disk": {...} | null,
+//!   "second_dest_disk": {...} | null,
+//!   "image_files": { "dest_disk": [ { "name": "EV1.E01", "size": 0 } ],
+//!                    "second_dest_disk": [...] } }
+//! ```
+use super::report_json::list_image_files;
+use crate::disk_utils;
+use crate::logger::log_warn;
+use rusqlite::{params, Connection};
+use serde_json::{Map, Value};
+
+/// Klíče disků ve snímku i v datech reportu.
+pub const DISK_KEYS: [&str; 3] = ["source_disk", "dest_disk", "second_dest_disk"];
+
+fn probe(path: &str) -> Value {
+    disk_utils::get_disk_info(path)
+        .and_then(|d| serde_json::to_value(d).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| {
+            log_warn(&format!("Snímek disku {path} se nepodařilo pořídit: {e}"));
+            Value::Null
+        })
+}
+
+/// Sejme zdrojový a cílové disky (`/dev/disk/by-path/...`). Disk, který
+/// nejde přečíst, se uloží jako `null`.
+pub fn capture_disks(source: &str, outputs: &[String]) -> Value {
+    let mut snapshot = Map::new();
+    snapshot.insert(DISK_KEYS[0].into(), probe(source));
+    for (i, key) in DISK_KEYS[1..].iter().enumerate() {
+        let disk = outputs.get(i).map(|o| probe(o)).unwrap_or(Value::Null);
+        snapshot.insert((*key).into(), disk);
+    }
+    Value::Object(snapshot)
+}
+
+/// Přečte snímek z řádku `copy_process` (sloupec je JSON text, u starších
+/// procesů `NULL`).
+pub fn parse(copy_process: &Map<String, Value>) -> Option<Map<String, Value>> {
+    copy_process
+        .get("acquisition_snapshot")
+        .and_then(Value::as_str)
+        .and_then(|s| serde_json::from_str(s).ok())
+}
+
+/// Název prvního souboru obrazu uloženého ve snímku (primární cíl má
+/// přednost), prázdný, pokud proces soubory uložené nemá. Čte z dat reportu
+/// (`image_files` z [`load_report_data`](super::load_report_data)).
+pub fn first_image_file(report: &Map<String, Value>) -> String {
+    let stored = report.get("image_files").and_then(Value::as_object);
+    DISK_KEYS[1..]
+        .iter()
+        .filter_map(|key| stored?.get(*key)?.as_array()?.first())
+        .find_map(|f| f.get("name").and_then(Value::as_str))
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Doplní do snímku procesu soubory obrazu ve složkách s důkazem na cílových
+/// discích (`evidence_dirs` v pořadí `dest_disk`, `second_dest_disk`).
+pub fn record_image_files(
+    conn: &Connection,
+    process_id: i64,
+    evidence_dirs: &[String],
+    evidence_number: &str,
+) -> Result<(), String> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT acquisition_snapshot FROM copy_process WHERE id = ?1",
+            [process_id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let mut snapshot: Map<String, Value> = stored
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let mut files = Map::new();
+    for (key, dir) in DISK_KEYS[1..].iter().zip(evidence_dirs) {
+        let listed = serde_json::to_value(list_image_files(dir, evidence_number))
+            .map_err(|e| e.to_string())?;
+        files.insert((*key).into(), listed);
+    }
+    snapshot.insert("image_files".into(), Value::Object(files));
+
+    conn.execute(
+        "UPDATE copy_process SET acquisition_snapshot = ?1 WHERE id = ?2",
+        params![Value::Object(snapshot).to_string(), process_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,329 @@
+//! Výběr šablony reportu.
+//!
+//! Operátor může vestavěnou šablonu nahradit souborem v `[report] template_dir`
+//! (např. s hlavičkou a logem útvaru). Šablona z disku se před použitím ověří
+//! renderem proti vzorovému contextu; neplatná šablona se jen zaloguje
+//! a report se vyrenderuje vestavěnou šablonou.
+use super::{report_context, sha256_hex};
+use crate::config::{mount_root, report_template_dir};
+use crate::db::DB_POOL;
+use crate::lockscreen::require_admin;
+use crate::logger::{log_warn, write_audit};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::Path;
+use tera::Tera;
+
+static TEMPLATE_EN_EWF: &str = include_str!("./templates/en_ewf.tex");
+static TEMPLATE_EN_DD: &str = include_str!("./templates/en_dd.tex");
+
+/// Přípony souborů, které se instalují spolu se šablonou (loga, podpisy).
+const ASSET_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "pdf"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateKind {
+    Ewf,
+    Dd,
+}
+
+impl TemplateKind {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "ewf" | "ewfacquire" => Ok(Self::Ewf),
+            "dd" | "dcfldd" => Ok(Self::Dd),
+            other => Err(format!("Unknown template kind: {}", other)),
+        }
+    }
+
+    /// Typ šablony podle nástroje, kterým proces běžel.
+    pub fn of_report(report: &Map<String, Value>) -> Self {
+        let is_ewf = report["copy_process"]
+            .get("triggered_by_ewf")
+            .map(|v| !v.is_null())
+            .unwrap_or(false);
+        if is_ewf {
+            Self::Ewf
+        } else {
+            Self::Dd
+        }
+    }
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Ewf => "en_ewf.tex",
+            Self::Dd => "en_dd.tex",
+        }
+    }
+
+    fn builtin_source(self) -> &'static str {
+        match self {
+            Self::Ewf => TEMPLATE_EN_EWF,
+            Self::Dd => TEMPLATE_EN_DD,
+        }
+    }
+
+    fn override_path(self) -> String {
+        format!("{}/{}", report_template_dir(), self.file_name())
+    }
+}
+
+pub struct ReportTemplate {
+    /// `builtin:en_ewf.tex` nebo cesta k šabloně na disku
+    pub name: String,
+    pub source: String,
+    pub sha256: String,
+    pub builtin: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateStatus {
+    pub kind: String,
+    pub active: String,
+    pub sha256: String,
+    pub builtin: bool,
+    pub override_path: String,
+    /// Důvod, proč se šablona z disku nepoužívá
+    pub override_error: Option<String>,
+}
+
+fn builtin_template(kind: TemplateKind) -> ReportTemplate {
+    let source = kind.builtin_source();
+    ReportTemplate {
+        name: format!("builtin:{}", kind.file_name()),
+        source: source.to_string(),
+        sha256: sha256_hex(source.as_bytes()),
+        builtin: true,
+    }
+}
+
+/// Vzorová data reportu, proti kterým se šablona ověřuje.
+fn sample_report(kind: TemplateKind) -> Map<String, Value> {
+    let now = chrono::Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let (ewf, dd) = match kind {
+        TemplateKind::Ewf => (json!(1), Value::Null),
+        TemplateKind::Dd => (Value::Null, json!(1)),
+    };
+    let mut report = Map::new();
+    report.insert(
+        "copy_process".into(),
+        json!({
+            "id": 0, "status": "done", "start_datetime": now, "end_datetime": now,
+            "triggered_by_ewf": ewf, "triggered_by_dd": dd
+        }),
+    );
+    report.insert(
+        "log_record".into(),
+        json!({
+            "case_number": "CASE-0001", "evidence_number": "EV-0001",
+            "investigator_name": "Examiner", "notes": "", "description": "",
+            "end_datetime": now, "offset": "0", "bytes_to_read": "0", "limit_value": "0",
+            "md5_hash": "d41d8cd98f00b204e9800998ecf8427e"
+        }),
+    );
+    report.insert(
+        "config_record".into(),
+        json!({ "confname": "sample", "hash_types": "sha256" }),
+    );
+    for key in ["source_disk", "dest_disk", "second_dest_disk"] {
+        report.insert(key.into(), Value::Null);
+    }
+    report
+}
+
+/// Ověří, že šablona je LaTeX dokument a vyrenderuje se se vzorovými daty,
+/// tj. používá jen klíče, které report do contextu skutečně vkládá.
+pub fn validate_template(kind: TemplateKind, source: &str) -> Result<(), String> {
+    if !source.contains("\\documentclass") || !source.contains("\\end{document}") {
+        return Err("Šablona není LaTeX dokument".into());
+    }
+    let mut ctx = report_context(kind, &sample_report(kind))?;
+    ctx.insert("template_name", "sample");
+    ctx.insert("template_hash", &sha256_hex(source.as_bytes()));
+    // příloha s logem – vzorový řádek, aby se ověřily i smyčky
+    ctx.insert("appendix_enabled", &true);
+    ctx.insert("command_line", "sudo ewfacquire -u /dev/sda");
+    ctx.insert(
+        "log_lines",
+        &[json!({ "timestamp": "2025-01-01 00:00:00", "text": "sample" })],
+    );
+    ctx.insert("error_log", &["sample"]);
+    ctx.insert("qr_code", super::qr::QR_IMAGE_FILE);
+    ctx.insert("qr_payload", "CRATEC1");
+    Tera::one_off(source, &ctx, false).map(|_| ()).map_err(|e| {
+        match std::error::Error::source(&e) {
+            Some(cause) => format!("Neplatná šablona: {e}: {cause}"),
+            None => format!("Neplatná šablona: {e}"),
+        }
+    })
+}
+
+/// Načte a ověří šablonu ze složky šablon (`[report] template_dir`). `Ok(None)` = žádná není.
+fn load_override(kind: TemplateKind) -> Result<Option<ReportTemplate>, String> {
+    let path = kind.override_path();
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+    let source = fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+    validate_template(kind, &source)?;
+    Ok(Some(ReportTemplate {
+        sha256: sha256_hex(source.as_bytes()),
+        name: path,
+        source,
+        builtin: false,
+    }))
+}
+
+/// Šablona, se kterou se report vyrenderuje.
+pub fn active_template(kind: TemplateKind) -> ReportTemplate {
+    match load_override(kind) {
+        Ok(Some(t)) => t,
+        Ok(None) => builtin_template(kind),
+        Err(e) => {
+            log_warn(&format!(
+                "Šablona {} se nepoužije, použita vestavěná: {e}",
+                kind.override_path()
+            ));
+            builtin_template(kind)
+        }
+    }
+}
+
+fn template_status(kind: TemplateKind) -> TemplateStatus {
+    let (active, override_error) = match load_override(kind) {
+        Ok(Some(t)) => (t, None),
+        Ok(None) => (builtin_template(kind), None),
+        Err(e) => (builtin_template(kind), Some(e)),
+    };
+    TemplateStatus {
+        kind: kind.file_name().into(),
+        active: active.name,
+        sha256: active.sha256,
+        builtin: active.builtin,
+        override_path: kind.override_path(),
+        override_error,
+    }
+}
+
+/// Ověří, že soubor leží na připojeném výstupu pod `mount_root()`.
+pub(crate) fn mounted_file(path: &str) -> Result<std::path::PathBuf, String> {
+    let p = fs::canonicalize(path).map_err(|e| format!("{path}: {e}"))?;
+    if !p.starts_with(mount_root()) || !p.is_file() {
+        return Err(format!(
+            "Access denied: path {} is outside of MOUNT_ROOT",
+            path
+        ));
+    }
+    Ok(p)
+}
+
+pub(crate) fn sudo(args: &[&str]) -> Result<(), String> {
+    let status = std::process::Command::new("sudo")
+        .args(args)
+        .status()
+        .map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!(
+            "sudo {} failed with status {:?}",
+            args[0],
+            status.code()
+        ));
+    }
+    Ok(())
+}
+
+/// Stav šablon (vestavěná / z disku, hash, případná chyba šablony z disku).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_report_templates() -> Result<Vec<TemplateStatus>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        vec![
+            template_status(TemplateKind::Ewf),
+            template_status(TemplateKind::Dd),
+        ]
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))
+}
+
+/// Ověří šablonu (např. na USB flashce) bez instalace. Vrací její SHA-256.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn validate_report_template(kind: String, path: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let kind = TemplateKind::parse(&kind)?;
+        let source = fs::read_to_string(mounted_file(&path)?).map_err(|e| e.to_string())?;
+        validate_template(kind, &source)?;
+        Ok(sha256_hex(source.as_bytes()))
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Zapíše změnu šablony do auditního logu.
+fn audit(message: &str) -> Result<(), String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    write_audit(pooled.connection(), message).map_err(|e| e.to_string())
+}
+
+/// Ověří a nainstaluje šablonu do složky šablon spolu s obrázky ze stejné
+/// složky (loga apod.). Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn install_report_template(
+    kind: String,
+    path: String,
+    admin_code: String,
+) -> Result<TemplateStatus, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let kind = TemplateKind::parse(&kind)?;
+        let src = mounted_file(&path)?;
+        let source = fs::read_to_string(&src).map_err(|e| e.to_string())?;
+        validate_template(kind, &source)?;
+
+        let dir = report_template_dir();
+        sudo(&["mkdir", "-p", dir])?;
+        if let Some(entries) = src.parent().and_then(|d| fs::read_dir(d).ok()) {
+            for entry in entries.flatten() {
+                let asset = entry.path();
+                let is_asset = asset
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| ASSET_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+                    .unwrap_or(false);
+                if is_asset && asset.is_file() {
+                    sudo(&["cp", &asset.to_string_lossy(), dir])?;
+                }
+            }
+        }
+        sudo(&["cp", &src.to_string_lossy(), &kind.override_path()])?;
+        let status = template_status(kind);
+        audit(&format!(
+            "Nainstalována šablona reportu {} z {} (SHA-256 {})",
+            kind.file_name(),
+            src.display(),
+            sha256_hex(source.as_bytes())
+        ))?;
+        Ok(status)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Odstraní šablonu z disku, report se vrátí k vestavěné šabloně. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn remove_report_template(
+    kind: String,
+    admin_code: String,
+) -> Result<TemplateStatus, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let kind = TemplateKind::parse(&kind)?;
+        sudo(&["rm", "-f", &kind.override_path()])?;
+        audit(&format!(
+            "Odstraněna šablona reportu {}, platí vestavěná",
+            kind.file_name()
+        ))?;
+        Ok(template_status(kind))
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -0,0 +1,94 @@
+//! Překlad LaTeXu do PDF knihovnou tectonic přímo v procesu.
+//!
+//! Jednotka v terénu nemá síť, proto se používá jen lokální bundle
+//! `TECTONIC_BUNDLE` (zip nebo složka s fonty a balíčky LaTeXu), který je
+//! součástí instalace. Nic se nestahuje a nepoužívá se uživatelská cache;
+//! zkompilovaný formát se ukládá do `TECTONIC_FORMAT_CACHE_DIR`.
+use crate::config::{TECTONIC_BUNDLE, TECTONIC_FORMAT_CACHE_DIR};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tectonic::config::PersistentConfig;
+use tectonic::driver::{OutputFormat, ProcessingSessionBuilder};
+use tectonic::status::NoopStatusBackend;
+
+/// Ověří, že je bundle nainstalovaný a dá se otevřít. Volá se při startu,
+/// aby chybějící bundle nebyl poznat až na prvním reportu.
+pub fn verify_bundle() -> Result<(), String> {
+    if !Path::new(TECTONIC_BUNDLE).exists() {
+        return Err(format!(
+            "tectonic bundle {TECTONIC_BUNDLE} není nainstalovaný"
+        ));
+    }
+    let config = PersistentConfig::open(false).map_err(|e| format!("tectonic config: {e}"))?;
+    config
+        .make_local_file_provider(
+            PathBuf::from(TECTONIC_BUNDLE),
+            &mut NoopStatusBackend::default(),
+        )
+        .map(|_| ())
+        .map_err(|e| format!("tectonic bundle {TECTONIC_BUNDLE} nelze otevřít: {e}"))
+}
+
+/// Přeloží LaTeX zdroj a vrátí obsah PDF. `resource_dir` je složka, ze které
+/// si šablona načítá obrázky (`\includegraphics`).
+pub fn latex_to_pdf(latex: &str, resource_dir: Option<&Path>) -> Result<Vec<u8>, String> {
+    let mut status = NoopStatusBackend::default();
+    let config = PersistentConfig::open(false).map_err(|e| format!("tectonic config: {e}"))?;
+    let bundle = config
+        .make_local_file_provider(PathBuf::from(TECTONIC_BUNDLE), &mut status)
+        .map_err(|e| format!("tectonic bundle {TECTONIC_BUNDLE} nelze otevřít: {e}"))?;
+    fs::create_dir_all(TECTONIC_FORMAT_CACHE_DIR)
+        .map_err(|e| format!("tectonic format cache {TECTONIC_FORMAT_CACHE_DIR}: {e}"))?;
+
+    let mut sb = ProcessingSessionBuilder::default();
+    sb.bundle(bundle)
+        .primary_input_buffer(latex.as_bytes())
+        .tex_input_name("report.tex")
+        .format_name("latex")
+        .format_cache_path(TECTONIC_FORMAT_CACHE_DIR)
+        .keep_logs(false)
+        .keep_intermediates(false)
+        .print_stdout(false)
+        .output_format(OutputFormat::Pdf)
+        .do_not_write_output_files();
+    if let Some(dir) = resource_dir {
+        sb.filesystem_root(dir);
+    }
+
+    let mut session = sb
+        .create(&mut status)
+        .map_err(|e| format!("tectonic session: {e}"))?;
+    session
+        .run(&mut status)
+        .map_err(|e| format!("Překlad LaTeXu selhal: {e}"))?;
+
+    session
+        .into_file_data()
+        .remove("report.pdf")
+        .map(|f| f.data)
+        .ok_or_else(|| "tectonic nevytvořil report.pdf".to_string())
+}
+
+/// Pořadí pracovní složky v rámci procesu aplikace.
+static WORK_DIR_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// Spustí `f` s prázdnou dočasnou pracovní složkou (obrázky pro šablonu)
+/// a složku po dokončení smaže. Každé volání dostane vlastní složku
+/// (`/tmp/cratec_<name>_<pid>_<čas>_<pořadí>`), souběžné běhy se nepřepisují.
+pub fn with_work_dir<T>(
+    name: &str,
+    f: impl FnOnce(&Path) -> Result<T, String>,
+) -> Result<T, String> {
+    let dir = PathBuf::from(format!(
+        "/tmp/cratec_{name}_{}_{}_{}",
+        std::process::id(),
+        chrono::Utc::now().format("%Y%m%d%H%M%S%f"),
+        WORK_DIR_SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::create_dir(&dir)
+        .map_err(|e| format!("Pracovní složku {} nelze vytvořit: {e}", dir.display()))?;
+    let result = f(&dir);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
@@ -0,0 +1,141 @@
+//! Certifikát o smazání cílového disku (PDF).
+//!
+//! Vzniká po každém mazání (i neúspěšném) a ukládá se do
+//! `wipe_job.certificate_pdf`; `export_wipe_certificate` jej zapíše na
+//! připojený výstup.
+use super::{copy_to_dirs, latex_escape, tex, validate_output_mount};
+use crate::system_info::get_report_system_info;
+use crate::wipe::{self, WipeJob};
+use std::fs;
+use tera::{Context, Tera};
+
+static TEMPLATE_WIPE: &str = include_str!("./templates/wipe_certificate.tex");
+
+/// Popis metody a kategorie podle NIST SP 800-88.
+fn method_text(job: &WipeJob) -> (String, &'static str) {
+    match job.method.as_str() {
+        "sanitize" => ("Firmware sanitize (device internal erase)".into(), "Purge"),
+        "pattern" => (
+            format!("Single-pass overwrite with pattern 0x{}", job.pattern),
+            "Clear",
+        ),
+        _ => ("Single-pass overwrite with zeros".into(), "Clear"),
+    }
+}
+
+fn result_text(job: &WipeJob) -> String {
+    match (job.status.as_str(), job.verify) {
+        ("done", _) if job.method == "sanitize" => {
+            "COMPLETED -- device reported successful sanitize".into()
+        }
+        ("done", true) => "PASSED -- media overwritten and verified".into(),
+        ("done", false) => "COMPLETED -- media overwritten, not verified".into(),
+        ("running", _) => "IN PROGRESS".into(),
+        _ => "FAILED -- media must not be considered sanitized".into(),
+    }
+}
+
+/// Vyrenderuje certifikát mazání jako PDF.
+pub fn render_wipe_certificate(job: &WipeJob) -> Result<Vec<u8>, String> {
+    let unit = get_report_system_info().ok();
+    let (method, category) = method_text(job);
+    let verification = if job.method == "sanitize" {
+        "not applicable (post-sanitize content is vendor-defined)"
+    } else if !job.verify {
+        "not requested"
+    } else if job.mismatch_offset.is_some() {
+        "FAILED"
+    } else if job.status == "done" {
+        "passed (full read-back)"
+    } else {
+        "not completed"
+    };
+
+    let mut ctx = Context::new();
+    ctx.insert(
+        "software_hash",
+        unit.as_ref()
+            .map(|u| u.cratec_hash.as_str())
+            .unwrap_or("N/A"),
+    );
+    ctx.insert(
+        "build_date",
+        unit.as_ref()
+            .map(|u| u.build_date.as_str())
+            .unwrap_or("N/A"),
+    );
+    ctx.insert(
+        "unit_serial",
+        unit.as_ref()
+            .map(|u| u.short_hw_id.as_str())
+            .unwrap_or("N/A"),
+    );
+    ctx.insert("job_id", &job.id);
+    ctx.insert("bay_name", &latex_escape(&job.bay_name));
+    ctx.insert("interface", &latex_escape(&job.interface_path));
+    ctx.insert("device", &latex_escape(&job.device));
+    ctx.insert(
+        "model",
+        &latex_escape(job.model.as_deref().unwrap_or("N/A")),
+    );
+    ctx.insert(
+        "serial",
+        &latex_escape(job.serial.as_deref().unwrap_or("N/A")),
+    );
+    ctx.insert("size_bytes", &job.size_bytes);
+    ctx.insert(
+        "size_human",
+        &format!("{:.1} GB", job.size_bytes as f64 / 1e9),
+    );
+    ctx.insert("sector_size", &job.sector_size);
+    ctx.insert("method", &latex_escape(&method));
+    ctx.insert("category", category);
+    ctx.insert(
+        "sanitize_command",
+        &job.sanitize_command.as_deref().map(latex_escape),
+    );
+    ctx.insert("pattern", &format!("0x{}", job.pattern));
+    ctx.insert("bytes_written", &job.bytes_written);
+    ctx.insert("verification", verification);
+    ctx.insert("bytes_verified", &job.bytes_verified);
+    ctx.insert("mismatch_offset", &job.mismatch_offset);
+    ctx.insert("start_datetime", &job.start_datetime);
+    ctx.insert("end_datetime", job.end_datetime.as_deref().unwrap_or("N/A"));
+    ctx.insert("result", &result_text(job));
+    ctx.insert("error", &job.error.as_deref().map(latex_escape));
+    ctx.insert("operator", &latex_escape(&job.operator));
+
+    let latex = Tera::one_off(TEMPLATE_WIPE, &ctx, false)
+        .map_err(|e| format!("Render certifikátu selhal: {e}"))?;
+    tex::latex_to_pdf(&latex, None)
+}
+
+/// Uloží certifikát mazání `wipe_id` do složky `wipe_certificates` na
+/// připojeném výstupu `target`. Vrací cestu zapsaného souboru.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_wipe_certificate(wipe_id: i64, target: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || -> Result<String, String> {
+        let mount = validate_output_mount(&target)?;
+        let (job, pdf) = wipe::certificate(wipe_id)?;
+        let serial = job
+            .serial
+            .as_deref()
+            .unwrap_or("unknown")
+            .replace(|c: char| !c.is_ascii_alphanumeric() && c != '-', "_");
+        let file_name = format!("wipe-certificate-{wipe_id}-{serial}.pdf");
+        let tmp_path = format!("/tmp/wipe_certificate_{wipe_id}.pdf");
+        fs::write(&tmp_path, pdf).map_err(|e| e.to_string())?;
+        let written = copy_to_dirs(
+            &[format!("{mount}/wipe_certificates/")],
+            &tmp_path,
+            &file_name,
+        );
+        let _ = fs::remove_file(&tmp_path);
+        written
+            .map_err(|e| e.to_string())?
+            .pop()
+            .ok_or_else(|| "Certifikát nebyl zapsán".to_string())
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -0,0 +1,359 @@
+//! Retence dat případů v databázi jednotky.
+//!
+//! Pravidla (sekce `[retention]` v nastavení jednotky) se uplatní při startu aplikace,
+//! případně ručně administrátorem. Smazání celého případu je jen
+//! administrátorská akce. Po každém mazání se databáze zkompaktuje se
+//! `secure_delete`, aby uvolněné stránky neobsahovaly původní data, a do
+//! auditní stopy (`logs`, úroveň AUDIT) se zapíše záznam o rozsahu mazání.
+//!
+//! Smazání případu zasáhne i lokální zálohy databáze (před migrací a před
+//! obnovou ze zálohy) a nezpracované spool soubory výpisů jeho procesů.
+use crate::config::database_path;
+use crate::db::create_new_connection;
+use crate::db::log_writer::spool_path;
+use crate::lockscreen::require_admin;
+use crate::logger::write_audit;
+use crate::settings::settings;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Serialize)]
+pub struct RetentionPolicy {
+    pub process_log_days: Option<u32>,
+    pub hashes_only_days: Option<u32>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RetentionResult {
+    /// Smazané řádky výpisů procesů
+    pub log_lines_deleted: usize,
+    /// Procesy, ze kterých zůstaly jen identifikátory a hashe
+    pub processes_redacted: usize,
+    pub reports_deleted: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CasePurgeResult {
+    pub case_number: String,
+    pub processes_deleted: usize,
+    pub copy_logs_deleted: usize,
+    pub log_lines_deleted: usize,
+    pub reports_deleted: usize,
+    /// Lokální zálohy DB, ze kterých byl případ odstraněn
+    pub backups_purged: Vec<String>,
+    /// Lokální zálohy DB, ze kterých případ odstranit nešlo, a proto byly smazány
+    pub backups_deleted: Vec<String>,
+    /// Smazané spool soubory výpisů procesů případu
+    pub spool_files_deleted: usize,
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    e.to_string()
+}
+
+fn has_table(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?1)",
+        [name],
+        |r| r.get(0),
+    )
+}
+
+/// Přepíše uvolněné stránky a zmenší databázi i WAL.
+/// VACUUM nesmí běžet v transakci – volá se až po commitu mazání.
+fn secure_compact(conn: &Connection) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "secure_delete", "ON")?;
+    if has_table(conn, "history_fts")? {
+        // FTS5 jinak drží smazané tokeny ve starých segmentech indexu
+        conn.execute(
+            "INSERT INTO history_fts(history_fts) VALUES('optimize')",
+            [],
+        )?;
+    }
+    conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+}
+
+/// Podmínka na `copy_process` pro dokončené procesy starší než `?1` dní.
+const OLDER_THAN: &str =
+    "status <> 'running' AND start_datetime < datetime('now', '-' || ?1 || ' days')";
+
+fn apply_policy(conn: &mut Connection) -> rusqlite::Result<RetentionResult> {
+    let rules = &settings().retention;
+    let mut result = RetentionResult::default();
+    let tx = conn.transaction()?;
+
+    if let Some(days) = rules.hashes_only_days {
+        let old = format!("SELECT id FROM copy_process WHERE {OLDER_THAN}");
+        result.reports_deleted += tx.execute(
+            &format!("DELETE FROM report_archive WHERE process_id IN ({old})"),
+            params![days],
+        )?;
+        result.log_lines_deleted += tx.execute(
+            &format!("DELETE FROM process_log_lines WHERE process_id IN ({old})"),
+            params![days],
+        )?;
+        for (table, fk) in [
+            ("copy_log_ewf", "triggered_by_ewf"),
+            ("copy_log_dd", "triggered_by_dd"),
+        ] {
+            result.processes_redacted += tx.execute(
+                &format!(
+                    "UPDATE {table} SET investigator_name = '', description = '', notes = NULL, source = ''
+                     WHERE id IN (SELECT {fk} FROM copy_process WHERE {OLDER_THAN})
+                       AND (investigator_name <> '' OR description <> '' OR notes IS NOT NULL OR source <> '')"
+                ),
+                params![days],
+            )?;
+        }
+        tx.execute(
+            &format!(
+                "UPDATE copy_process SET command_line = NULL, error_log_path = NULL
+                 WHERE {OLDER_THAN} AND (command_line IS NOT NULL OR error_log_path IS NOT NULL)"
+            ),
+            params![days],
+        )?;
+        if has_table(&tx, "history_fts")? {
+            tx.execute(
+                &format!(
+                    "UPDATE history_fts SET investigator_name = '', description = '', notes = ''
+                     WHERE rowid IN ({old}) AND (investigator_name <> '' OR description <> '' OR notes <> '')"
+                ),
+                params![days],
+            )?;
+        }
+    }
+
+    if let Some(days) = rules.process_log_days {
+        result.log_lines_deleted += tx.execute(
+            &format!(
+                "DELETE FROM process_log_lines
+                 WHERE process_id IN (SELECT id FROM copy_process WHERE {OLDER_THAN})"
+            ),
+            params![days],
+        )?;
+    }
+
+    let changed = result.log_lines_deleted + result.processes_redacted + result.reports_deleted;
+    if changed > 0 {
+        write_audit(
+            &tx,
+            &format!(
+                "Retence: smazáno {} řádků výpisů a {} archivovaných reportů, redukováno {} záznamů na hashe (pravidla: výpisy {:?} dní, jen hashe {:?} dní)",
+                result.log_lines_deleted,
+                result.reports_deleted,
+                result.processes_redacted,
+                rules.process_log_days,
+                rules.hashes_only_days
+            ),
+        )?;
+    }
+    tx.commit()?;
+
+    if changed > 0 {
+        secure_compact(conn)?;
+    }
+    Ok(result)
+}
+
+/// Uplatní pravidla retence. Volá se při startu aplikace po inicializaci DB.
+pub fn apply_retention_policy() -> Result<RetentionResult, String> {
+    let rules = &settings().retention;
+    if rules.process_log_days.is_none() && rules.hashes_only_days.is_none() {
+        return Ok(RetentionResult::default());
+    }
+    let mut conn = create_new_connection().map_err(|e| e.to_string())?;
+    apply_policy(&mut conn).map_err(db_err)
+}
+
+/// Procesy případu `?1`.
+const CASE_PROCESSES: &str = "SELECT p.id FROM copy_process p
+                              LEFT JOIN copy_log_ewf e ON e.id = p.triggered_by_ewf
+                              LEFT JOIN copy_log_dd d ON d.id = p.triggered_by_dd
+                              WHERE COALESCE(e.case_number, d.case_number) = ?1";
+
+/// Smaže řádky případu. Tabulky, které starší schéma (záloha před migrací)
+/// ještě nemá, přeskočí. Vrací počty (procesy, záznamy akvizice, řádky
+/// výpisů, archivované reporty).
+fn delete_case_rows(
+    conn: &Connection,
+    case_number: &str,
+) -> rusqlite::Result<(usize, usize, usize, usize)> {
+    let delete = |sql: String| conn.execute(&sql, params![case_number]);
+    let mut reports_deleted = 0;
+    if has_table(conn, "report_archive")? {
+        reports_deleted = delete(format!(
+            "DELETE FROM report_archive WHERE process_id IN ({CASE_PROCESSES})"
+        ))?;
+    }
+    let mut log_lines_deleted = 0;
+    if has_table(conn, "process_log_lines")? {
+        log_lines_deleted = delete(format!(
+            "DELETE FROM process_log_lines WHERE process_id IN ({CASE_PROCESSES})"
+        ))?;
+    }
+    let processes_deleted = delete(format!(
+        "DELETE FROM copy_process WHERE id IN ({CASE_PROCESSES})"
+    ))?;
+    let copy_logs_deleted = delete("DELETE FROM copy_log_ewf WHERE case_number = ?1".into())?
+        + delete("DELETE FROM copy_log_dd WHERE case_number = ?1".into())?;
+    Ok((
+        processes_deleted,
+        copy_logs_deleted,
+        log_lines_deleted,
+        reports_deleted,
+    ))
+}
+
+/// Lokální zálohy databáze: `{db}.pre-v*-*.bak` (před migrací) a
+/// `{db}.pre-restore-*.bak` (před obnovou ze zálohy).
+fn local_backups() -> Vec<PathBuf> {
+    let db = Path::new(database_path());
+    let (Some(dir), Some(name)) = (db.parent(), db.file_name().and_then(|n| n.to_str())) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix(name))
+                .is_some_and(|rest| {
+                    (rest.starts_with(".pre-v") || rest.starts_with(".pre-restore-"))
+                        && rest.ends_with(".bak")
+                })
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+fn purge_backup(path: &Path, case_number: &str) -> rusqlite::Result<()> {
+    let mut conn = Connection::open(path)?;
+    let tx = conn.transaction()?;
+    delete_case_rows(&tx, case_number)?;
+    tx.commit()?;
+    secure_compact(&conn)
+}
+
+fn purge_case_rows(conn: &mut Connection, case_number: &str) -> Result<CasePurgeResult, String> {
+    let tx = conn.transaction().map_err(db_err)?;
+
+    let (found, running): (i64, i64) = tx
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), COALESCE(SUM(status = 'running'), 0) FROM copy_process
+                 WHERE id IN ({CASE_PROCESSES})"
+            ),
+            params![case_number],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .map_err(db_err)?;
+    if found == 0 {
+        return Err(format!("Případ {case_number} neexistuje"));
+    }
+    if running > 0 {
+        return Err(format!("Případ {case_number} má běžící akvizici"));
+    }
+
+    // nezpracované výpisy procesů případu (po pádu aplikace)
+    let process_ids: Vec<i64> = {
+        let mut stmt = tx.prepare(CASE_PROCESSES).map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![case_number], |r| r.get(0))
+            .map_err(db_err)?;
+        rows.collect::<Result<_, _>>().map_err(db_err)?
+    };
+    let spool_files_deleted = process_ids
+        .iter()
+        .filter(|id| fs::remove_file(spool_path(**id)).is_ok())
+        .count();
+
+    // zálohy obsahují celou databázi; co nejde vyčistit, se smaže
+    let mut backups_purged = Vec::new();
+    let mut backups_deleted = Vec::new();
+    for backup in local_backups() {
+        let name = backup.to_string_lossy().to_string();
+        match purge_backup(&backup, case_number) {
+            Ok(()) => backups_purged.push(name),
+            Err(e) => {
+                eprintln!("Purge of case from {name} failed ({e}), deleting the backup");
+                fs::remove_file(&backup).map_err(|e| {
+                    format!("Zálohu {name} s daty případu nelze vyčistit ani smazat: {e}")
+                })?;
+                backups_deleted.push(name);
+            }
+        }
+    }
+
+    let (processes_deleted, copy_logs_deleted, log_lines_deleted, reports_deleted) =
+        delete_case_rows(&tx, case_number).map_err(db_err)?;
+
+    write_audit(
+        &tx,
+        &format!(
+            "Případ {case_number} smazán administrátorem: {processes_deleted} procesů, {copy_logs_deleted} záznamů akvizice, {log_lines_deleted} řádků výpisů, {reports_deleted} archivovaných reportů, {spool_files_deleted} spool souborů; vyčištěné zálohy DB: [{}], smazané zálohy DB: [{}]",
+            backups_purged.join(", "),
+            backups_deleted.join(", ")
+        ),
+    )
+    .map_err(db_err)?;
+    tx.commit().map_err(db_err)?;
+    secure_compact(conn).map_err(db_err)?;
+
+    Ok(CasePurgeResult {
+        case_number: case_number.to_string(),
+        processes_deleted,
+        copy_logs_deleted,
+        log_lines_deleted,
+        reports_deleted,
+        backups_purged,
+        backups_deleted,
+        spool_files_deleted,
+    })
+}
+
+/// Aktuální pravidla retence.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_retention_policy() -> Result<RetentionPolicy, String> {
+    let rules = &settings().retention;
+    Ok(RetentionPolicy {
+        process_log_days: rules.process_log_days,
+        hashes_only_days: rules.hashes_only_days,
+    })
+}
+
+/// Ručně uplatní pravidla retence (administrátor).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn run_retention_policy(admin_code: String) -> Result<RetentionResult, String> {
+    require_admin(&admin_code)?;
+    tauri::async_runtime::spawn_blocking(apply_retention_policy)
+        .await
+        .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Smaže všechna data případu (procesy, záznamy akvizice, výpisy, reporty)
+/// včetně lokálních záloh DB a spool souborů a přepíše uvolněné místo
+/// v databázi. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn purge_case(
+    case_number: String,
+    admin_code: String,
+) -> Result<CasePurgeResult, String> {
+    require_admin(&admin_code)?;
+    let case_number = case_number.trim().to_string();
+    if case_number.is_empty() {
+        return Err("Číslo případu je prázdné".into());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut conn = create_new_connection().map_err(|e| e.to_string())?;
+        purge_case_rows(&mut conn, &case_number)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
@@ -0,0 +1,398 @@
+//! Systémové nastavení jednotky v `SETTINGS_PATH` (`/etc/cratec/cratec.toml`).
+//!
+//! Soubor obsahuje jen to, co se liší mezi hardwarovými sestavami. Chybějící
+//! klíče mají výchozí hodnoty, neznámé klíče jsou chyba. Nastavení se načte
+//! jednou při startu (`init`); změna přes `update_settings` se projeví po
+//! restartu aplikace.
+//!
+//! ```toml
+//! [database]
+//! path = "/var/lib/cratec/database.db"
+//!
+//! [storage]
+//! mount_root = "/mnt/cratec"
+//!
+//! [led]
+//! port = "/dev/ttyS0"
+//! baud_rate = 9600
+//!
+//! [websocket]
+//! port_min = 8080
+//! port_max = 8100
+//!
+//! [report]
+//! template_dir = "/etc/cratec/templates"
+//! log_appendix = false
+//!
+//! [retention]
+//! process_log_days = 90
+//! hashes_only_days = 365
+//!
+//! [[bays]]
+//! path = "pci-0000:03:00.0-ata-1"
+//! side = "input"
+//! name = "IN 1"
+//! ```
+use crate::config::SETTINGS_PATH;
+use crate::copy_configs::validation::{ConfigError, FieldError};
+use crate::db::DB_POOL;
+use crate::lockscreen::require_admin;
+use crate::logger::write_audit;
+use crate::report::template::sudo;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+static SETTINGS: OnceCell<Settings> = OnceCell::new();
+
+const BAUD_RATES: &[u32] = &[1200, 2400, 4800, 9600, 19200, 38400, 57600, 115200];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseSettings {
+    pub path: String,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            path: "/var/lib/cratec/database.db".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Kořen, pod který se připojují výstupní disky
+    pub mount_root: String,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            mount_root: "/mnt/cratec".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LedSettings {
+    pub port: String,
+    pub baud_rate: u32,
+}
+
+impl Default for LedSettings {
+    fn default() -> Self {
+        Self {
+            port: "/dev/ttyS0".into(),
+            baud_rate: 9600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebsocketSettings {
+    pub port_min: u16,
+    pub port_max: u16,
+}
+
+impl Default for WebsocketSettings {
+    fn default() -> Self {
+        Self {
+            port_min: 8080,
+            port_max: 8100,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportSettings {
+    /// Složka s operátorskými šablonami reportu (`en_ewf.tex`, `en_dd.tex`, loga)
+    pub template_dir: String,
+    /// Příloha reportu s kompletním výstupem nástroje, příkazem a chybovým logem
+    pub log_appendix: bool,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            template_dir: "/etc/cratec/templates".into(),
+            log_appendix: false,
+        }
+    }
+}
+
+/// Pravidla retence dat případů (viz `retention`). Chybějící klíč = pravidlo
+/// se neuplatní.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionSettings {
+    /// Po kolika dnech od akvizice se mažou výpisy procesů (`process_log_lines`)
+    pub process_log_days: Option<u32>,
+    /// Po kolika dnech od akvizice se z historie ponechá jen číslo případu/důkazu,
+    /// časy a hashe (jména, popisy, poznámky, výpisy a archivované reporty se smažou)
+    pub hashes_only_days: Option<u32>,
+}
+
+/// Šachta (port) jednotky, viz tabulka `interface`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BaySettings {
+    /// Název v `/dev/disk/by-path`
+    pub path: String,
+    /// input | output
+    pub side: String,
+    pub name: String,
+}
+
+fn bay(path: &str, side: &str, name: &str) -> BaySettings {
+    BaySettings {
+        path: path.into(),
+        side: side.into(),
+        name: name.into(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub database: DatabaseSettings,
+    pub storage: StorageSettings,
+    pub led: LedSettings,
+    pub websocket: WebsocketSettings,
+    pub report: ReportSettings,
+    pub retention: RetentionSettings,
+    pub bays: Vec<BaySettings>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            database: DatabaseSettings::default(),
+            storage: StorageSettings::default(),
+            led: LedSettings::default(),
+            websocket: WebsocketSettings::default(),
+            report: ReportSettings::default(),
+            retention: RetentionSettings::default(),
+            bays: vec![
+                bay("pci-0000:03:00.0-ata-1", "input", "IN 1"),
+                bay("pci-0000:03:00.0-ata-2", "input", "IN 2"),
+                bay("pci-0000:03:00.0-ata-3", "output", "OUT 1"),
+                bay("pci-0000:03:00.0-ata-4", "output", "OUT 2"),
+                bay(
+                    "pci-0000:00:14.0-usb-0:1:1.0-scsi-0:0:0:0",
+                    "input",
+                    "USB IN",
+                ),
+                bay(
+                    "pci-0000:00:14.0-usb-0:2:1.0-scsi-0:0:0:0",
+                    "output",
+                    "USB OUT",
+                ),
+            ],
+        }
+    }
+}
+
+fn error(field: &str, message: &str) -> FieldError {
+    FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        let absolute = |value: &str| value.starts_with('/') && value.len() > 1;
+
+        if !absolute(&self.database.path) {
+            errors.push(error("database.path", "Musí být absolutní cesta k souboru"));
+        }
+        if !absolute(&self.storage.mount_root) {
+            errors.push(error(
+                "storage.mount_root",
+                "Musí být absolutní cesta (ne /)",
+            ));
+        }
+        if !self.led.port.starts_with("/dev/") {
+            errors.push(error("led.port", "Musí být zařízení v /dev"));
+        }
+        if !BAUD_RATES.contains(&self.led.baud_rate) {
+            errors.push(error("led.baud_rate", "Nepodporovaná rychlost portu"));
+        }
+        if self.websocket.port_min < 1024 {
+            errors.push(error("websocket.port_min", "Port musí být alespoň 1024"));
+        }
+        if self.websocket.port_min > self.websocket.port_max {
+            errors.push(error(
+                "websocket.port_max",
+                "Konec rozsahu je menší než začátek",
+            ));
+        }
+
+        if !absolute(&self.report.template_dir) {
+            errors.push(error(
+                "report.template_dir",
+                "Musí být absolutní cesta (ne /)",
+            ));
+        }
+
+        for (field, days) in [
+            (
+                "retention.process_log_days",
+                self.retention.process_log_days,
+            ),
+            (
+                "retention.hashes_only_days",
+                self.retention.hashes_only_days,
+            ),
+        ] {
+            if days == Some(0) {
+                errors.push(error(field, "Musí být alespoň 1 den"));
+            }
+        }
+
+        let mut paths = HashSet::new();
+        let mut names = HashSet::new();
+        for (i, bay) in self.bays.iter().enumerate() {
+            let field = |key: &str| format!("bays[{i}].{key}");
+            if bay.path.trim().is_empty() || bay.path.contains('/') {
+                errors.push(error(&field("path"), "Musí být název z /dev/disk/by-path"));
+            } else if !paths.insert(bay.path.as_str()) {
+                errors.push(error(&field("path"), "Šachta je uvedená dvakrát"));
+            }
+            if !["input", "output"].contains(&bay.side.as_str()) {
+                errors.push(error(&field("side"), "Musí být input nebo output"));
+            }
+            if bay.name.trim().is_empty() {
+                errors.push(error(&field("name"), "Název nesmí být prázdný"));
+            } else if !names.insert(bay.name.as_str()) {
+                errors.push(error(&field("name"), "Název je použitý dvakrát"));
+            }
+        }
+        for side in ["input", "output"] {
+            if !self.bays.iter().any(|b| b.side == side) {
+                errors.push(error("bays", &format!("Chybí šachta typu {side}")));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Přečte a ověří nastavení ze souboru; bez souboru platí výchozí hodnoty.
+pub fn read_settings(path: &str) -> Result<Settings, ConfigError> {
+    let settings = match fs::read_to_string(path) {
+        Ok(text) => toml::from_str::<Settings>(&text)
+            .map_err(|e| ConfigError::from(format!("{path}: {e}")))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Settings::default(),
+        Err(e) => return Err(format!("{path}: {e}").into()),
+    };
+    settings.validate()?;
+    Ok(settings)
+}
+
+/// Načte nastavení při startu. Neplatný soubor zastaví start aplikace –
+/// výchozí hodnoty by na jiné sestavě ukazovaly na špatný hardware.
+pub fn init() -> Result<&'static Settings, String> {
+    let settings = read_settings(SETTINGS_PATH).map_err(|e| e.message)?;
+    Ok(SETTINGS.get_or_init(|| settings))
+}
+
+/// Nastavení platné pro běžící aplikaci.
+pub fn settings() -> &'static Settings {
+    SETTINGS.get_or_init(Settings::default)
+}
+
+#[derive(Debug, Serialize)]
+pub struct SettingsInfo {
+    pub path: String,
+    /// Nastavení, se kterým aplikace běží
+    pub active: Settings,
+    /// Nastavení v souboru (po `update_settings` před restartem se liší)
+    pub saved: Settings,
+    pub restart_required: bool,
+}
+
+fn settings_info() -> Result<SettingsInfo, ConfigError> {
+    let active = settings().clone();
+    let saved = read_settings(SETTINGS_PATH)?;
+    Ok(SettingsInfo {
+        path: SETTINGS_PATH.to_string(),
+        restart_required: saved != active,
+        active,
+        saved,
+    })
+}
+
+/// Názvy sekcí, ve kterých se `new` liší od `old` (pro auditní záznam).
+fn changed_sections(old: &Settings, new: &Settings) -> Vec<&'static str> {
+    [
+        ("database", old.database != new.database),
+        ("storage", old.storage != new.storage),
+        ("led", old.led != new.led),
+        ("websocket", old.websocket != new.websocket),
+        ("report", old.report != new.report),
+        ("retention", old.retention != new.retention),
+        ("bays", old.bays != new.bays),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(name, _)| name)
+    .collect()
+}
+
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_settings() -> Result<SettingsInfo, ConfigError> {
+    tauri::async_runtime::spawn_blocking(settings_info)
+        .await
+        .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Ověří a uloží nastavení do `SETTINGS_PATH`. Projeví se po restartu. Jen pro administrátora.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn update_settings(
+    settings: Settings,
+    admin_code: String,
+) -> Result<SettingsInfo, ConfigError> {
+    require_admin(&admin_code)?;
+    settings.validate()?;
+    tauri::async_runtime::spawn_blocking(move || -> Result<SettingsInfo, ConfigError> {
+        let previous = read_settings(SETTINGS_PATH).unwrap_or_default();
+        let text = toml::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+        let tmp_path = "/tmp/cratec.toml";
+        fs::write(tmp_path, text).map_err(|e| e.to_string())?;
+        let dir = Path::new(SETTINGS_PATH)
+            .parent()
+            .map(|d| d.to_string_lossy().to_string())
+            .unwrap_or_else(|| "/".into());
+        let copied =
+            sudo(&["mkdir", "-p", &dir]).and_then(|_| sudo(&["cp", tmp_path, SETTINGS_PATH]));
+        let _ = fs::remove_file(tmp_path);
+        copied?;
+
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        write_audit(
+            pooled.connection(),
+            &format!(
+                "Nastavení jednotky uloženo do {SETTINGS_PATH}, změněné sekce: {:?}",
+                changed_sections(&previous, &settings)
+            ),
+        )
+        .map_err(|e| e.to_string())?;
+        settings_info()
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
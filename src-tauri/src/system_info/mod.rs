@@ -1,6 +1,9 @@
 use serde::Serialize;
 use std::process::Command;
 use crate::db::DB_POOL; 
+use crate::db::backup::{last_integrity_report, IntegrityReport};
+use crate::db::migrations;
+use crate::config::database_path;
 use hex;                                
 use base64::engine::general_purpose::STANDARD_NO_PAD;
 use base64::Engine;                     
@@ -141,4 +144,29 @@ pub fn get_report_system_info() -> Result<ReportSystemInfo, String> {
         cratec_hash: get_cratec_md5()?,
         short_hw_id: get_short_hw_id()?,
     })
-}
\ No newline at end of file
+}
+
+
+#[derive(Serialize)]
+pub struct DatabaseInfo {
+    pub path: String,
+    pub size_bytes: u64,
+    pub schema_version: i64,
+    /// Poslední kontrola integrity (při startu nebo ručně)
+    pub integrity: Option<IntegrityReport>,
+}
+
+#[tauri::command]
+pub fn get_database_info() -> Result<DatabaseInfo, String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| format!("DB pool error: {}", e))?;
+    let schema_version = migrations::db_version(pooled.connection())
+        .map_err(|e| format!("DB query error: {}", e))?;
+    let size_bytes = std::fs::metadata(database_path()).map(|m| m.len()).unwrap_or(0);
+
+    Ok(DatabaseInfo {
+        path: database_path().to_string(),
+        size_bytes,
+        schema_version,
+        integrity: last_integrity_report(),
+    })
+}
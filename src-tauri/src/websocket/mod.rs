@@ -1,12 +1,14 @@
 use once_cell::sync::Lazy;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tokio::sync::Mutex;
 use tokio_tungstenite::{accept_async, tungstenite::Message};
 use futures_util::SinkExt;
 use tokio::time::{sleep, Duration};
-use crate::dashboard_layout::get_device_status;
-use serde_json::json;
+use crate::dashboard_layout::{monitor, system_usage, DeviceUpdate};
+
+static USAGE_LOOP_STARTED: AtomicBool = AtomicBool::new(false);
 
 // Globální seznam klientů chráněný asynchroním mutexem
 static CLIENTS: Lazy<Arc<Mutex<Vec<tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>>>>> =
@@ -29,22 +31,27 @@ async fn find_available_port(start: u16, end: u16) -> Result<u16, String> {
             }
         }
     }
-    Err("Žádný port není dostupný v rozsahu 8080-8100".into())
+    Err(format!("Žádný port není dostupný v rozsahu {}-{}", start, end))
 }
 
-/// Spustí websocket server. Najde volný port a spustí accept a broadcast smyčky.
+/// Spustí websocket server. Najde volný port, spustí sledování disků a accept smyčku.
+/// Rozsah portů je v nastavení (`[websocket]`).
 /// Vrátí adresu websocketu (např. "ws://127.0.0.1:8080").
 #[tauri::command]
 pub async fn start_websocket_server() -> Result<String, String> {
     log::debug!("Spouštím start_websocket_server...");
-    let port = find_available_port(8080, 8100).await?;
+    let ports = &crate::settings::settings().websocket;
+    let port = find_available_port(ports.port_min, ports.port_max).await?;
     let addr = format!("127.0.0.1:{}", port);
     let clients = CLIENTS.clone();
-    
+
+    if !USAGE_LOOP_STARTED.swap(true, Ordering::SeqCst) {
+        tokio::spawn(usage_loop());
+    }
+    // Registr disků musí být naplněný dřív, než se připojí první klient
+    monitor::start().await?;
     // Spustíme accept loop s dynamicky vybranou adresou
     tokio::spawn(accept_loop(addr.clone(), clients.clone()));
-    // Spustíme broadcast loop – každé 2 sekundy odešle full update
-    tokio::spawn(broadcast_loop(clients.clone()));
     
     log::debug!("Websocket server spuštěn na {}", addr);
     Ok(format!("ws://{}", addr))
@@ -59,9 +66,18 @@ async fn accept_loop(addr: String, clients: Arc<Mutex<Vec<tokio_tungstenite::Web
         let clients_clone = clients.clone();
         tokio::spawn(async move {
             match accept_async(stream).await {
-                Ok(ws_stream) => {
+                Ok(mut ws_stream) => {
                     log::debug!("Nové websocket spojení navázáno.");
-                    clients_clone.lock().await.push(ws_stream);
+                    // Nový klient dostane úplný stav, dál už jen změny. Zámek klientů
+                    // drží broadcast, takže mezi snímkem a zařazením se nic neztratí.
+                    let mut clients_lock = clients_clone.lock().await;
+                    let snapshot = DeviceUpdate::Full(monitor::snapshot());
+                    let json_str = serde_json::to_string(&snapshot).unwrap_or_default();
+                    if let Err(e) = ws_stream.send(Message::Text(json_str.into())).await {
+                        eprintln!("Error sending status snapshot: {}", e);
+                        return;
+                    }
+                    clients_lock.push(ws_stream);
                 }
                 Err(e) => {
                     eprintln!("WebSocket handshake failed: {}", e);
@@ -71,48 +87,17 @@ async fn accept_loop(addr: String, clients: Arc<Mutex<Vec<tokio_tungstenite::Web
     }
 }
 
-/// Broadcast loop: každé 2 sekundy získá aktuální stav (full update) a odešle ho všem klientům.
-async fn broadcast_loop(clients: Arc<Mutex<Vec<tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>>>>) {
+/// Každé 2 sekundy rozešle vytížení CPU a RAM; stav disků posílá `monitor` při změně.
+async fn usage_loop() {
     loop {
-        let current_status = match get_device_status() {
-            Ok(status) => status,
-            Err(e) => {
-                eprintln!("Error getting device status: {}", e);
-                sleep(Duration::from_secs(2)).await;
-                continue;
-            }
+        let (cpu_usage, ram_usage) = system_usage();
+        let update = DeviceUpdate::SystemUsage {
+            cpu_usage,
+            ram_usage,
         };
-
-        let status_update = json!({
-            "type": "Status",
-            "data": current_status
-        });
-        let json_str = status_update.to_string();
-
-        {
-            let mut clients_lock = clients.lock().await;
-            let mut indices_to_remove = Vec::new();
-
-            // Zkusíme odeslat všem připojeným klientům
-            for (i, client) in clients_lock.iter_mut().enumerate() {
-                match client.send(Message::Text(json_str.clone().into())).await {
-                    Ok(_) => {
-                      
-                    }
-                    Err(e) => {
-                        eprintln!("Error sending update to klient #{}: {}", i, e);
-                        indices_to_remove.push(i);
-                    }
-                }
-            }
-
-            for i in indices_to_remove.iter().rev() {
-                println!("Odstranění odpojeného klienta #{}", i);
-                clients_lock.remove(*i);
-            }
+        if let Ok(json_str) = serde_json::to_string(&update) {
+            broadcast_message(&json_str).await;
         }
-
-        // Po odeslání všem klientům počkáme 2 sekundy
         sleep(Duration::from_secs(2)).await;
     }
 }
@@ -0,0 +1,1018 @@
+//! Mazání cílových disků s ověřením a certifikátem.
+//!
+//! Mazat lze jen disk ve výstupní šachtě, na které právě nic neběží.
+//! Přepis vzorem i ověření jdou přes `sudo dd` (aplikace nemá přímý přístup
+//! k blokovým zařízením): vzor se zapisuje do stdin `dd`, při ověření se
+//! celý disk přečte zpět a porovná. Metoda `sanitize` použije interní mazání
+//! firmwaru (NVMe Sanitize / ATA SANITIZE), pokud jej disk podporuje; její
+//! výsledek se čtením neověřuje, obsah po mazání určuje výrobce.
+//!
+//! Průběh chodí websocketem stejně jako u akvizic (`ProcessFull` /
+//! `ProcessProgress` / `ProcessOutput` / `ProcessDone`) s ID `wipe-<id>`.
+//! Po skončení se vyrenderuje certifikát a uloží do `wipe_job`.
+use crate::copy_configs::validation::{ConfigError, FieldError};
+use crate::dashboard_layout::monitor::{self, MountHold};
+use crate::db::DB_POOL;
+use crate::disk_utils::writers::begin_eject;
+use crate::disk_utils::{
+    bay_device, disk_parts, get_lsblk_json, part_mounts, run_sudo, unmount_parts,
+};
+use crate::interfaces::{bay_by_interface, in_use};
+use crate::led::LED_CONTROLLER;
+use crate::lockscreen::require_admin;
+use crate::logger::{log_error, write_audit};
+use crate::report::sha256_hex;
+use crate::report::wipe_certificate::render_wipe_certificate;
+use crate::websocket::broadcast_message;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Velikost bloku pro zápis i čtení (odpovídá `bs=4M` u `dd`)
+const CHUNK: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WipeMethod {
+    Zero,
+    Pattern,
+    Sanitize,
+}
+
+impl WipeMethod {
+    fn as_str(self) -> &'static str {
+        match self {
+            WipeMethod::Zero => "zero",
+            WipeMethod::Pattern => "pattern",
+            WipeMethod::Sanitize => "sanitize",
+        }
+    }
+}
+
+/// Interní mazání, které disk podporuje.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizeKind {
+    NvmeBlockErase,
+    NvmeCryptoErase,
+    AtaBlockErase,
+    AtaCryptoScramble,
+}
+
+impl SanitizeKind {
+    fn command(self, device: &str) -> Vec<String> {
+        let args: &[&str] = match self {
+            SanitizeKind::NvmeBlockErase => &["nvme", "sanitize", device, "--sanact=2"],
+            SanitizeKind::NvmeCryptoErase => &["nvme", "sanitize", device, "--sanact=4"],
+            SanitizeKind::AtaBlockErase => &[
+                "hdparm",
+                "--yes-i-know-what-i-am-doing",
+                "--sanitize-block-erase",
+                device,
+            ],
+            SanitizeKind::AtaCryptoScramble => &[
+                "hdparm",
+                "--yes-i-know-what-i-am-doing",
+                "--sanitize-crypto-scramble",
+                device,
+            ],
+        };
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    /// Nejdelší doba, po kterou se čeká na dokončení interního mazání.
+    fn timeout(self) -> Duration {
+        match self {
+            SanitizeKind::NvmeCryptoErase | SanitizeKind::AtaCryptoScramble => {
+                Duration::from_secs(30 * 60)
+            }
+            SanitizeKind::NvmeBlockErase | SanitizeKind::AtaBlockErase => {
+                Duration::from_secs(12 * 60 * 60)
+            }
+        }
+    }
+
+    fn is_nvme(self) -> bool {
+        matches!(
+            self,
+            SanitizeKind::NvmeBlockErase | SanitizeKind::NvmeCryptoErase
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WipeParams {
+    pub interface: String,
+    pub method: WipeMethod,
+    /// Vzor pro metodu `pattern` v hex (1, 2, 4, 8 nebo 16 bajtů), např. `AA` nebo `DEADBEEF`
+    #[serde(default)]
+    pub pattern: Option<String>,
+    pub operator: String,
+    /// Sériové číslo opsané operátorem z disku – pojistka proti smazání jiného disku
+    pub confirm_serial: String,
+}
+
+/// Disk ve výstupní šachtě, jak jej vidí dialog mazání.
+#[derive(Debug, Serialize)]
+pub struct WipeTarget {
+    pub interface: String,
+    pub bay_name: String,
+    pub device: String,
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    pub size_bytes: u64,
+    pub sector_size: u64,
+    pub sanitize: Option<SanitizeKind>,
+    pub in_use: bool,
+}
+
+/// Záznam `wipe_job` (bez PDF certifikátu).
+#[derive(Debug, Clone, Serialize)]
+pub struct WipeJob {
+    pub id: i64,
+    pub interface_path: String,
+    pub bay_name: String,
+    pub device: String,
+    pub serial: Option<String>,
+    pub model: Option<String>,
+    pub size_bytes: i64,
+    pub sector_size: i64,
+    pub method: String,
+    pub sanitize_command: Option<String>,
+    /// Vzor v hex (`00` pro nuly i sanitize)
+    pub pattern: String,
+    pub verify: bool,
+    pub operator: String,
+    pub start_datetime: String,
+    pub end_datetime: Option<String>,
+    pub status: String,
+    pub bytes_written: i64,
+    pub bytes_verified: i64,
+    pub mismatch_offset: Option<i64>,
+    pub error: Option<String>,
+    pub certificate_sha256: Option<String>,
+}
+
+const JOB_COLUMNS: &str = "id, interface_path, bay_name, device, serial, model, size_bytes, \
+     sector_size, method, sanitize_command, pattern, verify, operator, start_datetime, \
+     end_datetime, status, bytes_written, bytes_verified, mismatch_offset, error, \
+     certificate_sha256";
+
+fn job_from_row(r: &Row) -> rusqlite::Result<WipeJob> {
+    Ok(WipeJob {
+        id: r.get(0)?,
+        interface_path: r.get(1)?,
+        bay_name: r.get(2)?,
+        device: r.get(3)?,
+        serial: r.get(4)?,
+        model: r.get(5)?,
+        size_bytes: r.get(6)?,
+        sector_size: r.get(7)?,
+        method: r.get(8)?,
+        sanitize_command: r.get(9)?,
+        pattern: r.get(10)?,
+        verify: r.get(11)?,
+        operator: r.get(12)?,
+        start_datetime: r.get(13)?,
+        end_datetime: r.get(14)?,
+        status: r.get(15)?,
+        bytes_written: r.get(16)?,
+        bytes_verified: r.get(17)?,
+        mismatch_offset: r.get(18)?,
+        error: r.get(19)?,
+        certificate_sha256: r.get(20)?,
+    })
+}
+
+fn load_job(conn: &Connection, id: i64) -> Result<WipeJob, String> {
+    conn.query_row(
+        &format!("SELECT {JOB_COLUMNS} FROM wipe_job WHERE id = ?1"),
+        [id],
+        job_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Mazání {} neexistuje", id))
+}
+
+fn field_error(field: &str, message: &str) -> ConfigError {
+    vec![FieldError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }]
+    .into()
+}
+
+/// Bajty vzoru pro danou metodu.
+fn parse_pattern(method: WipeMethod, pattern: Option<&str>) -> Result<Vec<u8>, ConfigError> {
+    if method != WipeMethod::Pattern {
+        return Ok(vec![0]);
+    }
+    let pattern = pattern.unwrap_or_default().trim();
+    let bytes = hex::decode(pattern)
+        .map_err(|_| field_error("pattern", "Vzor musí být zadaný v hex (např. AA nebo 55AA)"))?;
+    if ![1, 2, 4, 8, 16].contains(&bytes.len()) {
+        return Err(field_error(
+            "pattern",
+            "Vzor musí mít 1, 2, 4, 8 nebo 16 bajtů",
+        ));
+    }
+    Ok(bytes)
+}
+
+/// `pattern` opakovaný na délku `len`.
+fn pattern_buffer(pattern: &[u8], len: usize) -> Vec<u8> {
+    pattern.iter().copied().cycle().take(len).collect()
+}
+
+/// Najde v JSON výstupu `nvme-cli` první číselnou hodnotu klíče `key`.
+fn json_u64(value: &Value, key: &str) -> Option<u64> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .and_then(Value::as_u64)
+            .or_else(|| map.values().find_map(|v| json_u64(v, key))),
+        Value::Array(items) => items.iter().find_map(|v| json_u64(v, key)),
+        _ => None,
+    }
+}
+
+/// Zjistí, jestli disk umí interní mazání. Přednost má block erase.
+fn sanitize_support(device: &str) -> Option<SanitizeKind> {
+    if device.trim_start_matches("/dev/").starts_with("nvme") {
+        let out = run_sudo(&["nvme", "id-ctrl", device, "-o", "json"]).ok()?;
+        let sanicap = json_u64(&serde_json::from_str(&out).ok()?, "sanicap")?;
+        if sanicap & 0x2 != 0 {
+            Some(SanitizeKind::NvmeBlockErase)
+        } else if sanicap & 0x1 != 0 {
+            Some(SanitizeKind::NvmeCryptoErase)
+        } else {
+            None
+        }
+    } else {
+        // USB můstky ATA příkazy většinou nepropustí, hdparm pak selže
+        let out = run_sudo(&["hdparm", "-I", device]).ok()?;
+        if !out.contains("SANITIZE feature set") {
+            None
+        } else if out.contains("BLOCK_ERASE_EXT") {
+            Some(SanitizeKind::AtaBlockErase)
+        } else if out.contains("CRYPTO_SCRAMBLE_EXT") {
+            Some(SanitizeKind::AtaCryptoScramble)
+        } else {
+            None
+        }
+    }
+}
+
+fn probe(conn: &Connection, interface: &str) -> Result<WipeTarget, String> {
+    let bay = bay_by_interface(conn, interface)?;
+    if bay.side != "output" {
+        return Err(format!(
+            "Šachta '{}' je vstupní, smazat lze jen cílový disk",
+            bay.name
+        ));
+    }
+    let device = bay_device(interface)?;
+    let lsblk = get_lsblk_json(&device)?;
+    let disk = &lsblk["blockdevices"][0];
+    let text = |key: &str| {
+        disk[key]
+            .as_str()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    let size_bytes = disk["size"]
+        .as_u64()
+        .filter(|s| *s > 0)
+        .ok_or_else(|| format!("Nepodařilo se zjistit velikost disku {}", device))?;
+    Ok(WipeTarget {
+        interface: interface.to_string(),
+        in_use: in_use(conn, bay.id)?,
+        bay_name: bay.name,
+        serial: text("serial"),
+        model: text("model"),
+        size_bytes,
+        sector_size: disk["log-sec"].as_u64().unwrap_or(512),
+        sanitize: sanitize_support(&device),
+        device,
+    })
+}
+
+/// Ověří podmínky, odpojí oddíly disku a založí záznam `wipe_job`.
+/// Vrácený `MountHold` brání opětovnému připojení až do konce mazání.
+fn create_job(
+    params: &WipeParams,
+    pattern: &[u8],
+) -> Result<(WipeJob, Option<SanitizeKind>, MountHold), ConfigError> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let bay = bay_by_interface(conn, &params.interface)?;
+    let target = probe(conn, &params.interface)?;
+    if target.in_use {
+        return Err(format!("Šachta '{}' je právě používaná", bay.name).into());
+    }
+    // disk bez sériového čísla se potvrzuje názvem zařízení (sdb, nvme0n1)
+    let expected = target
+        .serial
+        .clone()
+        .unwrap_or_else(|| target.device.trim_start_matches("/dev/").to_string());
+    if !params.confirm_serial.trim().eq_ignore_ascii_case(&expected) {
+        return Err(field_error(
+            "confirm_serial",
+            "Sériové číslo nesouhlasí s diskem v šachtě",
+        ));
+    }
+    let sanitize =
+        match params.method {
+            WipeMethod::Sanitize => Some(target.sanitize.ok_or_else(|| {
+                field_error("method", "Disk nepodporuje interní mazání (sanitize)")
+            })?),
+            _ => None,
+        };
+    // přepis nulami nebo vzorem se vždy ověří čtením celého disku; obsah po
+    // sanitize určuje výrobce (nuly, jedničky, po kryptografickém mazání
+    // náhodná data), vzorem jej ověřit nelze
+    let verify = sanitize.is_none();
+
+    let parts = disk_parts(&target.device)?;
+    // zápis reportu nebo exportu na disk musí doběhnout, nové se nespustí
+    let _unmounting = begin_eject(&part_mounts(&parts))?;
+    let hold = monitor::hold_auto_mount(&target.device);
+    unmount_parts(&parts)?;
+    conn.execute(
+        "INSERT INTO wipe_job (bay_id, interface_path, bay_name, device, serial, model,
+             size_bytes, sector_size, method, sanitize_command, pattern, verify, operator)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            bay.id,
+            params.interface,
+            bay.name,
+            target.device,
+            target.serial,
+            target.model,
+            target.size_bytes as i64,
+            target.sector_size as i64,
+            params.method.as_str(),
+            sanitize.map(|k| k.command(&target.device).join(" ")),
+            hex::encode_upper(pattern),
+            verify,
+            params.operator.trim(),
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let job = load_job(conn, conn.last_insert_rowid())?;
+    write_audit(
+        conn,
+        &format!(
+            "Spuštěno mazání disku {}: šachta '{}', disk {} (SN {}), metoda {}, ověření {}, operátor {}",
+            job.id,
+            job.bay_name,
+            job.device,
+            job.serial.as_deref().unwrap_or("neznámé"),
+            job.method,
+            if job.verify { "ano" } else { "ne" },
+            job.operator
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok((job, sanitize, hold))
+}
+
+enum WipeEvent {
+    Output(String),
+    Progress { perc: u8, eta: u64, speed: f64 },
+}
+
+/// Průběh jedné fáze (zápis / ověření / sanitize) přepočtený na celé mazání.
+struct Progress<'a> {
+    tx: &'a UnboundedSender<WipeEvent>,
+    /// Procenta celého mazání na začátku fáze a podíl fáze
+    base: f64,
+    span: f64,
+    total: u64,
+    /// Bajty, které zbývají zpracovat v dalších fázích (pro odhad času)
+    after: u64,
+    started: Instant,
+    last: Option<Instant>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(
+        tx: &'a UnboundedSender<WipeEvent>,
+        base: f64,
+        span: f64,
+        total: u64,
+        after: u64,
+    ) -> Self {
+        Progress {
+            tx,
+            base,
+            span,
+            total,
+            after,
+            started: Instant::now(),
+            last: None,
+        }
+    }
+
+    fn output(&self, line: String) {
+        let _ = self.tx.send(WipeEvent::Output(line));
+    }
+
+    fn report(&mut self, done: u64) {
+        if self
+            .last
+            .is_some_and(|t| t.elapsed() < Duration::from_secs(1))
+        {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let bytes_per_sec = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let remaining = self.total.saturating_sub(done) + self.after;
+        let eta = if bytes_per_sec > 0.0 {
+            (remaining as f64 / bytes_per_sec) as u64
+        } else {
+            0
+        };
+        let fraction = done as f64 / self.total.max(1) as f64;
+        let _ = self.tx.send(WipeEvent::Progress {
+            perc: (self.base + self.span * fraction).min(100.0) as u8,
+            eta,
+            speed: bytes_per_sec / (1024.0 * 1024.0),
+        });
+    }
+}
+
+fn dd_stderr(child: Child) -> String {
+    child
+        .wait_with_output()
+        .map(|o| String::from_utf8_lossy(&o.stderr).trim().to_string())
+        .unwrap_or_default()
+}
+
+/// Přepíše celý disk vzorem.
+fn write_pattern(
+    device: &str,
+    size: u64,
+    pattern: &[u8],
+    progress: &mut Progress,
+    written: &mut u64,
+) -> Result<(), String> {
+    let mut child = Command::new("sudo")
+        .args(["dd", &format!("of={device}"), "bs=4M", "iflag=fullblock"])
+        .args(["oflag=direct", "conv=fsync", "status=none"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run dd: {}", e))?;
+    let mut stdin = child.stdin.take().ok_or("dd nemá stdin")?;
+    let buffer = pattern_buffer(pattern, CHUNK);
+    while *written < size {
+        let n = (size - *written).min(CHUNK as u64) as usize;
+        if let Err(e) = stdin.write_all(&buffer[..n]) {
+            drop(stdin);
+            return Err(format!(
+                "Zápis selhal na offsetu {}: {} {}",
+                written,
+                e,
+                dd_stderr(child)
+            ));
+        }
+        *written += n as u64;
+        progress.report(*written);
+    }
+    drop(stdin);
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "dd selhal: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Přečte celý disk a porovná jej se vzorem. První neshoda ukončí ověření.
+fn verify_pattern(
+    device: &str,
+    size: u64,
+    pattern: &[u8],
+    progress: &mut Progress,
+    verified: &mut u64,
+    mismatch: &mut Option<u64>,
+) -> Result<(), String> {
+    let mut child = Command::new("sudo")
+        .args([
+            "dd",
+            &format!("if={device}"),
+            "bs=4M",
+            "iflag=direct",
+            "status=none",
+        ])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run dd: {}", e))?;
+    let mut stdout = child.stdout.take().ok_or("dd nemá stdout")?;
+    let expected = pattern_buffer(pattern, CHUNK + pattern.len());
+    let mut buf = vec![0u8; CHUNK];
+    loop {
+        let n = match stdout.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                drop(stdout);
+                return Err(format!(
+                    "Čtení selhalo na offsetu {}: {} {}",
+                    verified,
+                    e,
+                    dd_stderr(child)
+                ));
+            }
+        };
+        let start = (*verified % pattern.len() as u64) as usize;
+        if let Some(i) = buf[..n]
+            .iter()
+            .zip(&expected[start..start + n])
+            .position(|(a, b)| a != b)
+        {
+            let offset = *verified + i as u64;
+            *mismatch = Some(offset);
+            // zavřená roura ukončí dd (SIGPIPE)
+            drop(stdout);
+            let _ = dd_stderr(child);
+            return Err(format!(
+                "Ověření selhalo: bajt na offsetu {} neodpovídá vzoru",
+                offset
+            ));
+        }
+        *verified += n as u64;
+        progress.report(*verified);
+    }
+    drop(stdout);
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "dd selhal: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    if *verified != size {
+        return Err(format!(
+            "Ověření přečetlo jen {} z {} bajtů",
+            verified, size
+        ));
+    }
+    Ok(())
+}
+
+/// Jak dlouho smí NVMe sanitize log po přijetí příkazu hlásit, že žádné
+/// mazání neproběhlo (stav 0), než se mazání považuje za nespuštěné.
+const SANITIZE_START_GRACE: Duration = Duration::from_secs(60);
+
+enum SanitizeState {
+    /// Disk zatím nehlásí žádné mazání (NVMe stav 0)
+    NotStarted,
+    Running(f64),
+    Done,
+    Failed(String),
+}
+
+fn sanitize_state(kind: SanitizeKind, device: &str) -> Result<SanitizeState, String> {
+    if kind.is_nvme() {
+        let out = run_sudo(&["nvme", "sanitize-log", device, "-o", "json"])?;
+        let log: Value = serde_json::from_str(&out).map_err(|e| e.to_string())?;
+        let sstat = json_u64(&log, "sstat").unwrap_or(0);
+        let sprog = json_u64(&log, "sprog").unwrap_or(0);
+        Ok(match sstat & 0x7 {
+            1 | 4 => SanitizeState::Done,
+            2 => SanitizeState::Running(sprog as f64 / 65536.0),
+            3 => SanitizeState::Failed("NVMe sanitize skončil chybou".into()),
+            0 => SanitizeState::NotStarted,
+            other => SanitizeState::Failed(format!("Neznámý stav NVMe sanitize ({other})")),
+        })
+    } else {
+        let out = run_sudo(&["hdparm", "--sanitize-status", device])?;
+        ata_sanitize_state(&out)
+    }
+}
+
+/// Stav ATA sanitize z výpisu `hdparm --sanitize-status`: řádek
+/// `State: SDn ...`, u běžícího mazání `Progress: 0x.... (NN%)` a příznak
+/// `Last Sanitize Operation Completed Without Error`.
+fn ata_sanitize_state(out: &str) -> Result<SanitizeState, String> {
+    let lines: Vec<&str> = out.lines().map(str::trim).collect();
+    let state = lines
+        .iter()
+        .find_map(|l| l.strip_prefix("State:"))
+        .map(str::trim)
+        .ok_or_else(|| format!("hdparm nevrátil stav sanitize: {}", out.trim()))?;
+    let succeeded = lines
+        .iter()
+        .any(|l| l.eq_ignore_ascii_case("Last Sanitize Operation Completed Without Error"));
+    match state.split_whitespace().next().unwrap_or("") {
+        "SD2" => {
+            let perc = lines
+                .iter()
+                .find_map(|l| l.strip_prefix("Progress:"))
+                .and_then(|p| p.split(['(', '%']).nth(1))
+                .and_then(|p| p.trim().parse::<f64>().ok())
+                .unwrap_or(0.0);
+            Ok(SanitizeState::Running(perc / 100.0))
+        }
+        "SD4" => Ok(SanitizeState::Done),
+        // disk se po dokončení vrací do SD0 (idle) s příznakem úspěchu
+        "SD0" | "SD1" if succeeded => Ok(SanitizeState::Done),
+        "SD3" => Ok(SanitizeState::Failed(format!(
+            "ATA sanitize skončil chybou ({})",
+            state
+        ))),
+        _ => Ok(SanitizeState::Failed(format!(
+            "ATA sanitize nebyl dokončen ({})",
+            state
+        ))),
+    }
+}
+
+/// Spustí interní mazání disku a počká na jeho dokončení, nejdéle
+/// [`SanitizeKind::timeout`].
+fn run_sanitize(kind: SanitizeKind, device: &str, progress: &mut Progress) -> Result<(), String> {
+    let command = kind.command(device);
+    progress.output(format!("sudo {}", command.join(" ")));
+    let args: Vec<&str> = command.iter().map(String::as_str).collect();
+    let out = run_sudo(&args)?;
+    out.lines()
+        .filter(|l| !l.trim().is_empty())
+        .for_each(|l| progress.output(l.to_string()));
+    let started = Instant::now();
+    loop {
+        thread::sleep(Duration::from_secs(2));
+        match sanitize_state(kind, device)? {
+            SanitizeState::NotStarted if started.elapsed() > SANITIZE_START_GRACE => {
+                return Err(format!(
+                    "Disk po přijetí příkazu nehlásí žádné interní mazání (po {} s)",
+                    SANITIZE_START_GRACE.as_secs()
+                ));
+            }
+            SanitizeState::NotStarted => {}
+            SanitizeState::Running(fraction) => {
+                progress.report((fraction * progress.total as f64) as u64)
+            }
+            SanitizeState::Done => return Ok(()),
+            SanitizeState::Failed(e) => return Err(e),
+        }
+        if started.elapsed() > kind.timeout() {
+            return Err(format!(
+                "Interní mazání nebylo dokončeno do {} min",
+                kind.timeout().as_secs() / 60
+            ));
+        }
+    }
+}
+
+#[derive(Default)]
+struct WipeOutcome {
+    bytes_written: u64,
+    bytes_verified: u64,
+    mismatch_offset: Option<u64>,
+    error: Option<String>,
+}
+
+/// Vlastní mazání (blokující). Do `outcome` zapisuje i částečný výsledek.
+fn wipe_disk(
+    job: &WipeJob,
+    pattern: &[u8],
+    sanitize: Option<SanitizeKind>,
+    tx: &UnboundedSender<WipeEvent>,
+    outcome: &mut WipeOutcome,
+) -> Result<(), String> {
+    let size = job.size_bytes as u64;
+    let write_span = if job.verify { 50.0 } else { 100.0 };
+    let after = if job.verify { size } else { 0 };
+
+    let mut progress = Progress::new(tx, 0.0, write_span, size, after);
+    match sanitize {
+        Some(kind) => {
+            run_sanitize(kind, &job.device, &mut progress)?;
+            outcome.bytes_written = size;
+        }
+        None => {
+            progress.output(format!(
+                "Přepis {} ({} B) vzorem 0x{}",
+                job.device, size, job.pattern
+            ));
+            write_pattern(
+                &job.device,
+                size,
+                pattern,
+                &mut progress,
+                &mut outcome.bytes_written,
+            )?;
+        }
+    }
+    progress.output(format!("Zapsáno {} B", outcome.bytes_written));
+
+    if job.verify {
+        let mut progress = Progress::new(tx, 50.0, 50.0, size, 0);
+        progress.output(format!("Ověření {} (čtení celého disku)", job.device));
+        verify_pattern(
+            &job.device,
+            size,
+            pattern,
+            &mut progress,
+            &mut outcome.bytes_verified,
+            &mut outcome.mismatch_offset,
+        )?;
+        progress.output(format!("Ověřeno {} B, bez neshody", outcome.bytes_verified));
+    }
+    Ok(())
+}
+
+/// Zapíše výsledek mazání a certifikát do DB.
+fn finish_job(id: i64, outcome: &WipeOutcome, end_datetime: &str) -> Result<WipeJob, String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let status = if outcome.error.is_none() {
+        "done"
+    } else {
+        "error"
+    };
+    conn.execute(
+        "UPDATE wipe_job SET status = ?1, end_datetime = ?2, bytes_written = ?3,
+             bytes_verified = ?4, mismatch_offset = ?5, error = ?6
+         WHERE id = ?7",
+        params![
+            status,
+            end_datetime,
+            outcome.bytes_written as i64,
+            outcome.bytes_verified as i64,
+            outcome.mismatch_offset.map(|o| o as i64),
+            outcome.error,
+            id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    let job = load_job(conn, id)?;
+    // certifikát lze vytvořit i později při exportu
+    if let Err(e) = store_certificate(conn, &job) {
+        log_error(&format!(
+            "Certifikát mazání {} se nepodařilo vytvořit: {}",
+            id, e
+        ));
+    }
+    write_audit(
+        conn,
+        &format!(
+            "Mazání disku {} skončilo ({}): disk {} (SN {}), zapsáno {} B, ověřeno {} B{}",
+            job.id,
+            job.status,
+            job.device,
+            job.serial.as_deref().unwrap_or("neznámé"),
+            job.bytes_written,
+            job.bytes_verified,
+            job.error
+                .as_deref()
+                .map(|e| format!(", chyba: {}", e))
+                .unwrap_or_default()
+        ),
+    )
+    .map_err(|e| e.to_string())?;
+    load_job(conn, id)
+}
+
+/// Vyrenderuje certifikát a uloží jej k záznamu. Vrací PDF.
+fn store_certificate(conn: &Connection, job: &WipeJob) -> Result<Vec<u8>, String> {
+    let pdf = render_wipe_certificate(job)?;
+    conn.execute(
+        "UPDATE wipe_job SET certificate_pdf = ?1, certificate_sha256 = ?2 WHERE id = ?3",
+        params![pdf, sha256_hex(&pdf), job.id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(pdf)
+}
+
+/// Záznam mazání a jeho certifikát; chybějící certifikát (např. selhal
+/// render) se vytvoří znovu.
+pub fn certificate(id: i64) -> Result<(WipeJob, Vec<u8>), String> {
+    let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+    let conn = pooled.connection();
+    let job = load_job(conn, id)?;
+    if job.status == "running" {
+        return Err(format!("Mazání {} ještě běží", id));
+    }
+    let stored: Option<Vec<u8>> = conn
+        .query_row(
+            "SELECT certificate_pdf FROM wipe_job WHERE id = ?1",
+            [id],
+            |r| r.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let pdf = match stored {
+        Some(pdf) => pdf,
+        None => store_certificate(conn, &job)?,
+    };
+    Ok((job, pdf))
+}
+
+#[derive(Serialize)]
+struct WsWipeProcess<'a> {
+    #[serde(rename = "type")]
+    msg_type: &'a str,
+    id: &'a str,
+    start_datetime: String,
+    end_datetime: Option<String>,
+    status: &'a str,
+    triggered_by_ewf: bool,
+    triggered_by_dd: bool,
+    triggered_by_wipe: bool,
+    speed: f64,
+    source_disk: String,
+    destination_disks: Vec<String>,
+    progress_perc: u8,
+    progress_time: u64,
+    out_log: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct WsWipeOutput<'a> {
+    #[serde(rename = "type")]
+    msg_type: &'a str,
+    id: &'a str,
+    output: String,
+}
+
+#[derive(Serialize)]
+struct WsWipeProgress<'a> {
+    #[serde(rename = "type")]
+    msg_type: &'a str,
+    id: &'a str,
+    progress_perc: Option<u8>,
+    progress_time: Option<u64>,
+    speed: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct WsWipeDone<'a> {
+    #[serde(rename = "type")]
+    msg_type: &'a str,
+    id: &'a str,
+    status: &'a str,
+    end_datetime: String,
+}
+
+async fn send<T: Serialize>(msg: &T) {
+    if let Ok(json) = serde_json::to_string(msg) {
+        broadcast_message(&json).await;
+    }
+}
+
+/// Běh mazání na pozadí: blokující práce ve vlákně, průběh do websocketu.
+async fn run_wipe(job: WipeJob, pattern: Vec<u8>, sanitize: Option<SanitizeKind>, hold: MountHold) {
+    let ws_id = format!("wipe-{}", job.id);
+    LED_CONTROLLER.notify_process_start();
+    send(&WsWipeProcess {
+        msg_type: "ProcessFull",
+        id: &ws_id,
+        start_datetime: Utc::now().to_rfc3339(),
+        end_datetime: None,
+        status: "running",
+        triggered_by_ewf: false,
+        triggered_by_dd: false,
+        triggered_by_wipe: true,
+        speed: 0.0,
+        source_disk: String::new(),
+        destination_disks: vec![job.device.clone()],
+        progress_perc: 0,
+        progress_time: 0,
+        out_log: Vec::new(),
+    })
+    .await;
+
+    let (tx, mut rx) = unbounded_channel();
+    let worker_job = job.clone();
+    let worker = tauri::async_runtime::spawn_blocking(move || {
+        let mut outcome = WipeOutcome::default();
+        if let Err(e) = wipe_disk(&worker_job, &pattern, sanitize, &tx, &mut outcome) {
+            let _ = tx.send(WipeEvent::Output(format!("Chyba: {}", e)));
+            outcome.error = Some(e);
+        }
+        outcome
+    });
+    while let Some(event) = rx.recv().await {
+        match event {
+            WipeEvent::Output(output) => {
+                send(&WsWipeOutput {
+                    msg_type: "ProcessOutput",
+                    id: &ws_id,
+                    output,
+                })
+                .await
+            }
+            WipeEvent::Progress { perc, eta, speed } => {
+                send(&WsWipeProgress {
+                    msg_type: "ProcessProgress",
+                    id: &ws_id,
+                    progress_perc: Some(perc),
+                    progress_time: Some(eta),
+                    speed: Some(speed),
+                })
+                .await
+            }
+        }
+    }
+    let outcome = worker.await.unwrap_or_else(|e| WipeOutcome {
+        error: Some(format!("Async error: {}", e)),
+        ..Default::default()
+    });
+    drop(hold);
+    LED_CONTROLLER.notify_process_end();
+
+    let end_datetime = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let status = if outcome.error.is_none() {
+        "done"
+    } else {
+        "error"
+    };
+    let (id, end) = (job.id, end_datetime.clone());
+    match tauri::async_runtime::spawn_blocking(move || finish_job(id, &outcome, &end)).await {
+        Ok(Ok(_)) => {}
+        Ok(Err(e)) => log_error(&format!("Dokončení mazání {} selhalo: {}", job.id, e)),
+        Err(e) => log_error(&format!("Async error: {}", e)),
+    }
+    send(&WsWipeDone {
+        msg_type: "ProcessDone",
+        id: &ws_id,
+        status,
+        end_datetime,
+    })
+    .await;
+    // disk je po mazání bez oddílů, registr musí vidět nový stav
+    monitor::rescan_and_publish().await;
+}
+
+/// Popis disku ve výstupní šachtě pro dialog mazání (sériové číslo,
+/// velikost, podpora interního mazání).
+#[tauri::command(rename_all = "snake_case")]
+pub async fn probe_wipe_target(interface: String) -> Result<WipeTarget, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        probe(pooled.connection(), &interface)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
+
+/// Spustí mazání disku ve výstupní šachtě. Jen pro administrátora; operátor
+/// musí opsat sériové číslo disku. Vrací ID záznamu `wipe_job`, průběh chodí
+/// websocketem pod ID `wipe-<id>`.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn start_wipe(params: WipeParams, admin_code: String) -> Result<i64, ConfigError> {
+    require_admin(&admin_code)?;
+    if params.operator.trim().is_empty() {
+        return Err(field_error("operator", "Zadejte jméno operátora"));
+    }
+    let pattern = parse_pattern(params.method, params.pattern.as_deref())?;
+
+    let job_pattern = pattern.clone();
+    let (job, sanitize, hold) =
+        tauri::async_runtime::spawn_blocking(move || create_job(&params, &job_pattern))
+            .await
+            .map_err(|e| format!("Async error: {}", e))??;
+    let id = job.id;
+    tauri::async_runtime::spawn(run_wipe(job, pattern, sanitize, hold));
+    Ok(id)
+}
+
+/// Historie mazání, nejnovější první.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn list_wipe_jobs() -> Result<Vec<WipeJob>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut pooled = DB_POOL.get_connection().map_err(|e| e.to_string())?;
+        let mut stmt = pooled
+            .connection()
+            .prepare(&format!(
+                "SELECT {JOB_COLUMNS} FROM wipe_job ORDER BY id DESC"
+            ))
+            .map_err(|e| e.to_string())?;
+        let jobs = stmt
+            .query_map([], job_from_row)
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| e.to_string())?;
+        Ok(jobs)
+    })
+    .await
+    .map_err(|e| format!("Async error: {}", e))?
+}
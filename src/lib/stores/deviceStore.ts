@@ -10,6 +10,7 @@ export interface DeviceBase {
 	side: string | null;
 	type: 'usb' | 'sata';
 	mountpoint: string | null;
+	ejected?: boolean; // bezpečně odebraný disk, lze vytáhnout
 }
 
 export interface DeviceStatus {
@@ -25,3 +26,20 @@ export const deviceStore = persisted<DeviceStatus>('deviceStore', {
 	cpu_usage: 0,
 	ram_usage: 0
 });
+
+export interface DeviceEvent {
+	bus: 'usb' | 'sata';
+	device: DeviceBase;
+}
+
+// Zapracuje DeviceAttached / DeviceChanged / DeviceEjected / DeviceDetached z websocketu
+export function applyDeviceEvent(type: string, event: DeviceEvent) {
+	const key = event.bus === 'usb' ? 'usb_devices' : 'sata_devices';
+	deviceStore.update((status) => {
+		const others = status[key].filter((d) => d.interface !== event.device.interface);
+		return {
+			...status,
+			[key]: type === 'DeviceDetached' ? others : [...others, event.device]
+		};
+	});
+}
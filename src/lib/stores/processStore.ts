@@ -6,8 +6,10 @@ export interface Process {
 	start_datetime: string; // Datum a čas spuštění procesu
 	end_datetime: string | null; // Datum a čas ukončení procesu, null pokud stále běží
 	status: string; // Aktuální stav procesu (např. 'běží', 'ukončen', 'chyba')
+	log_error?: string; // Výpis procesu není v databázi celý nebo nebyl zálohovaný
 	triggered_by_ewf: boolean; // Indikuje, zda byl proces spuštěn pomocí EWF
 	triggered_by_dd: boolean; // Indikuje, zda byl proces spuštěn pomocí DD
+	triggered_by_wipe?: boolean; // Mazání cílového disku (ID "wipe-<id>")
 	speed: number; // Rychlost zpracování (např. MB/s)
 	source_disk: DeviceBase; // Zdrojový disk – nemůže být null
 	destination_disks: [DeviceBase, ...DeviceBase[]]; // Pole cílových disků – vždy alespoň jeden